@@ -1,6 +1,7 @@
-use bitcoincore_rpc::bitcoin::secp256k1::Scalar;
+use bitcoincore_rpc::bitcoin::secp256k1::{Scalar, Secp256k1, Verification};
 
-use crate::{secp256k1::SecretKey, Error};
+use crate::{secp256k1::SecretKey, Address, ConversionError, Error, Network, PublicKey, ScriptKind, H256};
+use std::str::FromStr;
 
 pub fn calculate_deposit_secret_key(vault_key: SecretKey, issue_key: SecretKey) -> Result<SecretKey, Error> {
     let mut deposit_key = vault_key;
@@ -8,6 +9,74 @@ pub fn calculate_deposit_secret_key(vault_key: SecretKey, issue_key: SecretKey)
     Ok(deposit_key)
 }
 
+/// The P2WPKH address of the deposit secret key d = v * c, i.e. the address
+/// to which the issue with the secret `issue_key` is paid.
+pub fn derive_deposit_address(vault_key: SecretKey, issue_key: SecretKey, network: Network) -> Result<Address, Error> {
+    let deposit_key = calculate_deposit_secret_key(vault_key, issue_key)?;
+    let deposit_pubkey = PublicKey::new(deposit_key.public_key(&Secp256k1::signing_only()));
+    Ok(Address::p2wpkh(&deposit_pubkey, network).map_err(ConversionError::from)?)
+}
+
+fn deposit_address<C: Verification>(
+    secp: &Secp256k1<C>,
+    vault_pubkey: &PublicKey,
+    id: &H256,
+    network: Network,
+    kind: ScriptKind,
+) -> Result<Address, Error> {
+    // D = V * c
+    let deposit_pubkey = PublicKey {
+        compressed: vault_pubkey.compressed,
+        inner: vault_pubkey
+            .inner
+            .mul_tweak(secp, &Scalar::from(SecretKey::from_slice(id.as_bytes())?))?,
+    };
+    Ok(match kind {
+        ScriptKind::P2pkh => Address::p2pkh(&deposit_pubkey, network),
+        ScriptKind::P2shP2wpkh => Address::p2shwpkh(&deposit_pubkey, network).map_err(ConversionError::from)?,
+        ScriptKind::P2wpkh => Address::p2wpkh(&deposit_pubkey, network).map_err(ConversionError::from)?,
+        ScriptKind::P2tr => Address::p2tr(secp, deposit_pubkey.inner.x_only_public_key().0, None, network),
+    })
+}
+
+/// The deposit address of the issue with the secure `id`, derived from the
+/// public key of the vault.
+pub fn calculate_deposit_address(
+    vault_pubkey: &PublicKey,
+    id: &H256,
+    network: Network,
+    kind: ScriptKind,
+) -> Result<Address, Error> {
+    deposit_address(&Secp256k1::verification_only(), vault_pubkey, id, network, kind)
+}
+
+/// The deposit addresses of many issues, which share a single secp context
+/// instead of creating one per address as `calculate_deposit_address` does.
+pub fn calculate_deposit_addresses(
+    vault_pubkey: &PublicKey,
+    ids: &[H256],
+    network: Network,
+    kind: ScriptKind,
+) -> Result<Vec<Address>, Error> {
+    let secp = Secp256k1::verification_only();
+    ids.iter()
+        .map(|id| deposit_address(&secp, vault_pubkey, id, network, kind))
+        .collect()
+}
+
+/// The network encoded in the prefix or human-readable part of the address
+/// `s`, or `None` if it is not a valid address. Legacy addresses of testnet,
+/// signet and regtest share their prefixes and are reported as testnet, as
+/// are bech32 addresses of signet.
+pub fn detect_network(s: &str) -> Option<Network> {
+    Address::from_str(s).ok().map(|address| address.network)
+}
+
+/// Whether `s` is a valid address of any network.
+pub fn is_valid_address(s: &str) -> bool {
+    detect_network(s).is_some()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,5 +110,77 @@ mod tests {
             deposit_public_key,
             PublicKey::from_secret_key(&secp, &deposit_secret_key)
         );
+
+        // the address of the deposit public key D
+        assert_eq!(
+            derive_deposit_address(vault_secret_key, secret_key, Network::Regtest).unwrap(),
+            Address::p2wpkh(&crate::PublicKey::new(deposit_public_key), Network::Regtest).unwrap()
+        );
+        assert_eq!(
+            derive_deposit_address(vault_secret_key, secret_key, Network::Regtest).unwrap(),
+            calculate_deposit_address(
+                &crate::PublicKey::new(vault_public_key),
+                &secure_id,
+                Network::Regtest,
+                ScriptKind::P2wpkh
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_detect_network() {
+        for (addr, network) in [
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", Network::Bitcoin),
+            ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", Network::Bitcoin),
+            ("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", Network::Testnet),
+            ("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", Network::Testnet),
+            ("bcrt1q6v2c7q7uv8vu6xle2k9ryfj3y3fuuy4rqnl50f", Network::Regtest),
+        ] {
+            assert_eq!(detect_network(addr), Some(network), "{}", addr);
+            assert!(is_valid_address(addr));
+        }
+
+        for addr in ["", "not an address", "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"] {
+            assert_eq!(detect_network(addr), None, "{}", addr);
+            assert!(!is_valid_address(addr));
+        }
+    }
+
+    #[test]
+    fn test_calculate_deposit_addresses() {
+        let secp = Secp256k1::new();
+        let raw_secret_key: [u8; SECRET_KEY_SIZE] = thread_rng().gen();
+        let vault_secret_key = SecretKey::from_slice(&raw_secret_key).unwrap();
+        let vault_public_key = crate::PublicKey::new(PublicKey::from_secret_key(&secp, &vault_secret_key));
+        let ids: Vec<_> = (0..16).map(|_| H256::random()).collect();
+
+        for kind in [
+            ScriptKind::P2pkh,
+            ScriptKind::P2shP2wpkh,
+            ScriptKind::P2wpkh,
+            ScriptKind::P2tr,
+        ] {
+            let addresses = calculate_deposit_addresses(&vault_public_key, &ids, Network::Regtest, kind).unwrap();
+            let expected = ids
+                .iter()
+                .map(|id| calculate_deposit_address(&vault_public_key, id, Network::Regtest, kind).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(addresses, expected);
+        }
+
+        // the address is that of the deposit secret key d = v * c
+        let deposit_secret_key =
+            calculate_deposit_secret_key(vault_secret_key, SecretKey::from_slice(ids[0].as_bytes()).unwrap()).unwrap();
+        let deposit_public_key = crate::PublicKey::new(PublicKey::from_secret_key(&secp, &deposit_secret_key));
+        assert_eq!(
+            calculate_deposit_addresses(&vault_public_key, &ids, Network::Regtest, ScriptKind::P2wpkh).unwrap()[0],
+            Address::p2wpkh(&deposit_public_key, Network::Regtest).unwrap()
+        );
+        assert!(
+            calculate_deposit_addresses(&vault_public_key, &[], Network::Regtest, ScriptKind::P2wpkh)
+                .unwrap()
+                .is_empty()
+        );
     }
 }
@@ -0,0 +1,74 @@
+/// Number of confirmations required for a deposit depending on its value,
+/// so that small amounts can be accepted faster than large ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationPolicy {
+    /// Confirmations required for amounts below the first band.
+    min_confirmations: u32,
+    /// Minimum amount (in satoshis) of each band and its confirmations,
+    /// sorted by amount.
+    bands: Vec<(u128, u32)>,
+}
+
+impl ConfirmationPolicy {
+    /// Create a policy where each band applies from its (inclusive) minimum amount.
+    ///
+    /// # Arguments
+    /// * `min_confirmations` - confirmations required for any amount
+    /// * `bands` - minimum amounts and the confirmations they require
+    pub fn new(min_confirmations: u32, bands: impl IntoIterator<Item = (u128, u32)>) -> Self {
+        let mut bands: Vec<_> = bands.into_iter().collect();
+        bands.sort_unstable();
+        Self {
+            min_confirmations,
+            bands,
+        }
+    }
+}
+
+/// Returns the confirmations required for the `amount` (in satoshis).
+///
+/// This never decreases for larger amounts: if the bands are not ordered
+/// by their confirmations, the highest count of any band that applies is used.
+pub fn required_confirmations(amount: u128, policy: &ConfirmationPolicy) -> u32 {
+    policy
+        .bands
+        .iter()
+        .take_while(|(min_amount, _)| amount >= *min_amount)
+        .map(|(_, confirmations)| *confirmations)
+        .fold(policy.min_confirmations, u32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTC: u128 = 100_000_000;
+
+    #[test]
+    fn test_required_confirmations() {
+        let policy = ConfirmationPolicy::new(1, vec![(10 * BTC, 6), (BTC / 10, 3)]);
+
+        assert_eq!(required_confirmations(0, &policy), 1);
+        assert_eq!(required_confirmations(BTC / 10 - 1, &policy), 1);
+        assert_eq!(required_confirmations(BTC / 10, &policy), 3);
+        assert_eq!(required_confirmations(BTC, &policy), 3);
+        assert_eq!(required_confirmations(10 * BTC - 1, &policy), 3);
+        assert_eq!(required_confirmations(10 * BTC, &policy), 6);
+        assert_eq!(required_confirmations(u128::MAX, &policy), 6);
+    }
+
+    #[test]
+    fn test_required_confirmations_without_bands() {
+        let policy = ConfirmationPolicy::new(2, vec![]);
+        assert_eq!(required_confirmations(0, &policy), 2);
+        assert_eq!(required_confirmations(u128::MAX, &policy), 2);
+    }
+
+    #[test]
+    fn test_required_confirmations_never_decrease() {
+        let policy = ConfirmationPolicy::new(3, vec![(BTC, 1), (10 * BTC, 6), (100 * BTC, 4)]);
+        assert_eq!(required_confirmations(BTC, &policy), 3);
+        assert_eq!(required_confirmations(10 * BTC, &policy), 6);
+        assert_eq!(required_confirmations(100 * BTC, &policy), 6);
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{BitcoinError, BitcoinLightError, ElectrsError};
 use bitcoincore_rpc::{
     bitcoin::{
+        bech32::Error as Bech32Error,
         consensus::encode::Error as BitcoinEncodeError,
         hashes::{hex::Error as HashHexError, Error as HashesError},
         secp256k1::Error as Secp256k1Error,
@@ -74,6 +75,10 @@ pub enum Error {
     MissingBitcoinFeeInfo,
     #[error("FailedToConstructWalletName")]
     FailedToConstructWalletName,
+    #[error("Coinbase does not contain a witness commitment")]
+    MissingWitnessCommitment,
+    #[error("Witness commitment does not match the witness merkle root")]
+    InvalidWitnessCommitment,
 }
 
 impl Error {
@@ -136,14 +141,23 @@ pub enum ConversionError {
     FromHexError(#[from] FromHexError),
     #[error("AddressError: {0}")]
     AddressError(#[from] AddressError),
+    #[error("Failed to parse address: {0}")]
+    AddressParse(String),
     #[error("HashesError: {0}")]
     HashesError(#[from] HashesError),
     #[error("HashHexError: {0}")]
     HashHexError(#[from] HashHexError),
+    #[error("Bech32Error: {0}")]
+    Bech32Error(#[from] Bech32Error),
+    #[error("Invalid human-readable part: expected {expected}, got {found}")]
+    InvalidHrp { expected: String, found: String },
     #[error("Invalid format")]
     InvalidFormat,
     #[error("Invalid payload")]
     InvalidPayload,
+    /// BIP-350: witness version 0 must use bech32, later versions bech32m.
+    #[error("Witness version {0} must be encoded with {}", if *.0 == 0 { "bech32" } else { "bech32m" })]
+    WrongChecksumVariant(u8),
     #[error("Could not convert block hash")]
     BlockHashError,
 }
@@ -0,0 +1,47 @@
+use crate::{ConversionError, Error, H256};
+
+/// Encode the `hash` as hex in reversed byte order, as shown by
+/// bitcoin core and block explorers for txids and block hashes.
+pub fn hash_to_display_hex(hash: &H256) -> String {
+    let mut bytes = hash.to_fixed_bytes();
+    bytes.reverse();
+    hex::encode(bytes)
+}
+
+/// Decode a hash from hex in reversed (display) byte order.
+pub fn hash_from_display_hex(value: &str) -> Result<H256, Error> {
+    let mut bytes = [0u8; 32];
+    hex::decode_to_slice(value, &mut bytes).map_err(ConversionError::from)?;
+    bytes.reverse();
+    Ok(H256(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromHex, Hash, Txid};
+
+    // coinbase of the genesis block
+    const GENESIS_TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    #[test]
+    fn should_round_trip_display_hex() {
+        let hash = hash_from_display_hex(GENESIS_TXID).unwrap();
+        assert_eq!(hash.as_bytes()[0], 0x3b);
+        assert_eq!(hash.as_bytes()[31], 0x4a);
+        assert_eq!(hash, H256(Txid::from_hex(GENESIS_TXID).unwrap().into_inner()));
+        assert_eq!(hash_to_display_hex(&hash), GENESIS_TXID);
+    }
+
+    #[test]
+    fn should_reject_invalid_display_hex() {
+        assert!(matches!(
+            hash_from_display_hex(&GENESIS_TXID[2..]),
+            Err(Error::ConversionError(ConversionError::FromHexError(_)))
+        ));
+        assert!(matches!(
+            hash_from_display_hex(&GENESIS_TXID.replace('a', "x")),
+            Err(Error::ConversionError(ConversionError::FromHexError(_)))
+        ));
+    }
+}
@@ -7,28 +7,43 @@ pub mod light;
 pub use light::{BitcoinLight, Error as BitcoinLightError};
 
 mod addr;
+mod confirmations;
 mod electrs;
 mod error;
+mod hash;
 mod iter;
+mod payload;
+mod script;
+mod vsize;
+mod witness;
 
+pub use addr::{
+    calculate_deposit_address, calculate_deposit_addresses, derive_deposit_address, detect_network, is_valid_address,
+};
 use async_trait::async_trait;
 use backoff::{backoff::Backoff, future::retry, ExponentialBackoff};
 use bitcoincore_rpc::{bitcoin::consensus::encode::serialize_hex, bitcoincore_rpc_json::ScanningDetails};
 pub use bitcoincore_rpc::{
     bitcoin::{
-        blockdata::{opcodes::all as opcodes, script::Builder},
+        bech32,
+        blockdata::{constants::genesis_block, opcodes::all as opcodes, script::Builder},
         consensus,
         consensus::encode::{deserialize, serialize},
-        hash_types::BlockHash,
+        hash_types::{BlockHash, WitnessMerkleNode},
         hashes::{
             self,
             hex::{FromHex, ToHex},
-            sha256, Hash,
+            sha256, sha256d, Hash,
         },
         secp256k1,
         secp256k1::{constants::PUBLIC_KEY_SIZE, SecretKey},
         util::{
-            self, address::Payload, key, merkleblock::PartialMerkleTree, psbt, psbt::serialize::Serialize,
+            self,
+            address::{Payload, WitnessVersion},
+            key,
+            merkleblock::PartialMerkleTree,
+            psbt,
+            psbt::serialize::Serialize,
             uint::Uint256,
         },
         Address, Amount, Block, BlockHeader, Network, OutPoint, PrivateKey, PubkeyHash, PublicKey, Script, ScriptHash,
@@ -42,10 +57,14 @@ pub use bitcoincore_rpc::{
     jsonrpc::{self, error::RpcError, Error as JsonRpcError},
     Auth, Client, Error as BitcoinError, RpcApi,
 };
+pub use confirmations::{required_confirmations, ConfirmationPolicy};
 pub use electrs::{ElectrsClient, Error as ElectrsError};
 pub use error::{BitcoinRpcError, ConversionError, Error};
+pub use hash::{hash_from_display_hex, hash_to_display_hex};
 pub use iter::{reverse_stream_transactions, stream_blocks, stream_in_chain_transactions};
 use log::{info, trace, warn};
+pub use payload::{supported_payload_types, DeploymentFeatures, NestedSegwit, PayloadExt, PayloadType};
+pub use script::extract_op_return;
 use serde_json::error::Category as SerdeJsonCategory;
 pub use sp_core::H256;
 use std::{
@@ -58,6 +77,8 @@ use tokio::{
     sync::{Mutex, OwnedMutexGuard},
     time::{sleep, timeout},
 };
+pub use vsize::{estimate_vsize, ScriptKind};
+pub use witness::verify_witness_commitment;
 
 #[macro_use]
 extern crate num_derive;
@@ -1077,6 +1098,7 @@ impl BitcoinCoreApi for BitcoinCore {
 pub trait TransactionExt {
     fn get_op_return(&self) -> Option<H256>;
     fn get_op_return_bytes(&self) -> Option<[u8; 34]>;
+    fn extract_op_return_id(&self) -> Option<H256>;
     fn get_payment_amount_to(&self, dest: Payload) -> Option<u64>;
     fn extract_output_addresses(&self) -> Vec<Payload>;
     fn extract_indexed_output_addresses(&self) -> Vec<(usize, Payload)>;
@@ -1103,6 +1125,28 @@ impl TransactionExt for Transaction {
         })
     }
 
+    /// Extract the id from the first OP_RETURN output which pushes exactly 32 bytes,
+    /// among the same outputs as `get_op_return`. Malformed or oversized OP_RETURN
+    /// outputs are ignored.
+    fn extract_op_return_id(&self) -> Option<H256> {
+        // the parachain only checks the first 3 positions
+        self.output
+            .iter()
+            .take(3)
+            .filter(|tx_out| tx_out.script_pubkey.is_op_return())
+            .find_map(|tx_out| match extract_op_return(&tx_out.script_pubkey) {
+                Some(id) if id.len() == 32 => Some(H256::from_slice(&id)),
+                _ => {
+                    warn!(
+                        "Ignoring malformed OP_RETURN in {}: {}",
+                        self.txid(),
+                        tx_out.script_pubkey.as_bytes().to_hex()
+                    );
+                    None
+                }
+            })
+    }
+
     /// Get the amount of btc that self sent to `dest`, if any
     fn get_payment_amount_to(&self, dest: Payload) -> Option<u64> {
         self.output.iter().find_map(|uxto| {
@@ -1153,7 +1197,11 @@ impl TransactionExt for Transaction {
 
 #[cfg(test)]
 mod tests {
-    use bitcoincore_rpc::bitcoin::hashes::{hex::FromHex, sha256::Hash as Sha256Hash, Hash};
+    use super::*;
+    use bitcoincore_rpc::bitcoin::{
+        hashes::{hex::FromHex, sha256::Hash as Sha256Hash, Hash},
+        PackedLockTime,
+    };
 
     #[test]
     fn test_op_return_hashing() {
@@ -1165,4 +1213,45 @@ mod tests {
 
         assert_eq!(expected, script_hash);
     }
+
+    #[test]
+    fn test_extract_op_return_id() {
+        let output = |script: Vec<u8>| TxOut {
+            value: 0,
+            script_pubkey: Script::from(script),
+        };
+        let transaction = |output: Vec<TxOut>| Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![],
+            output,
+        };
+        let id = H256::repeat_byte(0x42);
+        let op_return = |data: &[u8]| [&[0x6a, data.len() as u8][..], data].concat();
+
+        // only the first three outputs are checked, like the parachain does
+        let payment = output(Builder::new().push_int(1).into_script().to_bytes());
+        let valid = transaction(vec![payment.clone(), payment.clone(), output(op_return(id.as_bytes()))]);
+        assert_eq!(valid.extract_op_return_id(), Some(id));
+        let late = transaction(vec![
+            payment.clone(),
+            payment.clone(),
+            payment,
+            output(op_return(id.as_bytes())),
+        ]);
+        assert_eq!(late.extract_op_return_id(), None);
+
+        // garbage is ignored, the valid id is still found
+        let garbage = output(op_return(b"garbage"));
+        let oversized = output(op_return(&[0x42; 40]));
+        let mixed = transaction(vec![
+            garbage.clone(),
+            oversized.clone(),
+            output(op_return(id.as_bytes())),
+        ]);
+        assert_eq!(mixed.extract_op_return_id(), Some(id));
+
+        assert_eq!(transaction(vec![garbage, oversized]).extract_op_return_id(), None);
+        assert_eq!(transaction(vec![]).extract_op_return_id(), None);
+    }
 }
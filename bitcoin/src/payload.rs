@@ -0,0 +1,203 @@
+use crate::{secp256k1, Address, Hash, Network, Payload, PubkeyHash, Script, WPubkeyHash, WitnessVersion};
+
+const TAPROOT_PROGRAM_SIZE: usize = 32;
+
+/// Standard output types that can be encoded as an address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadType {
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+}
+
+impl PayloadType {
+    /// Returns `None` for non-standard witness programs.
+    pub fn of(payload: &Payload) -> Option<Self> {
+        match payload {
+            Payload::PubkeyHash(_) => Some(Self::P2pkh),
+            Payload::ScriptHash(_) => Some(Self::P2sh),
+            Payload::WitnessProgram {
+                version: WitnessVersion::V0,
+                program,
+            } => match program.len() {
+                20 => Some(Self::P2wpkh),
+                32 => Some(Self::P2wsh),
+                _ => None,
+            },
+            Payload::WitnessProgram {
+                version: WitnessVersion::V1,
+                program,
+            } if program.len() == TAPROOT_PROGRAM_SIZE => Some(Self::P2tr),
+            Payload::WitnessProgram { .. } => None,
+        }
+    }
+}
+
+/// Soft forks which may not be enabled on every deployment.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeploymentFeatures {
+    pub taproot: bool,
+}
+
+/// The payload types that may be used on the `network`.
+pub fn supported_payload_types(network: Network, features: DeploymentFeatures) -> Vec<PayloadType> {
+    let mut payload_types = match network {
+        // all networks currently share the same legacy and segwit v0 types
+        Network::Bitcoin | Network::Testnet | Network::Signet | Network::Regtest => vec![
+            PayloadType::P2pkh,
+            PayloadType::P2sh,
+            PayloadType::P2wpkh,
+            PayloadType::P2wsh,
+        ],
+    };
+    if features.taproot {
+        payload_types.push(PayloadType::P2tr);
+    }
+    payload_types
+}
+
+pub trait PayloadExt {
+    /// Check that the payload can be used on the `network` before encoding it.
+    fn is_supported_on(&self, network: Network, features: DeploymentFeatures) -> bool;
+
+    /// P2WPKH payload of the compressed `pubkey`.
+    fn p2wpkh_from_pubkey(pubkey: &secp256k1::PublicKey) -> Self;
+
+    /// P2PKH payload of the compressed `pubkey`.
+    fn p2pkh_from_pubkey(pubkey: &secp256k1::PublicKey) -> Self;
+}
+
+impl PayloadExt for Payload {
+    fn p2wpkh_from_pubkey(pubkey: &secp256k1::PublicKey) -> Self {
+        Payload::WitnessProgram {
+            version: WitnessVersion::V0,
+            program: WPubkeyHash::hash(&pubkey.serialize()).to_vec(),
+        }
+    }
+
+    fn p2pkh_from_pubkey(pubkey: &secp256k1::PublicKey) -> Self {
+        Payload::PubkeyHash(PubkeyHash::hash(&pubkey.serialize()))
+    }
+
+    fn is_supported_on(&self, network: Network, features: DeploymentFeatures) -> bool {
+        PayloadType::of(self).map_or(false, |payload_type| {
+            supported_payload_types(network, features).contains(&payload_type)
+        })
+    }
+}
+
+/// P2WPKH nested in P2SH (P2SH-P2WPKH), for wallets which can only send to
+/// legacy addresses. Inputs spending it are `ScriptKind::P2shP2wpkh`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestedSegwit {
+    /// The outer P2SH address to receive to.
+    pub address: Address,
+    /// The inner P2WPKH witness program, which is revealed when spending.
+    pub witness_program: Payload,
+}
+
+impl NestedSegwit {
+    pub fn from_pubkey(pubkey: &secp256k1::PublicKey, network: Network) -> Self {
+        let witness_program = Payload::p2wpkh_from_pubkey(pubkey);
+        Self {
+            address: Address {
+                payload: Payload::ScriptHash(witness_program.script_pubkey().script_hash()),
+                network,
+            },
+            witness_program,
+        }
+    }
+
+    /// The redeem script of the outer address, i.e. `OP_0 <20-byte-hash>`.
+    pub fn redeem_script(&self) -> Script {
+        self.witness_program.script_pubkey()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Address, PublicKey, Script};
+    use secp256k1::{Secp256k1, SecretKey};
+
+    fn taproot_payload() -> Payload {
+        Payload::WitnessProgram {
+            version: WitnessVersion::V1,
+            program: vec![1; TAPROOT_PROGRAM_SIZE],
+        }
+    }
+
+    #[test]
+    fn should_only_support_taproot_if_enabled() {
+        let disabled = DeploymentFeatures { taproot: false };
+        let enabled = DeploymentFeatures { taproot: true };
+        for network in [Network::Bitcoin, Network::Testnet, Network::Regtest] {
+            assert!(!taproot_payload().is_supported_on(network, disabled));
+            assert!(taproot_payload().is_supported_on(network, enabled));
+        }
+    }
+
+    #[test]
+    fn should_support_legacy_and_segwit_payloads() {
+        let payload = Payload::PubkeyHash(PubkeyHash::all_zeros());
+        assert!(payload.is_supported_on(Network::Bitcoin, DeploymentFeatures::default()));
+
+        let payload = Payload::WitnessProgram {
+            version: WitnessVersion::V0,
+            program: vec![1; 20],
+        };
+        assert!(payload.is_supported_on(Network::Bitcoin, DeploymentFeatures::default()));
+    }
+
+    #[test]
+    fn should_reject_non_standard_witness_programs() {
+        let payload = Payload::WitnessProgram {
+            version: WitnessVersion::V2,
+            program: vec![1; 32],
+        };
+        assert_eq!(PayloadType::of(&payload), None);
+        assert!(!payload.is_supported_on(Network::Bitcoin, DeploymentFeatures { taproot: true }));
+    }
+
+    #[test]
+    fn should_derive_payloads_from_pubkey() {
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+        let public_key = PublicKey::new(pubkey);
+
+        let payload = Payload::p2wpkh_from_pubkey(&pubkey);
+        assert_eq!(payload, Address::p2wpkh(&public_key, Network::Bitcoin).unwrap().payload);
+        assert_eq!(
+            payload.script_pubkey(),
+            Script::new_v0_p2wpkh(&public_key.wpubkey_hash().unwrap())
+        );
+
+        let payload = Payload::p2pkh_from_pubkey(&pubkey);
+        assert_eq!(payload, Address::p2pkh(&public_key, Network::Bitcoin).payload);
+        assert_eq!(payload.script_pubkey(), Script::new_p2pkh(&public_key.pubkey_hash()));
+    }
+
+    #[test]
+    fn should_derive_nested_segwit_from_pubkey() {
+        let secret_key = SecretKey::from_slice(&[1; 32]).unwrap();
+        let pubkey = secp256k1::PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+        let public_key = PublicKey::new(pubkey);
+
+        let nested = NestedSegwit::from_pubkey(&pubkey, Network::Bitcoin);
+        assert_eq!(
+            nested.address,
+            Address::p2shwpkh(&public_key, Network::Bitcoin).unwrap()
+        );
+        assert_eq!(PayloadType::of(&nested.address.payload), Some(PayloadType::P2sh));
+        assert_eq!(nested.address.to_string(), "35LM1A29K95ADiQ8rJ9uEfVZCKffZE4D9i");
+
+        assert_eq!(PayloadType::of(&nested.witness_program), Some(PayloadType::P2wpkh));
+        assert_eq!(nested.witness_program, Payload::p2wpkh_from_pubkey(&pubkey));
+        assert_eq!(
+            nested.redeem_script(),
+            Script::new_v0_p2wpkh(&public_key.wpubkey_hash().unwrap())
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{opcodes, Script};
+use bitcoincore_rpc::bitcoin::blockdata::script::Instruction;
+
+/// The data pushed by an `OP_RETURN` script, which must consist of the
+/// `OP_RETURN` and a single push. The data can be empty or larger than
+/// the 80 bytes which are relayed by default.
+pub fn extract_op_return(script: &Script) -> Option<Vec<u8>> {
+    let mut instructions = script.instructions();
+    match (instructions.next(), instructions.next(), instructions.next()) {
+        (Some(Ok(Instruction::Op(opcodes::OP_RETURN))), Some(Ok(Instruction::PushBytes(data))), None) => {
+            Some(data.to_vec())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Builder;
+
+    fn op_return(pushes: &[&[u8]]) -> Script {
+        pushes
+            .iter()
+            .fold(Builder::new().push_opcode(opcodes::OP_RETURN), |builder, data| {
+                builder.push_slice(data)
+            })
+            .into_script()
+    }
+
+    #[test]
+    fn test_extract_op_return() {
+        assert_eq!(extract_op_return(&op_return(&[&[1; 32]])), Some(vec![1; 32]));
+        assert_eq!(extract_op_return(&op_return(&[&[]])), Some(vec![]));
+        // larger than the default relay limit, pushed with OP_PUSHDATA1
+        assert_eq!(extract_op_return(&op_return(&[&[2; 100]])), Some(vec![2; 100]));
+    }
+
+    #[test]
+    fn test_extract_op_return_rejects_other_scripts() {
+        assert_eq!(extract_op_return(&op_return(&[])), None);
+        assert_eq!(extract_op_return(&op_return(&[&[1; 32], &[2; 4]])), None);
+        // push without OP_RETURN
+        assert_eq!(
+            extract_op_return(&Builder::new().push_slice(&[1; 32]).into_script()),
+            None
+        );
+        // push which is truncated
+        assert_eq!(extract_op_return(&Script::from(vec![0x6a, 32, 1, 2])), None);
+    }
+}
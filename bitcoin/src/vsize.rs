@@ -0,0 +1,140 @@
+use crate::PayloadType;
+use bitcoincore_rpc::bitcoin::blockdata::constants::WITNESS_SCALE_FACTOR;
+
+/// Version and lock time
+const TX_FIXED_SIZE: usize = 4 + 4;
+/// Segwit marker and flag, only counted once in weight units
+const SEGWIT_MARKER_WEIGHT: usize = 2;
+/// Outpoint and sequence
+const TX_IN_FIXED_SIZE: usize = 36 + 4;
+/// Value of the output
+const TX_OUT_FIXED_SIZE: usize = 8;
+
+/// Inputs whose size can be estimated from their type, P2SH and P2WSH
+/// inputs depend on the redeem / witness script so are not supported.
+///
+/// ECDSA signatures are assumed to be 72 bytes including the sighash type,
+/// so estimates may be slightly larger than the actual size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptKind {
+    P2pkh,
+    /// P2WPKH nested in P2SH
+    P2shP2wpkh,
+    P2wpkh,
+    /// Taproot key path spend
+    P2tr,
+}
+
+impl ScriptKind {
+    /// Size of the script sig, including its length prefix
+    fn script_sig_size(&self) -> usize {
+        match self {
+            // <sig> <pubkey>
+            Self::P2pkh => 1 + (1 + 72) + (1 + 33),
+            // <0 <20-byte-hash>>
+            Self::P2shP2wpkh => 1 + 23,
+            Self::P2wpkh | Self::P2tr => 1,
+        }
+    }
+
+    /// Size of the witness, including the number of items
+    fn witness_size(&self) -> usize {
+        match self {
+            Self::P2pkh => 0,
+            // <sig> <pubkey>
+            Self::P2shP2wpkh | Self::P2wpkh => 1 + (1 + 72) + (1 + 33),
+            // <schnorr sig> with the default sighash type
+            Self::P2tr => 1 + (1 + 64),
+        }
+    }
+}
+
+impl PayloadType {
+    /// Size of the output script, including its length prefix
+    pub fn output_script_size(&self) -> usize {
+        1 + match self {
+            // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+            Self::P2pkh => 25,
+            // OP_HASH160 <20 bytes> OP_EQUAL
+            Self::P2sh => 23,
+            // OP_0 <20 bytes>
+            Self::P2wpkh => 22,
+            // OP_0 <32 bytes> or OP_1 <32 bytes>
+            Self::P2wsh | Self::P2tr => 34,
+        }
+    }
+}
+
+fn var_int_size(value: usize) -> usize {
+    match value {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        0x10000..=0xffffffff => 5,
+        _ => 9,
+    }
+}
+
+/// Estimate the virtual size of a transaction spending the `input_kinds` to
+/// outputs of the `output_types`, e.g. from `PayloadType::of` for an address.
+pub fn estimate_vsize(input_kinds: &[ScriptKind], output_types: &[PayloadType]) -> usize {
+    let base_size = TX_FIXED_SIZE
+        + var_int_size(input_kinds.len())
+        + input_kinds
+            .iter()
+            .map(|kind| TX_IN_FIXED_SIZE + kind.script_sig_size())
+            .sum::<usize>()
+        + var_int_size(output_types.len())
+        + output_types
+            .iter()
+            .map(|output_type| TX_OUT_FIXED_SIZE + output_type.output_script_size())
+            .sum::<usize>();
+
+    let witness_weight = if input_kinds.iter().any(|kind| kind.witness_size() > 0) {
+        // inputs without a witness still need an empty witness
+        SEGWIT_MARKER_WEIGHT + input_kinds.iter().map(|kind| kind.witness_size().max(1)).sum::<usize>()
+    } else {
+        0
+    };
+
+    let weight = base_size * WITNESS_SCALE_FACTOR + witness_weight;
+    weight.div_ceil(WITNESS_SCALE_FACTOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_vsize_p2wpkh() {
+        // 109.5 vbytes with a 72 byte signature
+        assert_eq!(estimate_vsize(&[ScriptKind::P2wpkh], &[PayloadType::P2wpkh]), 110);
+        // with change
+        assert_eq!(
+            estimate_vsize(&[ScriptKind::P2wpkh], &[PayloadType::P2wpkh, PayloadType::P2wpkh]),
+            141
+        );
+    }
+
+    #[test]
+    fn test_estimate_vsize_p2pkh() {
+        assert_eq!(estimate_vsize(&[ScriptKind::P2pkh], &[PayloadType::P2pkh]), 192);
+    }
+
+    #[test]
+    fn test_estimate_vsize_mixed() {
+        // 10.5 (overhead incl. segwit marker) + 148.25 (P2PKH with empty witness)
+        // + 68 (P2WPKH) + 91 (P2SH-P2WPKH) + 57.5 (P2TR) + 34 + 32 + 43 outputs
+        assert_eq!(
+            estimate_vsize(
+                &[
+                    ScriptKind::P2pkh,
+                    ScriptKind::P2wpkh,
+                    ScriptKind::P2shP2wpkh,
+                    ScriptKind::P2tr
+                ],
+                &[PayloadType::P2pkh, PayloadType::P2sh, PayloadType::P2tr]
+            ),
+            485
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{sha256d, Error, Hash, Transaction, WitnessMerkleNode};
+
+/// OP_RETURN, push 36 bytes, followed by the commitment header 0xaa21a9ed.
+const WITNESS_COMMITMENT_HEADER: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+const WITNESS_COMMITMENT_SIZE: usize = 32;
+
+/// Verify that the coinbase commits to the witness merkle root as defined in BIP-141,
+/// i.e. the commitment is the double-SHA256 of the root and the witness reserved value.
+///
+/// # Arguments
+/// * `coinbase` - the coinbase transaction of the block
+/// * `witness_merkle_root` - the merkle root of the wtxids in the block
+pub fn verify_witness_commitment(coinbase: &Transaction, witness_merkle_root: &WitnessMerkleNode) -> Result<(), Error> {
+    // if multiple outputs match, the one with the highest index is the commitment
+    let commitment = coinbase
+        .output
+        .iter()
+        .rev()
+        .find_map(|output| {
+            let script = output.script_pubkey.as_bytes();
+            if script.len() >= WITNESS_COMMITMENT_HEADER.len() + WITNESS_COMMITMENT_SIZE
+                && script.starts_with(&WITNESS_COMMITMENT_HEADER)
+            {
+                Some(&script[WITNESS_COMMITMENT_HEADER.len()..][..WITNESS_COMMITMENT_SIZE])
+            } else {
+                None
+            }
+        })
+        .ok_or(Error::MissingWitnessCommitment)?;
+
+    // the coinbase witness must consist of a single 32-byte reserved value
+    let reserved_value = match coinbase.input.first().map(|input| input.witness.to_vec()).as_deref() {
+        Some([reserved_value]) if reserved_value.len() == 32 => reserved_value.clone(),
+        _ => return Err(Error::MissingWitnessCommitment),
+    };
+
+    let mut data = witness_merkle_root.into_inner().to_vec();
+    data.extend(reserved_value);
+    if sha256d::Hash::hash(&data).into_inner() == commitment {
+        Ok(())
+    } else {
+        Err(Error::InvalidWitnessCommitment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoincore_rpc::bitcoin::{
+        hashes::hex::FromHex, Block, OutPoint, PackedLockTime, Script, Sequence, TxIn, TxOut, Witness,
+        WitnessCommitment,
+    };
+
+    fn coinbase_with_commitment(commitment: WitnessCommitment, reserved_value: [u8; 32]) -> Transaction {
+        let mut script_pubkey = WITNESS_COMMITMENT_HEADER.to_vec();
+        script_pubkey.extend(commitment.into_inner());
+        Transaction {
+            version: 1,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::null(),
+                script_sig: Script::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::from_vec(vec![reserved_value.to_vec()]),
+            }],
+            output: vec![
+                TxOut {
+                    value: 625_000_000,
+                    script_pubkey: Script::new(),
+                },
+                TxOut {
+                    value: 0,
+                    script_pubkey: Script::from(script_pubkey),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_verify_witness_commitment() {
+        // mainnet blocks whose only transaction is the coinbase: the wtxid of the
+        // coinbase is zero, so is the witness root, and the reserved value is zero
+        let witness_merkle_root = WitnessMerkleNode::from_inner([0u8; 32]);
+        let commitment = WitnessCommitment::from_slice(
+            &Vec::from_hex("e2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9").unwrap(),
+        )
+        .unwrap();
+        let coinbase = coinbase_with_commitment(commitment, [0u8; 32]);
+        assert_eq!(
+            coinbase.output[1].script_pubkey.as_bytes(),
+            &Vec::from_hex("6a24aa21a9ede2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9").unwrap()[..]
+        );
+
+        assert!(verify_witness_commitment(&coinbase, &witness_merkle_root).is_ok());
+
+        let tampered_root = WitnessMerkleNode::hash(b"tampered merkle root");
+        assert!(matches!(
+            verify_witness_commitment(&coinbase, &tampered_root),
+            Err(Error::InvalidWitnessCommitment)
+        ));
+    }
+
+    #[test]
+    fn test_verify_witness_commitment_without_commitment() {
+        let witness_merkle_root = WitnessMerkleNode::hash(b"witness merkle root");
+        let mut coinbase = coinbase_with_commitment(
+            Block::compute_witness_commitment(&witness_merkle_root, &[0u8; 32]),
+            [0u8; 32],
+        );
+        coinbase.output.pop();
+
+        assert!(matches!(
+            verify_witness_commitment(&coinbase, &witness_merkle_root),
+            Err(Error::MissingWitnessCommitment)
+        ));
+    }
+}
@@ -0,0 +1,74 @@
+use crate::config::PriceRoutes;
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, RwLock},
+};
+use warp::{Filter, Rejection, Reply};
+
+/// Read-only view of the active config for tooling, the routes are
+/// replaced whenever the config is (re)loaded.
+#[derive(Clone, Default)]
+pub struct RoutesApi {
+    routes: Arc<RwLock<Vec<PriceRoutes>>>,
+}
+
+impl RoutesApi {
+    pub fn set_routes(&self, routes: Vec<PriceRoutes>) {
+        *self.routes.write().unwrap_or_else(|err| err.into_inner()) = routes;
+    }
+
+    fn filter(&self) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+        let routes = self.routes.clone();
+        warp::get()
+            .and(warp::path("routes"))
+            .and(warp::path::end())
+            .map(move || {
+                let routes = routes.read().unwrap_or_else(|err| err.into_inner());
+                warp::reply::json(&*routes)
+            })
+    }
+
+    /// Serve the routes at `/routes` on the given port until the process exits.
+    pub fn serve(&self, port: u16) {
+        let filter = self.filter();
+        log::info!("Serving routes at http://{}:{}/routes", Ipv4Addr::LOCALHOST, port);
+        tokio::task::spawn(async move {
+            warp::serve(filter)
+                .run(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port))
+                .await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OracleConfig;
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn should_serve_current_routes() {
+        let config = |feed: &str| {
+            serde_json::from_value::<OracleConfig>(json!({
+                "currencies": {},
+                "prices": [{ "pair": ["BTC", "USD"], "feeds": { feed: [["BTC", "USD"]] } }]
+            }))
+            .unwrap()
+        };
+        let api = RoutesApi::default();
+        let get_routes = || async {
+            let response = warp::test::request().path("/routes").reply(&api.filter()).await;
+            serde_json::from_slice::<Value>(response.body()).unwrap()
+        };
+
+        api.set_routes(config("kraken").routes());
+        assert_eq!(get_routes().await[0]["feeds"]["kraken"][0][0]["pair"], "BTC/USD");
+
+        // reloading replaces the routes
+        api.set_routes(config("gateio").routes());
+        assert_eq!(
+            get_routes().await[0]["feeds"],
+            json!({ "gateio": [[{ "pair": "BTC/USD", "inverted": false }]] })
+        );
+    }
+}
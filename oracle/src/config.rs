@@ -1,32 +1,701 @@
 use crate::{
     currency::*,
-    error::{ConfigError, PriceConfigError},
+    error::{ConfigError, ConfigWarning, PriceConfigError},
     feeds::FeedName,
+    graph::CurrencyGraph,
+    Error,
 };
-use serde::Deserialize;
-use std::{collections::BTreeMap, convert::TryFrom};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    path::Path,
+    time::Duration,
+};
+use url::Url;
 
 pub type CurrencyStore<Symbol> = BTreeMap<Symbol, CurrencyConfig>;
 
+/// Formats the oracle config can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+}
+
+/// Number of bytes of the config hash to keep.
+const CONFIG_HASH_SIZE: usize = 4;
+
+/// Largest number of decimals of a currency, more would overflow when
+/// prices are scaled into fixed-point.
+pub const MAX_DECIMALS: u32 = 36;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct OracleConfig {
     pub currencies: CurrencyStore<String>,
     pub prices: Vec<PriceConfig<Currency>>,
+    /// Per-feed settings, unlisted feeds use the defaults.
+    #[serde(default)]
+    pub feeds: BTreeMap<FeedName, FeedConfig>,
+    /// How published values are rounded.
+    #[serde(default)]
+    pub rounding_mode: RoundingMode,
+    /// Prices with more paths (across all feeds and groups) are rejected,
+    /// which guards against duplicated feed blocks.
+    #[serde(default = "default_max_feeds_per_pair")]
+    pub max_feeds_per_pair: usize,
+    /// Short hash of the config, set when it is parsed from a string or file.
+    /// The hash of a layered config is that of the merged config.
+    #[serde(skip)]
+    pub hash: String,
+}
+
+/// Sort all object keys so that the encoding does not depend on the
+/// order or formatting of the config file.
+fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(left, _), (right, _)| left.cmp(right));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+        value => value,
+    }
+}
+
+fn config_hash(value: &Value) -> String {
+    let canonical = canonicalize(value.clone()).to_string();
+    hex::encode(&Sha256::digest(canonical.as_bytes())[..CONFIG_HASH_SIZE])
+}
+
+/// The pair of a price of the config, by which the price is replaced in a layer.
+fn price_pair(price: &Value) -> Result<CurrencyPair<Currency>, serde_json::Error> {
+    #[derive(Deserialize)]
+    struct Price {
+        pair: CurrencyPair<Currency>,
+    }
+    Ok(Price::deserialize(price)?.pair)
+}
+
+/// Layer the `layer` on top of the `config`, see `OracleConfig::read_layered`.
+/// The files are merged as JSON so that the merged config is hashed like a
+/// single file with the same content.
+fn merge_layer(config: &mut Map<String, Value>, layer: Map<String, Value>) -> Result<(), serde_json::Error> {
+    // like a missing field, null does not override anything
+    for (key, value) in layer.into_iter().filter(|(_, value)| !value.is_null()) {
+        match (key.as_str(), config.entry(key.clone()).or_insert(Value::Null)) {
+            ("currencies", Value::Object(entries)) | ("feeds", Value::Object(entries)) => {
+                entries.extend(serde_json::from_value::<Map<String, Value>>(value)?)
+            }
+            ("prices", Value::Array(prices)) => {
+                for price in serde_json::from_value::<Vec<Value>>(value)? {
+                    let pair = price_pair(&price)?;
+                    match prices
+                        .iter()
+                        .position(|existing| price_pair(existing).ok().as_ref() == Some(&pair))
+                    {
+                        Some(index) => prices[index] = price,
+                        None => prices.push(price),
+                    }
+                }
+            }
+            (_, existing) => *existing = value,
+        }
+    }
+    Ok(())
+}
+
+impl OracleConfig {
+    /// Parse the config and hash its canonical JSON encoding, so that
+    /// published prices can be tied to the config which produced them.
+    fn from_json(data: &str) -> Result<Self, serde_json::Error> {
+        Self::from_value(serde_json::from_str(data)?)
+    }
+
+    fn from_value(value: Value) -> Result<Self, serde_json::Error> {
+        let hash = config_hash(&value);
+        Ok(Self {
+            hash,
+            ..serde_json::from_value(value)?
+        })
+    }
+
+    /// Parse the config and validate the currencies and the routes of each
+    /// price, without checking that their feeds are available.
+    pub fn from_str_with_format(content: &str, format: ConfigFormat) -> Result<Self, Error> {
+        let oracle_config = match format {
+            ConfigFormat::Json => Self::from_json(content)?,
+        };
+        oracle_config
+            .validate_currencies()
+            .map_err(|err| Error::InvalidCurrencyConfig(Box::new(err)))?;
+        for price_config in &oracle_config.prices {
+            price_config
+                .validate()
+                .map_err(|err| Error::InvalidConfig(Box::new(err)))?
+        }
+        Ok(oracle_config)
+    }
+
+    /// Read the config from a JSON file, see `from_str_with_format`.
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        Self::from_str_with_format(&std::fs::read_to_string(path)?, ConfigFormat::Json)
+    }
+
+    /// Read the config from the first of the `paths` and layer the others on
+    /// top in order, without validating it. Later files override currencies
+    /// and feeds by key and replace prices by pair.
+    pub fn read_layered(paths: &[&Path]) -> Result<Self, ConfigError<Currency>> {
+        let mut values = paths
+            .iter()
+            .map(|path| {
+                Ok(serde_json::from_str::<Map<String, Value>>(&std::fs::read_to_string(
+                    path,
+                )?)?)
+            })
+            .collect::<Result<Vec<_>, ConfigError<Currency>>>()?
+            .into_iter();
+        let mut merged = values.next().ok_or(ConfigError::NoConfigFile)?;
+        for layer in values {
+            merge_layer(&mut merged, layer)?;
+        }
+        Ok(Self::from_value(Value::Object(merged))?)
+    }
+
+    /// Read the layered config, see `read_layered`. The merged config is
+    /// validated like `from_str_with_format` but with the errors of all prices.
+    pub fn load_layered(paths: &[&Path]) -> Result<Self, ConfigError<Currency>> {
+        let oracle_config = Self::read_layered(paths)?;
+        oracle_config.validate_currencies()?;
+        oracle_config.validate_all().map_err(ConfigError::InvalidPrices)?;
+        Ok(oracle_config)
+    }
+
+    pub fn is_enabled(&self, feed: &FeedName) -> bool {
+        self.feeds.get(feed).map_or(true, |feed_config| feed_config.enabled)
+    }
+
+    /// The price source of each feed, unlisted feeds use the last trade.
+    pub fn price_sources(&self) -> BTreeMap<FeedName, PriceSource> {
+        self.feeds
+            .iter()
+            .map(|(name, feed_config)| (name.clone(), feed_config.price_source))
+            .collect()
+    }
+
+    /// The timestamp config of each feed, unlisted feeds use the defaults.
+    pub fn timestamps(&self) -> BTreeMap<FeedName, TimestampConfig> {
+        self.feeds
+            .iter()
+            .map(|(name, feed_config)| (name.clone(), feed_config.timestamp))
+            .collect()
+    }
+
+    /// The conversion of each feed which has one.
+    pub fn conversions(&self) -> BTreeMap<FeedName, ConversionConfig> {
+        self.feeds
+            .iter()
+            .filter_map(|(name, feed_config)| Some((name.clone(), feed_config.conversion.clone()?)))
+            .collect()
+    }
+
+    /// Check that the decimals of every currency (including those of feeds)
+    /// are at most `MAX_DECIMALS`,
+    /// and that every currency used by a price (including those of paths) is
+    /// listed in the currencies.
+    pub fn validate_currencies(&self) -> Result<(), ConfigError<Currency>> {
+        if let Some((currency, decimals)) = self
+            .currencies
+            .iter()
+            .flat_map(|(currency, currency_config)| {
+                std::iter::once(currency_config.decimals)
+                    .chain(currency_config.feed_decimals.values().copied())
+                    .map(move |decimals| (currency, decimals))
+            })
+            .find(|(_, decimals)| *decimals > MAX_DECIMALS)
+        {
+            return Err(ConfigError::DecimalsOutOfRange {
+                currency: currency.clone(),
+                decimals,
+            });
+        }
+        match self
+            .all_currencies()
+            .into_iter()
+            .find(|currency| !self.currencies.contains_key(&currency.symbol()))
+        {
+            Some(currency) => Err(ConfigError::UnknownCurrency(currency)),
+            None => Ok(()),
+        }
+    }
+
+    /// Check that no price has more than `max_feeds_per_pair` paths and that
+    /// every enabled feed used by a price has a registered adapter.
+    pub fn validate(&self, adapters: &BTreeSet<FeedName>) -> Result<(), PriceConfigError<Currency>> {
+        for price_config in &self.prices {
+            let routes = price_config.clone().routes();
+            let count = routes.values().map(Vec::len).sum();
+            if count <= self.max_feeds_per_pair {
+                continue;
+            }
+            // report the feed whose paths exceed the maximum
+            let mut paths = 0;
+            if let Some(name) = routes.iter().find_map(|(name, routes)| {
+                paths += routes.len();
+                Some(name).filter(|_| paths > self.max_feeds_per_pair)
+            }) {
+                return Err(PriceConfigError {
+                    feed: Some(name.clone()),
+                    pair: price_config.pair.clone(),
+                    error: ConfigError::TooManyFeeds {
+                        pair: price_config.pair.clone(),
+                        count,
+                        max: self.max_feeds_per_pair,
+                    },
+                });
+            }
+        }
+
+        for price_config in self.enabled_prices() {
+            if let Some(name) = price_config
+                .feed_names()
+                .into_iter()
+                .find(|name| !adapters.contains(name))
+            {
+                return Err(PriceConfigError {
+                    feed: Some(name.clone()),
+                    pair: price_config.pair,
+                    error: missing_adapter(name),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the paths of every price, collecting all errors instead of
+    /// stopping at the first.
+    pub fn validate_all(&self) -> Result<(), Vec<PriceConfigError<Currency>>> {
+        let errors = self
+            .prices
+            .iter()
+            .flat_map(|price_config| price_config.errors())
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Validate the routes and adapters of all prices, collecting every error
+    /// and warning instead of stopping at the first error.
+    pub fn validation_report(&self, adapters: &BTreeSet<FeedName>) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        for price_config in &self.prices {
+            let pair = format!(
+                "{}/{}",
+                currency_name(&price_config.pair.base),
+                currency_name(&price_config.pair.quote)
+            );
+            for err in price_config.errors() {
+                report.errors.push(ValidationIssue {
+                    pair: pair.clone(),
+                    feed: err.feed,
+                    message: err.error.to_string(),
+                });
+            }
+
+            let enabled = price_config
+                .feed_names()
+                .into_iter()
+                .filter(|name| self.is_enabled(name))
+                .collect::<Vec<_>>();
+            if price_config.value.is_none() && price_config.has_feeds() && enabled.is_empty() {
+                report.warnings.push(ValidationIssue {
+                    pair: pair.clone(),
+                    feed: None,
+                    message: ConfigWarning::AllFeedsDisabled.to_string(),
+                });
+            }
+            for name in enabled.into_iter().filter(|name| !adapters.contains(name)) {
+                report.errors.push(ValidationIssue {
+                    pair: pair.clone(),
+                    feed: Some(name.clone()),
+                    message: missing_adapter::<Currency>(name).to_string(),
+                });
+            }
+        }
+        report
+    }
+
+    /// Every currency used by a price, including the intermediate currencies of
+    /// all paths (of both enabled and disabled feeds). Currencies with a
+    /// feed-specific path such as `KSM=kusama` are listed once per path.
+    pub fn all_currencies(&self) -> BTreeSet<Currency> {
+        self.prices
+            .iter()
+            .flat_map(|price_config| {
+                std::iter::once(&price_config.pair)
+                    .chain(price_config.feeds.values().flatten().map(|hop| &hop.pair))
+                    .chain(price_config.groups.values().flatten().flatten().map(|hop| &hop.pair))
+            })
+            .flat_map(|currency_pair| vec![currency_pair.base.clone(), currency_pair.quote.clone()])
+            .collect()
+    }
+
+    /// The requests made to each feed on a tick where all prices are due.
+    pub fn request_plan(&self) -> RequestPlan {
+        let mut feeds = BTreeMap::<FeedName, FeedRequests>::new();
+        for price_config in self.enabled_prices() {
+            if price_config.value.is_some() {
+                continue;
+            }
+            for (name, routes) in price_config.routes() {
+                for hop in routes.iter().flatten() {
+                    let feed_requests = feeds.entry(hop.feed_or(&name).clone()).or_default();
+                    // feeds are queried once per pair, there is no batching or dedup
+                    feed_requests.requests += 1;
+                    feed_requests.pairs.insert(format!(
+                        "{}/{}",
+                        currency_name(&hop.pair.base),
+                        currency_name(&hop.pair.quote)
+                    ));
+                }
+            }
+        }
+        RequestPlan { feeds }
+    }
+
+    /// The legs of every route of the enabled prices, oriented from the
+    /// base to the quote of the price as they are combined by the feeds.
+    pub fn routes(&self) -> Vec<PriceRoutes> {
+        self.enabled_prices()
+            .into_iter()
+            .map(|price_config| {
+                let pair = price_config.pair.clone();
+                PriceRoutes {
+                    pair: format!("{}/{}", currency_name(&pair.base), currency_name(&pair.quote)),
+                    value: price_config.value,
+                    feeds: price_config
+                        .routes()
+                        .into_iter()
+                        .map(|(name, routes)| {
+                            let legs = routes.into_iter().map(|route| route_legs(&pair, route)).collect();
+                            (name, legs)
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// The pairs read by the enabled feeds, to find paths for new prices.
+    pub fn currency_graph(&self) -> CurrencyGraph<Currency> {
+        CurrencyGraph::new(self.enabled_prices().into_iter().flat_map(|price_config| {
+            price_config
+                .routes()
+                .into_values()
+                .flatten()
+                .flatten()
+                .map(|hop| hop.pair)
+        }))
+    }
+
+    /// Returns the prices to compute with all disabled feeds removed.
+    /// Prices which only have disabled feeds are skipped entirely so
+    /// that the last value set on the parachain is kept.
+    pub fn enabled_prices(&self) -> Vec<PriceConfig<Currency>> {
+        self.prices
+            .iter()
+            .cloned()
+            .filter_map(|mut price_config| {
+                if price_config.value.is_some() || !price_config.has_feeds() {
+                    return Some(price_config);
+                }
+                // paths are skipped if their own feed or the feed of any hop is disabled
+                let is_enabled = |name: &FeedName, path: &[PathHop<Currency>]| {
+                    self.is_enabled(name) && path.iter().all(|hop| self.is_enabled(hop.feed_or(name)))
+                };
+                price_config.feeds.retain(|name, path| is_enabled(name, path));
+                price_config.groups.retain(|name, paths| {
+                    paths.retain(|path| is_enabled(name, path));
+                    !paths.is_empty()
+                });
+                if !price_config.has_feeds() {
+                    log::warn!("Skipping {}: all feeds are disabled", price_config.pair);
+                    None
+                } else {
+                    Some(price_config)
+                }
+            })
+            .collect()
+    }
+}
+
+/// The error for a feed without an adapter, names which do not match any
+/// feed are reported as unknown.
+fn missing_adapter<Currency>(name: FeedName) -> ConfigError<Currency> {
+    match name {
+        FeedName::Unknown(name) => ConfigError::UnknownFeed(name),
+        name => ConfigError::NoAdapterForFeed(name),
+    }
+}
+
+/// Orient each leg of the `route` from the currency it continues from. Routes
+/// which start at the quote are walked from there and flipped at the end.
+fn route_legs(pair: &CurrencyPair<Currency>, route: Vec<PathHop<Currency>>) -> Vec<RouteLeg> {
+    let pairs = route.iter().map(|hop| hop.pair.clone()).collect::<Vec<_>>();
+    route_orientation(pair, &pairs)
+        .into_iter()
+        .zip(route)
+        .map(|((currency_pair, inverted), hop)| RouteLeg {
+            pair: format!(
+                "{}/{}",
+                currency_name(&currency_pair.base),
+                currency_name(&currency_pair.quote)
+            ),
+            inverted,
+            feed: hop.feed,
+        })
+        .collect()
+}
+
+/// Each leg of the `route` with whether its reciprocal is used, following
+/// the route from the base or, if it starts there, from the quote of `pair`.
+fn route_orientation<Currency: Clone + PartialEq>(
+    pair: &CurrencyPair<Currency>,
+    route: &[CurrencyPair<Currency>],
+) -> Vec<(CurrencyPair<Currency>, bool)> {
+    let from_base = route
+        .first()
+        .map_or(true, |currency_pair| currency_pair.contains(&pair.base));
+    let mut current = if from_base { &pair.base } else { &pair.quote }.clone();
+    route
+        .iter()
+        .map(|currency_pair| {
+            let inverted = currency_pair.base != current;
+            current = if inverted {
+                currency_pair.base.clone()
+            } else {
+                currency_pair.quote.clone()
+            };
+            (currency_pair.clone(), inverted == from_base)
+        })
+        .collect()
+}
+
+/// Check that the product of the `legs` of a validated path, taking the
+/// reciprocal of each inverted leg, is the price of `pair` and not its
+/// reciprocal.
+pub fn validate_orientation<Currency: Clone + PartialEq>(
+    pair: &CurrencyPair<Currency>,
+    legs: &[(CurrencyPair<Currency>, bool)],
+) -> Result<(), ConfigError<Currency>> {
+    // the power of each currency in the product, which must be the base over
+    // the quote once the intermediate currencies cancel out
+    let mut powers = Vec::<(Currency, i32)>::new();
+    let mut multiply = |currency: &Currency, power: i32| match powers.iter_mut().find(|(other, _)| other == currency) {
+        Some((_, total)) => *total += power,
+        None => powers.push((currency.clone(), power)),
+    };
+    multiply(&pair.base, -1);
+    multiply(&pair.quote, 1);
+    for (currency_pair, inverted) in legs {
+        let power = if *inverted { -1 } else { 1 };
+        multiply(&currency_pair.base, power);
+        multiply(&currency_pair.quote, -power);
+    }
+
+    if powers.iter().all(|(_, power)| *power == 0) {
+        Ok(())
+    } else {
+        Err(ConfigError::WrongOrientation)
+    }
+}
+
+/// The symbol and path (if any) as written in the config, e.g. `KSM=kusama`.
+pub fn currency_name(currency: &Currency) -> String {
+    match currency.path() {
+        Some(path) => format!("{}={}", currency.symbol(), path),
+        None => currency.symbol(),
+    }
+}
+
+/// All errors and warnings of the config, see `OracleConfig::validation_report`.
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub errors: Vec<ValidationIssue>,
+    pub warnings: Vec<ValidationIssue>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// The price, e.g. `BTC/USD`.
+    pub pair: String,
+    /// The feed the issue applies to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed: Option<FeedName>,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct RequestPlan {
+    pub feeds: BTreeMap<FeedName, FeedRequests>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct FeedRequests {
+    /// Distinct pairs requested from the feed.
+    pub pairs: BTreeSet<String>,
+    /// Number of requests made per tick.
+    pub requests: usize,
+}
+
+/// The routes of a price, see `OracleConfig::routes`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PriceRoutes {
+    pub pair: String,
+    /// Fixed value which is used instead of the feeds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    pub feeds: BTreeMap<FeedName, Vec<Vec<RouteLeg>>>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct RouteLeg {
+    /// The pair requested from the feed, e.g. `DOT/USD`.
+    pub pair: String,
+    /// Whether the reciprocal of the price of this pair is used.
+    pub inverted: bool,
+    /// The feed the pair is read from, if not the feed of the route.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed: Option<FeedName>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FeedConfig {
+    /// Skip this feed when fetching prices, can be toggled
+    /// with a config reload.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Which price to read from the feed's ticker.
+    #[serde(default)]
+    pub price_source: PriceSource,
+    /// Converts the legs which the feed fails to price directly.
+    #[serde(default)]
+    pub conversion: Option<ConversionConfig>,
+    /// How to read the timestamps of the feed's prices.
+    #[serde(default)]
+    pub timestamp: TimestampConfig,
+}
+
+/// How the timestamps reported by a feed are read, see `timestamp::normalize`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampConfig {
+    #[serde(default)]
+    pub unit: TimestampUnit,
+    #[serde(default)]
+    pub source: TimestampSource,
+    /// Server timestamps which are further ahead of the local clock are rejected.
+    #[serde(default = "default_max_skew_secs")]
+    pub max_skew_secs: u64,
+}
+
+impl Default for TimestampConfig {
+    fn default() -> Self {
+        Self {
+            unit: Default::default(),
+            source: Default::default(),
+            max_skew_secs: default_max_skew_secs(),
+        }
+    }
+}
+
+fn default_max_skew_secs() -> u64 {
+    60
+}
+
+/// The unit of the Unix timestamps reported by a feed.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampUnit {
+    #[default]
+    Seconds,
+    Milliseconds,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampSource {
+    /// The time reported by the exchange's server.
+    #[default]
+    Server,
+    /// The time at which the response was received, for feeds whose
+    /// clock cannot be trusted.
+    Received,
+}
+
+/// Legs with the `currency` which the feed fails to price are priced in the
+/// `vehicle` currency instead, e.g. BTC/EUR for BTC/USD, and converted with
+/// the price of vehicle/currency from the conversion `feed`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ConversionConfig {
+    pub currency: Currency,
+    pub vehicle: Currency,
+    pub feed: FeedName,
+}
+
+/// The price to read from a ticker, feeds without the order book
+/// top only support the last trade.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// The price each feed has always reported, i.e. the last
+    /// trade on gate.io and today's VWAP on Kraken.
+    #[default]
+    LastTrade,
+    /// Average of the best bid and ask.
+    Mid,
+    Bid,
+    Ask,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_feeds_per_pair() -> usize {
+    8
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct CurrencyConfig {
     pub name: String,
     pub decimals: u32,
+    /// The precision at which feeds report this currency, if it differs
+    /// from the `decimals`.
+    #[serde(default)]
+    pub feed_decimals: BTreeMap<FeedName, u32>,
 }
 
-impl<Symbol: Ord> CurrencyInfo<Symbol> for CurrencyStore<Symbol> {
-    fn name(&self, id: &Symbol) -> Option<String> {
-        self.get(id).map(|asset_config| asset_config.name.clone())
+impl CurrencyConfig {
+    /// The decimals at which the `feed` reports this currency, which are the
+    /// canonical `decimals` unless overridden.
+    pub fn decimals_for(&self, feed: &FeedName) -> u32 {
+        self.feed_decimals.get(feed).copied().unwrap_or(self.decimals)
     }
+}
 
-    fn decimals(&self, id: &Symbol) -> Option<u32> {
-        self.get(id).map(|asset_config| asset_config.decimals)
+impl<Symbol: Ord> CurrencyInfo<Symbol> for CurrencyStore<Symbol> {
+    fn config(&self, id: &Symbol) -> Result<&CurrencyConfig, Error> {
+        self.get(id).ok_or(Error::InvalidCurrency)
     }
 }
 
@@ -38,47 +707,353 @@ pub struct PriceConfig<Currency> {
     pub value: Option<f64>,
     // Feeds to consume to calculate this exchange rate.
     #[serde(default)]
-    pub feeds: BTreeMap<FeedName, Vec<CurrencyPair<Currency>>>,
+    pub feeds: BTreeMap<FeedName, Vec<PathHop<Currency>>>,
+    /// Feeds with multiple paths, the median is taken within each
+    /// feed first and then across all feeds.
+    #[serde(default)]
+    pub groups: BTreeMap<FeedName, Vec<Vec<PathHop<Currency>>>>,
+    /// Publish this price at most once per interval, if unset
+    /// the global interval is used.
+    #[serde(default)]
+    pub min_publish_interval_secs: Option<u64>,
+    /// Compare the computed price against an external oracle, this
+    /// is only used for monitoring and never as a feed.
+    #[serde(default)]
+    pub reference_check: Option<ReferenceCheckConfig>,
+    /// Range in which the price of each route is expected, used to
+    /// detect legs which are written in the wrong orientation.
+    #[serde(default)]
+    pub plausible_range: Option<PlausibleRangeConfig>,
+    /// How the prices of multiple feeds are combined.
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    /// The price is not published if its last successful update is
+    /// older than this, e.g. while retrying a failed submission. Feed
+    /// prices observed longer ago are not aggregated.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+    /// Discard feeds whose price deviates from the median of all feeds
+    /// by more than this percentage before aggregating.
+    #[serde(default)]
+    pub max_deviation_pct: Option<f64>,
+}
+
+/// A pair of a path, which is read from the feed of the path unless another
+/// feed is named, e.g. `["kraken", ["USDT", "USD"]]` instead of `["USDT", "USD"]`.
+/// The price is still aggregated under the feed of the path.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "PathHopConfig<Currency>")]
+pub struct PathHop<Currency> {
+    pub feed: Option<FeedName>,
+    pub pair: CurrencyPair<Currency>,
+    /// Whether the reciprocal of the pair's price is expected to be used,
+    /// which is checked against the path if set.
+    pub inverted: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PathHopConfig<Currency> {
+    Pair(CurrencyPair<Currency>),
+    WithFeed(FeedName, CurrencyPair<Currency>),
+    /// e.g. `{ "pair": ["DOT", "USD"], "inverted": true }`
+    Declared {
+        #[serde(default)]
+        feed: Option<FeedName>,
+        pair: CurrencyPair<Currency>,
+        inverted: bool,
+    },
+}
+
+impl<Currency> From<PathHopConfig<Currency>> for PathHop<Currency> {
+    fn from(config: PathHopConfig<Currency>) -> Self {
+        match config {
+            PathHopConfig::Pair(pair) => pair.into(),
+            PathHopConfig::WithFeed(feed, pair) => Self {
+                feed: Some(feed),
+                pair,
+                inverted: None,
+            },
+            PathHopConfig::Declared { feed, pair, inverted } => Self {
+                feed,
+                pair,
+                inverted: Some(inverted),
+            },
+        }
+    }
+}
+
+impl<Currency> From<CurrencyPair<Currency>> for PathHop<Currency> {
+    fn from(pair: CurrencyPair<Currency>) -> Self {
+        Self {
+            feed: None,
+            pair,
+            inverted: None,
+        }
+    }
+}
+
+impl<Currency> PathHop<Currency> {
+    /// The feed to read the pair from, in a path of the feed `name`.
+    pub fn feed_or<'a>(&'a self, name: &'a FeedName) -> &'a FeedName {
+        self.feed.as_ref().unwrap_or(name)
+    }
+}
+
+/// Combines the median price of each feed.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    #[default]
+    Median,
+    Mean,
+    /// Mean weighted by feed, there must be a weight for every feed.
+    WeightedMean(BTreeMap<FeedName, f64>),
+    /// Mean without the lowest and highest `trim_pct` percent of the prices,
+    /// falls back to the median if that would remove every price.
+    TrimmedMean {
+        trim_pct: f64,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReferenceCheckConfig {
+    /// Endpoint returning the reference price as JSON.
+    pub url: Url,
+    /// JSON pointer to the price in the response, e.g. `/bitcoin/usd`.
+    pub extractor: String,
+    /// Maximum relative divergence from the reference, e.g. `0.05` for 5%.
+    pub tolerance: f64,
+    /// Refuse to publish the price if it diverges by more than the tolerance.
+    #[serde(default)]
+    pub strict: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlausibleRangeConfig {
+    pub min: f64,
+    pub max: f64,
+    /// Use the reciprocal of a likely inverted price instead of only logging a warning.
+    #[serde(default)]
+    pub auto_correct: bool,
+}
+
+impl PlausibleRangeConfig {
+    pub fn contains(&self, price: f64) -> bool {
+        self.min <= price && price <= self.max
+    }
+
+    /// Whether the `price` is outside the range but its reciprocal is inside,
+    /// i.e. it is off by roughly the square of the expected magnitude.
+    pub fn is_inverted(&self, price: f64) -> bool {
+        !self.contains(price) && self.contains(price.invert())
+    }
+}
+
+impl<Currency> PriceConfig<Currency> {
+    pub fn publish_interval(&self, default: Duration) -> Duration {
+        self.min_publish_interval_secs.map_or(default, Duration::from_secs)
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age_secs.map(Duration::from_secs)
+    }
+
+    pub fn has_feeds(&self) -> bool {
+        !self.feeds.is_empty() || !self.groups.is_empty()
+    }
+
+    /// Every feed which is read by a path, including those named by a hop.
+    pub fn feed_names(&self) -> BTreeSet<FeedName> {
+        let paths = self.feeds.iter().chain(
+            self.groups
+                .iter()
+                .flat_map(|(name, paths)| paths.iter().map(move |path| (name, path))),
+        );
+        paths
+            .flat_map(|(name, path)| std::iter::once(name).chain(path.iter().map(move |hop| hop.feed_or(name))))
+            .cloned()
+            .collect()
+    }
+
+    /// All paths to consume, grouped by feed.
+    pub fn routes(self) -> BTreeMap<FeedName, Vec<Vec<PathHop<Currency>>>> {
+        let mut routes = self.groups;
+        for (name, path) in self.feeds {
+            routes.entry(name).or_default().push(path);
+        }
+        routes
+    }
 }
 
 impl<Currency> PriceConfig<Currency>
 where
     Currency: Clone + PartialEq,
 {
-    // TODO: validate currencies exist
     pub fn validate(&self) -> Result<(), PriceConfigError<Currency>> {
-        for (name, path) in &self.feeds {
-            let end = &match &path.first() {
-                Some(currency_pair) if currency_pair.contains(&self.pair.base) => Ok(self.pair.quote.clone()),
-                Some(currency_pair) if currency_pair.contains(&self.pair.quote) => Ok(self.pair.base.clone()),
-                _ => Err(PriceConfigError {
-                    feed: name.clone(),
-                    pair: self.pair.clone(),
-                    error: ConfigError::NoStart,
-                }),
-            }?;
+        match self.errors().into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether exactly one of a value or feeds is set, followed by the first
+    /// error of every path and the weights without a feed.
+    pub fn errors(&self) -> Vec<PriceConfigError<Currency>> {
+        let source_error = match (self.value, self.has_feeds()) {
+            (Some(_), true) => Some(ConfigError::AmbiguousSource),
+            (None, false) => Some(ConfigError::NoSource),
+            _ => None,
+        };
+        if let Some(error) = source_error {
+            return vec![PriceConfigError {
+                feed: None,
+                pair: self.pair.clone(),
+                error,
+            }];
+        }
 
-            match &path.last() {
-                Some(currency_pair) if currency_pair.contains(end) => Ok(()),
-                _ => Err(PriceConfigError {
-                    feed: name.clone(),
+        let paths = self.feeds.iter().chain(
+            self.groups
+                .iter()
+                .flat_map(|(name, paths)| paths.iter().map(move |path| (name, path))),
+        );
+        let mut errors = paths
+            .filter_map(|(name, path)| self.validate_path(name, path).err())
+            .collect::<Vec<_>>();
+
+        if self.max_age_secs == Some(0) {
+            errors.push(PriceConfigError {
+                feed: None,
+                pair: self.pair.clone(),
+                error: ConfigError::ZeroMaxAge,
+            });
+        }
+
+        // the price would be published in a busy loop
+        if self.min_publish_interval_secs == Some(0) {
+            errors.push(PriceConfigError {
+                feed: None,
+                pair: self.pair.clone(),
+                error: ConfigError::ZeroPublishInterval,
+            });
+        }
+
+        if let Aggregation::WeightedMean(weights) = &self.aggregation {
+            errors.extend(
+                weights
+                    .keys()
+                    .filter(|name| !self.feeds.contains_key(name) && !self.groups.contains_key(name))
+                    .map(|name| PriceConfigError {
+                        feed: Some(name.clone()),
+                        pair: self.pair.clone(),
+                        error: ConfigError::WeightsMismatch,
+                    }),
+            );
+            // a zero total weight or negative weights would yield an invalid price
+            errors.extend(
+                weights
+                    .iter()
+                    .filter(|(_, weight)| !(weight.is_finite() && **weight > 0.0))
+                    .map(|(name, weight)| PriceConfigError {
+                        feed: Some(name.clone()),
+                        pair: self.pair.clone(),
+                        error: ConfigError::InvalidWeight(*weight),
+                    }),
+            );
+        }
+
+        // the cast in the trimmed mean would turn these into 0 or the median
+        if let Aggregation::TrimmedMean { trim_pct } = self.aggregation {
+            if !(0.0..50.0).contains(&trim_pct) {
+                errors.push(PriceConfigError {
+                    feed: None,
                     pair: self.pair.clone(),
-                    error: ConfigError::NoEnd,
-                }),
-            }?;
+                    error: ConfigError::InvalidTrimPct(trim_pct),
+                });
+            }
+        }
+        errors
+    }
+
+    fn validate_path(&self, name: &FeedName, path: &[PathHop<Currency>]) -> Result<(), PriceConfigError<Currency>> {
+        let declared = path.iter().map(|hop| hop.inverted).collect::<Vec<_>>();
+        // hops are connected by their pairs, regardless of the feed they are read from
+        let path = &path.iter().map(|hop| hop.pair.clone()).collect::<Vec<_>>();
+        let end = &match &path.first() {
+            Some(currency_pair) if currency_pair.contains(&self.pair.base) => Ok(self.pair.quote.clone()),
+            Some(currency_pair) if currency_pair.contains(&self.pair.quote) => Ok(self.pair.base.clone()),
+            _ => Err(PriceConfigError {
+                feed: Some(name.clone()),
+                pair: self.pair.clone(),
+                error: ConfigError::NoStart,
+            }),
+        }?;
 
-            for [left, right] in path.windows(2).flat_map(<&[CurrencyPair<Currency>; 2]>::try_from) {
-                if !left.has_shared(right) {
+        match &path.last() {
+            Some(currency_pair) if currency_pair.contains(end) => Ok(()),
+            _ => Err(PriceConfigError {
+                feed: Some(name.clone()),
+                pair: self.pair.clone(),
+                error: ConfigError::NoEnd,
+            }),
+        }?;
+
+        // the currencies visited by the path, in order
+        let start = if end == &self.pair.quote {
+            &self.pair.base
+        } else {
+            &self.pair.quote
+        };
+        let mut chain = vec![start.clone()];
+        for [left, right] in path.windows(2).flat_map(<&[CurrencyPair<Currency>; 2]>::try_from) {
+            match left.shared_currency(right) {
+                Some(pivot) => chain.push(pivot),
+                None => {
                     return Err(PriceConfigError {
-                        feed: name.clone(),
+                        feed: Some(name.clone()),
                         pair: self.pair.clone(),
                         error: ConfigError::NoPath(left.clone(), right.clone()),
-                    });
+                    })
                 }
             }
         }
+        chain.push(end.clone());
 
-        Ok(())
+        if let Aggregation::WeightedMean(weights) = &self.aggregation {
+            if !weights.contains_key(name) {
+                return Err(PriceConfigError {
+                    feed: Some(name.clone()),
+                    pair: self.pair.clone(),
+                    error: ConfigError::WeightsMismatch,
+                });
+            }
+        }
+
+        // every currency may only be visited once
+        if let Some(currency) = chain
+            .iter()
+            .enumerate()
+            .find_map(|(i, currency)| chain[..i].contains(currency).then_some(currency))
+        {
+            return Err(PriceConfigError {
+                feed: Some(name.clone()),
+                pair: self.pair.clone(),
+                error: ConfigError::CyclicPath(currency.clone()),
+            });
+        }
+
+        // the path itself always yields the pair, but the declared inversions may not
+        let legs = route_orientation(&self.pair, path)
+            .into_iter()
+            .zip(declared)
+            .map(|((currency_pair, walked), declared)| (currency_pair, declared.unwrap_or(walked)))
+            .collect::<Vec<_>>();
+        validate_orientation(&self.pair, &legs).map_err(|error| PriceConfigError {
+            feed: Some(name.clone()),
+            pair: self.pair.clone(),
+            error,
+        })
     }
 }
 
@@ -91,7 +1066,14 @@ mod tests {
             PriceConfig {
                 pair: $pair,
                 value: None,
-                feeds: vec![(FeedName::Kraken, vec![$($path),*])].into_iter().collect()
+                feeds: vec![(FeedName::Kraken, vec![$(PathHop::from($path)),*])].into_iter().collect(),
+                groups: BTreeMap::new(),
+                min_publish_interval_secs: None,
+                reference_check: None,
+                plausible_range: None,
+                aggregation: Aggregation::Median,
+                max_age_secs: None,
+                max_deviation_pct: None,
             }
             .validate().expect("Config is valid")
         }};
@@ -102,14 +1084,21 @@ mod tests {
             let result = PriceConfig {
                 pair: $pair,
                 value: None,
-                feeds: vec![(FeedName::Kraken, vec![$($path),*])].into_iter().collect()
+                feeds: vec![(FeedName::Kraken, vec![$(PathHop::from($path)),*])].into_iter().collect(),
+                groups: BTreeMap::new(),
+                min_publish_interval_secs: None,
+                reference_check: None,
+                plausible_range: None,
+                aggregation: Aggregation::Median,
+                max_age_secs: None,
+                max_deviation_pct: None,
             }
             .validate();
             assert!(
                 matches!(
                     result,
                     Err(PriceConfigError{
-                        feed: FeedName::Kraken,
+                        feed: Some(FeedName::Kraken),
                         pair: _,
                         error: $err
                     })
@@ -135,6 +1124,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_accept_inverted_legs() {
+        assert_valid!(
+            CurrencyPair { base: "USD", quote: "BTC" } => [
+                CurrencyPair { base: "BTC", quote: "USD" }
+            ]
+        );
+
+        assert_valid!(
+            CurrencyPair { base: "USD", quote: "INTR" } => [
+                CurrencyPair { base: "BTC", quote: "USD" },
+                CurrencyPair { base: "INTR", quote: "BTC" }
+            ]
+        );
+
+        let config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [{ "pair": ["USD", "BTC"], "feeds": { "kraken": [["BTC", "USD"]] } }]
+        }))
+        .unwrap();
+        assert_eq!(
+            config.routes()[0].feeds[&FeedName::Kraken],
+            vec![vec![RouteLeg {
+                pair: "BTC/USD".to_string(),
+                inverted: true,
+                feed: None
+            }]]
+        );
+    }
+
     #[test]
     fn should_reject_invalid_paths() {
         assert_invalid!(
@@ -151,7 +1170,7 @@ mod tests {
 
         assert_invalid!(
             CurrencyPair { base: "BTC", quote: "KSM" } => [
-                CurrencyPair { base: "BTC", quote: "BTC" }
+                CurrencyPair::new_unchecked("BTC", "BTC")
             ],
             ConfigError::NoEnd
         );
@@ -183,4 +1202,924 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn should_reject_cyclic_paths() {
+        assert_invalid!(
+            CurrencyPair { base: "DOT", quote: "INTR" } => [
+                CurrencyPair { base: "USD", quote: "DOT" },
+                CurrencyPair { base: "USD", quote: "KSM" },
+                CurrencyPair { base: "KSM", quote: "USD" },
+                CurrencyPair { base: "USD", quote: "INTR" }
+            ],
+            ConfigError::CyclicPath("USD")
+        );
+
+        assert_invalid!(
+            CurrencyPair { base: "BTC", quote: "KSM" } => [
+                CurrencyPair::new_unchecked("BTC", "BTC"),
+                CurrencyPair { base: "BTC", quote: "KSM" }
+            ],
+            ConfigError::CyclicPath("BTC")
+        );
+    }
+
+    #[test]
+    fn should_require_either_value_or_feeds() {
+        let price_config = |value: Option<f64>, feeds: serde_json::Value| {
+            serde_json::from_value::<PriceConfig<Currency>>(serde_json::json!({
+                "pair": ["BTC", "USD"],
+                "value": value,
+                "feeds": feeds
+            }))
+            .unwrap()
+        };
+        let kraken = serde_json::json!({ "kraken": [["BTC", "USD"]] });
+
+        price_config(Some(20000.0), serde_json::json!({}))
+            .validate()
+            .expect("Uses the value");
+        price_config(None, kraken.clone()).validate().expect("Uses the feeds");
+
+        let err = price_config(Some(20000.0), kraken).validate().unwrap_err();
+        assert!(matches!(err.error, ConfigError::AmbiguousSource), "{:?}", err);
+        assert_eq!(err.feed, None);
+        let err = price_config(None, serde_json::json!({})).validate().unwrap_err();
+        assert!(matches!(err.error, ConfigError::NoSource), "{:?}", err);
+        assert_eq!(err.to_string(), "BTC/USD => Neither a value nor feeds are set");
+    }
+
+    #[test]
+    fn should_validate_orientation() {
+        let pair = CurrencyPair {
+            base: "BTC",
+            quote: "USD",
+        };
+        let leg = |base, quote, inverted| (CurrencyPair { base, quote }, inverted);
+
+        for legs in [
+            vec![leg("BTC", "DOT", false), leg("DOT", "USD", false)],
+            vec![leg("DOT", "BTC", true), leg("DOT", "USD", false)],
+            // the order of the legs does not matter
+            vec![
+                leg("USD", "DOT", true),
+                leg("BTC", "USD", false),
+                leg("USD", "DOT", false),
+            ],
+        ] {
+            validate_orientation(&pair, &legs).expect("Yields BTC/USD");
+        }
+
+        for legs in [
+            vec![leg("USD", "DOT", false), leg("DOT", "BTC", false)],
+            vec![leg("BTC", "USD", true)],
+        ] {
+            assert!(matches!(
+                validate_orientation(&pair, &legs),
+                Err(ConfigError::WrongOrientation)
+            ));
+        }
+
+        // routes from the quote are oriented like those from the base
+        let route = [
+            CurrencyPair {
+                base: "USD",
+                quote: "DOT",
+            },
+            CurrencyPair {
+                base: "DOT",
+                quote: "BTC",
+            },
+        ];
+        validate_orientation(&pair, &route_orientation(&pair, &route)).expect("Yields BTC/USD");
+    }
+
+    #[test]
+    fn should_reject_wrongly_declared_inversions() {
+        let price_config = |dot_inverted: bool| {
+            serde_json::from_value::<PriceConfig<Currency>>(serde_json::json!({
+                "pair": ["BTC", "DOT"],
+                "feeds": {
+                    "kraken": [
+                        { "pair": ["BTC", "USD"], "inverted": false },
+                        { "pair": ["DOT", "USD"], "inverted": dot_inverted }
+                    ]
+                }
+            }))
+            .unwrap()
+        };
+
+        price_config(true)
+            .validate()
+            .expect("BTC/USD over DOT/USD yields BTC/DOT");
+        assert!(matches!(
+            price_config(false).validate(),
+            Err(PriceConfigError {
+                feed: Some(FeedName::Kraken),
+                pair: _,
+                error: ConfigError::WrongOrientation
+            })
+        ));
+    }
+
+    #[test]
+    fn should_reject_weights_which_do_not_match_feeds() {
+        let price_config = |weights: serde_json::Value| {
+            serde_json::from_value::<PriceConfig<Currency>>(serde_json::json!({
+                "pair": ["BTC", "USD"],
+                "feeds": {
+                    "kraken": [["BTC", "USD"]],
+                    "gateio": [["BTC", "USD"]]
+                },
+                "aggregation": { "weighted_mean": weights }
+            }))
+            .unwrap()
+        };
+
+        price_config(serde_json::json!({ "kraken": 2.0, "gateio": 1.0 }))
+            .validate()
+            .expect("Config is valid");
+        for (weights, feed) in [
+            (serde_json::json!({ "kraken": 2.0 }), FeedName::GateIo),
+            (
+                serde_json::json!({ "kraken": 2.0, "gateio": 1.0, "coingecko": 1.0 }),
+                FeedName::CoinGecko,
+            ),
+        ] {
+            let err = price_config(weights).validate().unwrap_err();
+            assert_eq!(err.feed, Some(feed));
+            assert!(matches!(err.error, ConfigError::WeightsMismatch));
+        }
+    }
+
+    #[test]
+    fn should_reject_trim_pct_out_of_range() {
+        let price_config = |trim_pct: f64| PriceConfig::<Currency> {
+            aggregation: Aggregation::TrimmedMean { trim_pct },
+            ..serde_json::from_value(serde_json::json!({
+                "pair": ["BTC", "USD"],
+                "feeds": { "kraken": [["BTC", "USD"]] }
+            }))
+            .unwrap()
+        };
+
+        for trim_pct in [0.0, 10.0, 49.9] {
+            assert!(price_config(trim_pct).validate().is_ok(), "{}", trim_pct);
+        }
+        for trim_pct in [-1.0, 50.0, 100.0, f64::NAN] {
+            assert!(
+                matches!(
+                    price_config(trim_pct).validate(),
+                    Err(PriceConfigError {
+                        feed: None,
+                        pair: _,
+                        error: ConfigError::InvalidTrimPct(_)
+                    })
+                ),
+                "{}",
+                trim_pct
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_non_positive_weights() {
+        let price_config = |weight: f64| PriceConfig::<Currency> {
+            aggregation: Aggregation::WeightedMean(
+                vec![(FeedName::Kraken, 1.0), (FeedName::GateIo, weight)]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..serde_json::from_value(serde_json::json!({
+                "pair": ["BTC", "USD"],
+                "feeds": {
+                    "kraken": [["BTC", "USD"]],
+                    "gateio": [["BTC", "USD"]]
+                }
+            }))
+            .unwrap()
+        };
+
+        assert!(price_config(0.5).validate().is_ok());
+        for weight in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let err = price_config(weight).validate().unwrap_err();
+            assert_eq!(err.feed, Some(FeedName::GateIo));
+            assert!(matches!(err.error, ConfigError::InvalidWeight(_)), "{}", weight);
+        }
+    }
+
+    #[test]
+    fn should_collect_all_errors() {
+        let oracle_config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [
+                {
+                    "pair": ["BTC", "USD"],
+                    "feeds": {
+                        "kraken": [["DOT", "USD"]],
+                        "gateio": [["BTC", "KSM"]]
+                    }
+                },
+                { "pair": ["DOT", "USD"], "feeds": { "gateio": [["DOT", "USD"]] } },
+                {
+                    "pair": ["KSM", "USD"],
+                    "groups": { "dia": [[["KSM", "USD"]], [["KSM", "BTC"], ["DOT", "USD"]]] }
+                }
+            ]
+        }))
+        .unwrap();
+
+        let errors = oracle_config
+            .validate_all()
+            .unwrap_err()
+            .into_iter()
+            .map(|err| (err.feed, err.error.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                (Some(FeedName::Kraken), "No start".to_string()),
+                (Some(FeedName::GateIo), "No end".to_string()),
+                (Some(FeedName::Dia), "No path from KSM/BTC to DOT/USD".to_string()),
+            ]
+        );
+
+        // the first error of each price is still returned by `validate`
+        assert_eq!(
+            oracle_config.prices[0].validate().unwrap_err().feed,
+            Some(FeedName::Kraken)
+        );
+    }
+
+    #[test]
+    fn should_skip_disabled_feeds() {
+        let config = |kraken_enabled: bool| {
+            serde_json::from_value::<OracleConfig>(serde_json::json!({
+                "currencies": {},
+                "prices": [
+                    {
+                        "pair": ["BTC", "KSM"],
+                        "feeds": {
+                            "kraken": [["BTC", "KSM"]],
+                            "gateio": [["BTC", "KSM"]]
+                        }
+                    },
+                    {
+                        "pair": ["BTC", "KINT"],
+                        "feeds": {
+                            "kraken": [["BTC", "KINT"]]
+                        }
+                    }
+                ],
+                "feeds": {
+                    "kraken": { "enabled": kraken_enabled }
+                }
+            }))
+            .unwrap()
+            .enabled_prices()
+        };
+
+        let prices = config(false);
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].feeds.keys().collect::<Vec<_>>(), vec![&FeedName::GateIo]);
+
+        let prices = config(true);
+        assert_eq!(prices.len(), 2);
+        assert_eq!(
+            prices[0].feeds.keys().collect::<Vec<_>>(),
+            vec![&FeedName::Kraken, &FeedName::GateIo]
+        );
+        assert_eq!(prices[1].feeds.keys().collect::<Vec<_>>(), vec![&FeedName::Kraken]);
+    }
+
+    #[test]
+    fn should_read_hops_from_other_feeds() {
+        let config = |path: serde_json::Value, gateio_enabled: bool| {
+            serde_json::from_value::<OracleConfig>(serde_json::json!({
+                "currencies": {},
+                "prices": [{ "pair": ["BTC", "USD"], "feeds": { "kraken": path } }],
+                "feeds": { "gateio": { "enabled": gateio_enabled } }
+            }))
+            .unwrap()
+        };
+        let path = serde_json::json!([["gateio", ["BTC", "USDT"]], ["USDT", "USD"]]);
+
+        let oracle_config = config(path.clone(), true);
+        let price_config = &oracle_config.prices[0];
+        price_config.validate().expect("Hops connect across feeds");
+        assert_eq!(
+            price_config.feeds[&FeedName::Kraken][0],
+            PathHop {
+                feed: Some(FeedName::GateIo),
+                pair: CurrencyPair {
+                    base: "BTC".parse().unwrap(),
+                    quote: "USDT".parse().unwrap()
+                },
+                inverted: None
+            }
+        );
+        assert_eq!(
+            price_config.feed_names().into_iter().collect::<Vec<_>>(),
+            vec![FeedName::Kraken, FeedName::GateIo]
+        );
+        assert_eq!(
+            serde_json::to_value(&oracle_config.routes()[0].feeds).unwrap(),
+            serde_json::json!({
+                "kraken": [[
+                    { "pair": "BTC/USDT", "inverted": false, "feed": "gateio" },
+                    { "pair": "USDT/USD", "inverted": false }
+                ]]
+            })
+        );
+        let plan = oracle_config.request_plan();
+        assert_eq!(
+            plan.feeds[&FeedName::Kraken].pairs,
+            BTreeSet::from(["USDT/USD".to_string()])
+        );
+        assert_eq!(
+            plan.feeds[&FeedName::GateIo].pairs,
+            BTreeSet::from(["BTC/USDT".to_string()])
+        );
+
+        // the path is skipped if the feed of a hop is disabled
+        assert!(config(path, false).enabled_prices().is_empty());
+
+        let err = config(serde_json::json!([["gateio", ["BTC", "USDT"]], ["DOT", "USD"]]), true).prices[0]
+            .validate()
+            .unwrap_err();
+        assert!(matches!(err.error, ConfigError::NoPath(..)), "{:?}", err);
+    }
+
+    #[test]
+    fn should_reject_too_many_feeds() {
+        let config = |groups: usize| {
+            serde_json::from_value::<OracleConfig>(serde_json::json!({
+                "currencies": {},
+                "prices": [{
+                    "pair": ["BTC", "USD"],
+                    "feeds": { "gateio": [["BTC", "USD"]] },
+                    "groups": { "kraken": vec![[["BTC", "USD"]]; groups] }
+                }]
+            }))
+            .unwrap()
+        };
+        let adapters = vec![FeedName::Kraken, FeedName::GateIo].into_iter().collect();
+
+        // at most 8 paths by default
+        assert!(config(7).validate(&adapters).is_ok());
+        let err = config(8).validate(&adapters).unwrap_err();
+        assert_eq!(err.feed, Some(FeedName::GateIo));
+        assert!(
+            matches!(err.error, ConfigError::TooManyFeeds { count: 9, max: 8, .. }),
+            "Actual error: {:?}",
+            err
+        );
+
+        let mut oracle_config = config(8);
+        oracle_config.max_feeds_per_pair = 9;
+        assert!(oracle_config.validate(&adapters).is_ok());
+    }
+
+    #[test]
+    fn should_reject_feeds_without_adapter() {
+        let config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [
+                {
+                    "pair": ["BTC", "KSM"],
+                    "feeds": {
+                        "kraken": [["BTC", "KSM"]],
+                        "gateio": [["BTC", "KSM"]]
+                    }
+                },
+                {
+                    "pair": ["BTC", "KINT"],
+                    "groups": {
+                        "dia": [[["BTC", "KINT"]]]
+                    }
+                }
+            ],
+            "feeds": {
+                "gateio": { "enabled": false }
+            }
+        }))
+        .unwrap();
+
+        // disabled feeds do not need an adapter
+        let adapters = vec![FeedName::Kraken, FeedName::Dia].into_iter().collect();
+        assert!(config.validate(&adapters).is_ok());
+
+        let adapters = vec![FeedName::Kraken].into_iter().collect();
+        let result = config.validate(&adapters);
+        assert!(
+            matches!(
+                result,
+                Err(PriceConfigError {
+                    feed: Some(FeedName::Dia),
+                    pair: _,
+                    error: ConfigError::NoAdapterForFeed(FeedName::Dia)
+                })
+            ),
+            "Actual result: {:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn should_reject_zero_max_age() {
+        let price_config = |max_age_secs: u64| {
+            serde_json::from_value::<PriceConfig<Currency>>(serde_json::json!({
+                "pair": ["BTC", "USD"],
+                "feeds": { "kraken": [["BTC", "USD"]] },
+                "max_age_secs": max_age_secs
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(price_config(60).max_age(), Some(Duration::from_secs(60)));
+        assert!(price_config(60).validate().is_ok());
+        assert!(matches!(
+            price_config(0).validate(),
+            Err(PriceConfigError {
+                feed: None,
+                pair: _,
+                error: ConfigError::ZeroMaxAge
+            })
+        ));
+    }
+
+    #[test]
+    fn should_reject_zero_publish_interval() {
+        let price_config = |min_publish_interval_secs: u64| {
+            serde_json::from_value::<PriceConfig<Currency>>(serde_json::json!({
+                "pair": ["BTC", "USD"],
+                "feeds": { "kraken": [["BTC", "USD"]] },
+                "min_publish_interval_secs": min_publish_interval_secs
+            }))
+            .unwrap()
+        };
+
+        assert!(price_config(60).validate().is_ok());
+        assert!(matches!(
+            price_config(0).validate(),
+            Err(PriceConfigError {
+                feed: None,
+                pair: _,
+                error: ConfigError::ZeroPublishInterval
+            })
+        ));
+    }
+
+    #[test]
+    fn should_reject_unknown_feeds() {
+        let config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [{
+                "pair": ["BTC", "USD"],
+                "feeds": {
+                    "Kraken": [["BTC", "USD"]],
+                    "binance": [["BTC", "USD"]]
+                }
+            }]
+        }))
+        .unwrap();
+        assert_eq!(
+            config.prices[0].feed_names(),
+            vec![FeedName::Kraken, FeedName::Unknown("binance".to_string())]
+                .into_iter()
+                .collect()
+        );
+
+        let adapters = vec![FeedName::Kraken].into_iter().collect();
+        let result = config.validate(&adapters);
+        assert!(
+            matches!(
+                &result,
+                Err(PriceConfigError {
+                    feed: Some(FeedName::Unknown(_)),
+                    pair: _,
+                    error: ConfigError::UnknownFeed(name)
+                }) if name == "binance"
+            ),
+            "Actual result: {:?}",
+            result
+        );
+        assert_eq!(
+            config.validation_report(&adapters).errors[0].message,
+            "Unknown feed binance"
+        );
+    }
+
+    #[test]
+    fn should_compute_request_plan() {
+        let config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [
+                {
+                    "pair": ["BTC", "KSM"],
+                    "feeds": {
+                        "kraken": [["BTC", "USD"], ["KSM", "USD"]],
+                        "coingecko": [["KSM=kusama", "BTC"]]
+                    }
+                },
+                {
+                    "pair": ["BTC", "DOT"],
+                    "feeds": {
+                        "kraken": [["BTC", "USD"], ["DOT", "USD"]],
+                        "gateio": [["BTC", "DOT"]]
+                    }
+                },
+                {
+                    "pair": ["BTC", "INTR"],
+                    "value": 1.0
+                }
+            ],
+            "feeds": {
+                "gateio": { "enabled": false }
+            }
+        }))
+        .unwrap();
+
+        let plan = config.request_plan();
+        assert_eq!(
+            plan.feeds.keys().collect::<Vec<_>>(),
+            vec![&FeedName::Kraken, &FeedName::CoinGecko]
+        );
+        assert_eq!(
+            serde_json::to_value(&plan).unwrap(),
+            serde_json::json!({
+                "feeds": {
+                    "kraken": {
+                        "pairs": ["BTC/USD", "DOT/USD", "KSM/USD"],
+                        "requests": 4
+                    },
+                    "coingecko": {
+                        "pairs": ["KSM=kusama/BTC"],
+                        "requests": 1
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn should_list_routes() {
+        let config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [
+                {
+                    "pair": ["BTC", "DOT"],
+                    "feeds": {
+                        "kraken": [["BTC", "USD"], ["DOT", "USD"]],
+                        "coingecko": [["DOT=polkadot", "USD"], ["BTC=bitcoin", "USD"]]
+                    }
+                },
+                {
+                    "pair": ["BTC", "INTR"],
+                    "value": 1.0
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&config.routes()).unwrap(),
+            serde_json::json!([
+                {
+                    "pair": "BTC/DOT",
+                    "feeds": {
+                        "kraken": [[
+                            { "pair": "BTC/USD", "inverted": false },
+                            { "pair": "DOT/USD", "inverted": true }
+                        ]],
+                        "coingecko": [[
+                            { "pair": "DOT=polkadot/USD", "inverted": true },
+                            { "pair": "BTC=bitcoin/USD", "inverted": false }
+                        ]]
+                    }
+                },
+                {
+                    "pair": "BTC/INTR",
+                    "value": 1.0,
+                    "feeds": {}
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn should_collect_all_currencies() {
+        let config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [
+                {
+                    "pair": ["BTC", "KSM"],
+                    "feeds": {
+                        "kraken": [["BTC", "USD"], ["KSM", "USD"]],
+                        "coingecko": [["KSM=kusama", "BTC"]]
+                    }
+                },
+                {
+                    "pair": ["BTC", "INTR"],
+                    "groups": {
+                        "gateio": [
+                            [["BTC", "USDT"], ["INTR", "USDT"]],
+                            [["BTC", "ETH"], ["ETH", "INTR"]]
+                        ]
+                    }
+                },
+                {
+                    "pair": ["BTC", "KINT"],
+                    "value": 1.0
+                }
+            ],
+            "feeds": {
+                "gateio": { "enabled": false }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config.all_currencies().iter().map(currency_name).collect::<Vec<_>>(),
+            vec!["BTC", "ETH", "INTR", "KINT", "KSM", "KSM=kusama", "USD", "USDT"]
+        );
+    }
+
+    #[test]
+    fn should_hash_config() {
+        let config = r#"{
+            "currencies": { "BTC": { "name": "Bitcoin", "decimals": 8 } },
+            "prices": [{ "pair": ["BTC", "USD"], "feeds": { "kraken": [["BTC", "USD"]] } }]
+        }"#;
+        let hash = OracleConfig::from_json(config).unwrap().hash;
+        assert_eq!(hash.len(), CONFIG_HASH_SIZE * 2);
+
+        // reloading the same config gives the same hash, regardless of formatting
+        assert_eq!(OracleConfig::from_json(config).unwrap().hash, hash);
+        let reordered = r#"{"prices":[{"feeds":{"kraken":[["BTC","USD"]]},"pair":["BTC","USD"]}],
+            "currencies":{"BTC":{"decimals":8,"name":"Bitcoin"}}}"#;
+        assert_eq!(OracleConfig::from_json(reordered).unwrap().hash, hash);
+
+        let changed = config.replace("kraken", "gateio");
+        assert_ne!(OracleConfig::from_json(&changed).unwrap().hash, hash);
+    }
+
+    #[test]
+    fn should_parse_and_validate_config_string() {
+        let oracle_config = OracleConfig::from_str_with_format(
+            r#"{
+                "currencies": {
+                    "BTC": { "name": "Bitcoin", "decimals": 8 },
+                    "USD": { "name": "Dollar", "decimals": 2 }
+                },
+                "prices": [{ "pair": ["BTC", "USD"], "feeds": { "kraken": [["BTC", "USD"]] } }]
+            }"#,
+            ConfigFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(oracle_config.prices.len(), 1);
+
+        // the path does not start at either currency of the pair
+        assert!(matches!(
+            OracleConfig::from_str_with_format(
+                r#"{
+                    "currencies": {},
+                    "prices": [{ "pair": ["BTC", "USD"], "feeds": { "kraken": [["DOT", "USD"]] } }]
+                }"#,
+                ConfigFormat::Json,
+            ),
+            Err(Error::InvalidConfig(err)) if matches!(err.error, ConfigError::NoStart)
+        ));
+        assert!(matches!(
+            OracleConfig::from_str_with_format("{", ConfigFormat::Json),
+            Err(Error::SerdeJsonError(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_too_many_decimals() {
+        let config = |decimals: u32| {
+            serde_json::json!({
+                "currencies": {
+                    "BTC": { "name": "Bitcoin", "decimals": 8 },
+                    "USD": { "name": "Dollar", "decimals": decimals }
+                },
+                "prices": []
+            })
+            .to_string()
+        };
+
+        let oracle_config = OracleConfig::from_str_with_format(&config(36), ConfigFormat::Json).unwrap();
+        assert!(oracle_config.validate_currencies().is_ok());
+
+        let err = OracleConfig::from_str_with_format(&config(37), ConfigFormat::Json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid currency config: Decimals of USD must be at most 36, got 37"
+        );
+    }
+
+    #[test]
+    fn should_fall_back_to_canonical_decimals() {
+        let currency_config = serde_json::from_value::<CurrencyConfig>(serde_json::json!({
+            "name": "Bitcoin",
+            "decimals": 8,
+            "feed_decimals": { "kraken": 10 }
+        }))
+        .unwrap();
+        assert_eq!(currency_config.decimals_for(&FeedName::Kraken), 10);
+        assert_eq!(currency_config.decimals_for(&FeedName::GateIo), 8);
+    }
+
+    #[test]
+    fn should_merge_layered_configs() {
+        let write = |name: &str, value: serde_json::Value| {
+            let path = std::env::temp_dir().join(format!("oracle-config-{}-{}.json", std::process::id(), name));
+            std::fs::write(&path, value.to_string()).unwrap();
+            path
+        };
+        let base = write(
+            "base",
+            serde_json::json!({
+                "currencies": {
+                    "BTC": { "name": "Bitcoin", "decimals": 8 },
+                    "USD": { "name": "Dollar", "decimals": 12 }
+                },
+                "prices": [{ "pair": ["BTC", "USD"], "feeds": { "kraken": [["BTC", "USD"]] } }]
+            }),
+        );
+        // adds a currency, changes decimals and replaces the feeds of a price
+        let production = write(
+            "production",
+            serde_json::json!({
+                "currencies": {
+                    "USD": { "name": "Dollar", "decimals": 6 },
+                    "DOT": { "name": "Polkadot", "decimals": 10 }
+                },
+                "prices": [
+                    { "pair": ["BTC", "USD"], "feeds": { "gateio": [["BTC", "USD"]] } },
+                    { "pair": ["DOT", "USD"], "feeds": { "kraken": [["DOT", "USD"]] } }
+                ]
+            }),
+        );
+        let emergency = write(
+            "emergency",
+            serde_json::json!({ "prices": [{ "pair": ["DOT", "USD"], "value": 5.0 }] }),
+        );
+        let invalid = write(
+            "invalid",
+            serde_json::json!({ "prices": [{ "pair": ["KSM", "USD"], "feeds": { "kraken": [["KSM", "USD"]] } }] }),
+        );
+
+        let oracle_config =
+            OracleConfig::load_layered(&[base.as_path(), production.as_path(), emergency.as_path()]).unwrap();
+        assert_eq!(
+            oracle_config.currencies.keys().collect::<Vec<_>>(),
+            vec!["BTC", "DOT", "USD"]
+        );
+        assert_eq!(oracle_config.currencies["USD"].decimals, 6);
+        assert_eq!(oracle_config.prices.len(), 2);
+        assert_eq!(
+            oracle_config.prices[0].feed_names(),
+            vec![FeedName::GateIo].into_iter().collect()
+        );
+        // the last layer wins
+        assert_eq!(oracle_config.prices[1].value, Some(5.0));
+        assert!(!oracle_config.prices[1].has_feeds());
+
+        // the hash is that of the merged config
+        let merged = write(
+            "merged",
+            serde_json::json!({
+                "currencies": {
+                    "BTC": { "name": "Bitcoin", "decimals": 8 },
+                    "DOT": { "name": "Polkadot", "decimals": 10 },
+                    "USD": { "name": "Dollar", "decimals": 6 }
+                },
+                "prices": [
+                    { "pair": ["BTC", "USD"], "feeds": { "gateio": [["BTC", "USD"]] } },
+                    { "pair": ["DOT", "USD"], "value": 5.0 }
+                ]
+            }),
+        );
+        assert_eq!(OracleConfig::from_path(&merged).unwrap().hash, oracle_config.hash);
+
+        // the order of the layers matters
+        let oracle_config =
+            OracleConfig::load_layered(&[base.as_path(), emergency.as_path(), production.as_path()]).unwrap();
+        assert_eq!(oracle_config.prices[1].value, None);
+
+        let base_only = OracleConfig::load_layered(&[base.as_path()]).unwrap();
+        assert_eq!(base_only.currencies["USD"].decimals, 12);
+        assert_ne!(base_only.hash, oracle_config.hash);
+
+        // the merged config is validated
+        assert!(matches!(
+            OracleConfig::load_layered(&[base.as_path(), invalid.as_path()]),
+            Err(ConfigError::UnknownCurrency(_))
+        ));
+        // but can be read as is to report all of its errors, including those of the layers
+        let unvalidated = OracleConfig::read_layered(&[base.as_path(), invalid.as_path()]).unwrap();
+        let report = unvalidated.validation_report(&BTreeSet::new());
+        assert_eq!(
+            report
+                .errors
+                .iter()
+                .map(|issue| issue.pair.as_str())
+                .collect::<Vec<_>>(),
+            vec!["BTC/USD", "KSM/USD"]
+        );
+        assert!(matches!(
+            OracleConfig::load_layered(&[]),
+            Err(ConfigError::NoConfigFile)
+        ));
+
+        for path in [base, production, emergency, invalid, merged] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn should_look_up_currency_config() {
+        let currency_store = serde_json::from_value::<CurrencyStore<String>>(serde_json::json!({
+            "BTC": { "name": "Bitcoin", "decimals": 8 }
+        }))
+        .unwrap();
+        let currency_config = currency_store.config(&"BTC".to_string()).unwrap();
+        assert_eq!(currency_config.name, "Bitcoin");
+        assert_eq!(currency_config.decimals, 8);
+        assert_eq!(currency_store.name(&"BTC".to_string()), Some("Bitcoin".to_string()));
+        assert_eq!(currency_store.decimals(&"BTC".to_string()), Some(8));
+
+        assert!(matches!(
+            currency_store.config(&"DOT".to_string()),
+            Err(Error::InvalidCurrency)
+        ));
+        assert_eq!(currency_store.decimals(&"DOT".to_string()), None);
+    }
+
+    #[test]
+    fn should_reject_unknown_currencies() {
+        let config = |price: serde_json::Value| {
+            serde_json::json!({
+                "currencies": {
+                    "BTC": { "name": "Bitcoin", "decimals": 8 },
+                    "USD": { "name": "Dollar", "decimals": 2 }
+                },
+                "prices": [price]
+            })
+            .to_string()
+        };
+
+        OracleConfig::from_str_with_format(
+            &config(serde_json::json!({ "pair": ["BTC", "USD"], "feeds": { "kraken": [["BTC=bitcoin", "USD"]] } })),
+            ConfigFormat::Json,
+        )
+        .expect("Config is valid");
+
+        for (price, currency) in [
+            (serde_json::json!({ "pair": ["DOT", "USD"], "value": 5.0 }), "DOT"),
+            (
+                serde_json::json!({
+                    "pair": ["BTC", "USD"],
+                    "feeds": { "kraken": [["BTC", "USDT"], ["USDT", "USD"]] }
+                }),
+                "USDT",
+            ),
+        ] {
+            let err = OracleConfig::from_str_with_format(&config(price), ConfigFormat::Json).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("Invalid currency config: Unknown currency {}", currency)
+            );
+        }
+    }
+
+    #[test]
+    fn should_serialize_validation_report() {
+        let oracle_config = serde_json::from_value::<OracleConfig>(serde_json::json!({
+            "currencies": {},
+            "prices": [
+                { "pair": ["BTC", "USD"], "feeds": { "kraken": [["DOT", "USD"]] } },
+                { "pair": ["DOT", "USD"], "feeds": { "gateio": [["DOT", "USD"]] } },
+                { "pair": ["KSM", "USD"], "feeds": { "coingecko": [["KSM", "USD"]] } }
+            ],
+            "feeds": { "gateio": { "enabled": false } }
+        }))
+        .unwrap();
+        let adapters = vec![FeedName::Kraken, FeedName::GateIo].into_iter().collect();
+
+        assert_eq!(
+            serde_json::to_value(oracle_config.validation_report(&adapters)).unwrap(),
+            serde_json::json!({
+                "errors": [
+                    { "pair": "BTC/USD", "feed": "kraken", "message": "No start" },
+                    { "pair": "KSM/USD", "feed": "coingecko", "message": "No adapter for coingecko" }
+                ],
+                "warnings": [
+                    { "pair": "DOT/USD", "message": "All feeds are disabled" }
+                ]
+            })
+        );
+    }
 }
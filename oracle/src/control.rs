@@ -0,0 +1,180 @@
+use crate::currency::{Currency, CurrencyPairAndPrice};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+use warp::{http::StatusCode, Filter, Rejection, Reply};
+
+/// Halts publishing to all sinks, e.g. during a market-wide incident. Prices
+/// are still computed while paused and the withheld prices are reported.
+#[derive(Debug, Default)]
+pub struct PublishControl {
+    paused: AtomicBool,
+    withheld: RwLock<Vec<String>>,
+    config_hash: RwLock<String>,
+}
+
+impl PublishControl {
+    pub fn new(paused: bool) -> Self {
+        if paused {
+            log::warn!("Publishing is paused on startup");
+        }
+        Self {
+            paused: AtomicBool::new(paused),
+            ..Default::default()
+        }
+    }
+
+    pub fn pause_all(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        log::warn!("Publishing paused");
+    }
+
+    pub fn resume_all(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.withheld.write().unwrap_or_else(|err| err.into_inner()).clear();
+        log::info!("Publishing resumed");
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Record the prices which would have been published while paused.
+    pub fn withhold(&self, prices: &[CurrencyPairAndPrice<Currency>]) {
+        let withheld = prices.iter().map(ToString::to_string).collect::<Vec<_>>();
+        log::info!("Publishing is paused, withholding {}", withheld.join(", "));
+        *self.withheld.write().unwrap_or_else(|err| err.into_inner()) = withheld;
+    }
+
+    /// The prices of the last update while paused.
+    pub fn withheld(&self) -> Vec<String> {
+        self.withheld.read().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Record the hash of the active config, see `OracleConfig::hash`.
+    pub fn set_config_hash(&self, config_hash: &str) {
+        *self.config_hash.write().unwrap_or_else(|err| err.into_inner()) = config_hash.to_string();
+    }
+
+    pub fn config_hash(&self) -> String {
+        self.config_hash.read().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+}
+
+fn reply_status(control: &PublishControl, authorized: bool, action: impl FnOnce(&PublishControl)) -> impl Reply {
+    if !authorized {
+        return warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({ "error": "unauthorized" })),
+            StatusCode::UNAUTHORIZED,
+        );
+    }
+    action(control);
+    warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({
+            "paused": control.is_paused(),
+            "withheld": control.withheld(),
+            "config_hash": control.config_hash(),
+        })),
+        StatusCode::OK,
+    )
+}
+
+/// Routes to pause, resume and get the status of publishing. Requests must
+/// have the header `Authorization: Bearer <token>`, if no token is set
+/// all requests are rejected.
+fn control_routes(
+    control: Arc<PublishControl>,
+    token: Option<String>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let expected = token.map(|token| format!("Bearer {}", token));
+    let authorized = warp::header::optional::<String>("authorization").map(move |header: Option<String>| {
+        matches!((&expected, header), (Some(expected), Some(header)) if *expected == header)
+    });
+    let with_control = warp::any().map(move || control.clone());
+
+    let pause = warp::post()
+        .and(warp::path!("publishing" / "pause"))
+        .and(with_control.clone())
+        .and(authorized.clone())
+        .map(|control: Arc<PublishControl>, authorized| reply_status(&control, authorized, PublishControl::pause_all));
+    let resume = warp::post()
+        .and(warp::path!("publishing" / "resume"))
+        .and(with_control.clone())
+        .and(authorized.clone())
+        .map(|control: Arc<PublishControl>, authorized| reply_status(&control, authorized, PublishControl::resume_all));
+    let status = warp::get()
+        .and(warp::path!("publishing" / "status"))
+        .and(with_control)
+        .and(authorized)
+        .map(|control: Arc<PublishControl>, authorized| reply_status(&control, authorized, |_| ()));
+    pause.or(resume).or(status)
+}
+
+/// Serve the control routes on the given port until the process exits.
+pub fn serve(control: Arc<PublishControl>, token: Option<String>, port: u16) {
+    let routes = control_routes(control, token);
+    log::info!(
+        "Serving publishing controls at http://{}:{}/publishing",
+        Ipv4Addr::LOCALHOST,
+        port
+    );
+    tokio::task::spawn(async move {
+        warp::serve(routes)
+            .run(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port))
+            .await;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::price;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn should_pause_and_resume_publishing() {
+        let control = Arc::new(PublishControl::new(true));
+        let routes = control_routes(control.clone(), Some("secret".to_string()));
+        let request = |method: &str, path: &str, authorization: &str| {
+            warp::test::request()
+                .method(method)
+                .path(path)
+                .header("authorization", authorization)
+        };
+
+        let response = request("POST", "/publishing/resume", "Bearer wrong")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(control.is_paused());
+
+        control.withhold(&[price("BTC", "USD", 20000.0)]);
+        control.set_config_hash("0a1b2c3d");
+        let response = request("GET", "/publishing/status", "Bearer secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(
+            serde_json::from_slice::<Value>(response.body()).unwrap(),
+            serde_json::json!({ "paused": true, "withheld": ["BTC/USD => 20000"], "config_hash": "0a1b2c3d" })
+        );
+
+        // the hash follows config reloads
+        control.set_config_hash("4e5f6a7b");
+
+        let response = request("POST", "/publishing/resume", "Bearer secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(
+            serde_json::from_slice::<Value>(response.body()).unwrap(),
+            serde_json::json!({ "paused": false, "withheld": [], "config_hash": "4e5f6a7b" })
+        );
+        request("POST", "/publishing/pause", "Bearer secret")
+            .reply(&routes)
+            .await;
+        assert!(control.is_paused());
+    }
+}
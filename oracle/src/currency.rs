@@ -1,9 +1,15 @@
 #![allow(clippy::upper_case_acronyms)]
 
-use crate::{CurrencyStore, Error};
+use crate::{config::CurrencyConfig, error::CurrencyPairParseError, CurrencyStore, Error};
 use runtime::{FixedPointNumber, FixedPointTraits::*, FixedU128};
 use serde::Deserialize;
-use std::fmt::{self, Debug};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Debug},
+    str::FromStr,
+};
+
+const CURRENCY_PAIR_SEPARATOR: char = '/';
 
 pub trait ExchangeRate {
     fn invert(self) -> Self;
@@ -15,9 +21,91 @@ impl ExchangeRate for f64 {
     }
 }
 
+/// How published values are rounded to the precision of `FixedU128`.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Round half away from zero.
+    HalfUp,
+    /// Round half to the nearest even number (banker's rounding).
+    HalfEven,
+    /// Always round towards zero, like `FixedU128::from_float`.
+    #[default]
+    Down,
+    /// Always round away from zero.
+    Up,
+}
+
+impl RoundingMode {
+    pub fn round(self, value: f64) -> f64 {
+        match self {
+            Self::HalfUp => value.round(),
+            Self::HalfEven if (value - value.trunc()).abs() == 0.5 => 2.0 * (value / 2.0).round(),
+            Self::HalfEven => value.round(),
+            Self::Down => value.trunc(),
+            Self::Up if value.is_sign_negative() => value.floor(),
+            Self::Up => value.ceil(),
+        }
+    }
+
+    /// Round the `value` to the precision of `FixedU128`, returns `None`
+    /// if it is negative or does not fit.
+    pub fn to_fixed(self, value: f64) -> Option<FixedU128> {
+        let inner = self.round(value * <FixedU128 as FixedPointNumber>::DIV as f64);
+        if inner.is_finite() && inner >= 0.0 && inner < u128::MAX as f64 {
+            Some(FixedU128::from_inner(inner as u128))
+        } else {
+            None
+        }
+    }
+}
+
+/// Convert a human-readable `amount` of a currency with `decimals`, e.g.
+/// 0.5 BTC, into integer base units, e.g. 50000000 satoshi. Rounds half to
+/// even, fails if the amount is negative or does not fit.
+pub fn amount_to_base_units(decimals: u32, amount: f64) -> Result<u128, Error> {
+    let out_of_range = || Error::AmountOutOfRange { amount, decimals };
+    let factor = 10_u128.checked_pow(decimals).ok_or_else(out_of_range)?;
+    let scaled = RoundingMode::HalfEven.round(amount * factor as f64);
+    if scaled.is_finite() && scaled >= 0.0 && scaled < u128::MAX as f64 {
+        Ok(scaled as u128)
+    } else {
+        Err(out_of_range())
+    }
+}
+
+/// Convert integer base units of a currency with `decimals` into a
+/// human-readable amount, the inverse of `amount_to_base_units`.
+pub fn base_units_to_amount(decimals: u32, base_units: u128) -> Result<f64, Error> {
+    let factor = 10_u128.checked_pow(decimals).ok_or(Error::AmountOutOfRange {
+        amount: base_units as f64,
+        decimals,
+    })?;
+    Ok(base_units as f64 / factor as f64)
+}
+
 pub trait CurrencyInfo<Currency> {
-    fn name(&self, id: &Currency) -> Option<String>;
-    fn decimals(&self, id: &Currency) -> Option<u32>;
+    /// The whole config of the currency, to read several fields with a single lookup.
+    /// Fails with `InvalidCurrency` if the currency is not configured.
+    fn config(&self, id: &Currency) -> Result<&CurrencyConfig, Error>;
+
+    fn name(&self, id: &Currency) -> Option<String> {
+        self.config(id).ok().map(|currency_config| currency_config.name.clone())
+    }
+
+    fn decimals(&self, id: &Currency) -> Option<u32> {
+        self.config(id).ok().map(|currency_config| currency_config.decimals)
+    }
+
+    /// Convert a human-readable amount of the currency into its base units.
+    fn to_base_units(&self, id: &Currency, amount: f64) -> Result<u128, Error> {
+        amount_to_base_units(self.config(id)?.decimals, amount)
+    }
+
+    /// Convert base units of the currency into a human-readable amount.
+    fn from_base_units(&self, id: &Currency, base_units: u128) -> Result<f64, Error> {
+        base_units_to_amount(self.config(id)?.decimals, base_units)
+    }
 }
 
 #[derive(Default, Debug, Clone, Eq, PartialOrd, Ord)]
@@ -33,6 +121,14 @@ impl<'de> Deserialize<'de> for Currency {
     {
         use serde::de::Error;
         let value = String::deserialize(deserializer)?;
+        value.parse().map_err(|_| Error::custom("Invalid currency"))
+    }
+}
+
+impl FromStr for Currency {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
         match value.split('=').collect::<Vec<_>>()[..] {
             [symbol] => Ok(Self {
                 symbol: symbol.to_string(),
@@ -42,7 +138,7 @@ impl<'de> Deserialize<'de> for Currency {
                 symbol: symbol.to_string(),
                 path: Some(path.to_string()),
             }),
-            _ => Err(Error::custom("Invalid currency")),
+            _ => Err(Error::InvalidCurrency),
         }
     }
 }
@@ -57,6 +153,12 @@ impl Currency {
     }
 }
 
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol)
+    }
+}
+
 impl PartialEq for Currency {
     fn eq(&self, other: &Self) -> bool {
         // only compare symbols, path may differ
@@ -71,6 +173,10 @@ impl From<Currency> for String {
 }
 
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(
+    try_from = "CurrencyPairConfig<Currency>",
+    bound(deserialize = "Currency: Deserialize<'de> + PartialEq + fmt::Display")
+)]
 pub struct CurrencyPair<Currency> {
     /// This is the currency to **buy** - one unit.
     /// Also known as the "transaction" currency.
@@ -83,7 +189,46 @@ pub struct CurrencyPair<Currency> {
 
 impl fmt::Display for CurrencyPair<Currency> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {})", self.base.symbol(), self.quote.symbol())
+        write!(
+            f,
+            "{}{}{}",
+            self.base.symbol(),
+            CURRENCY_PAIR_SEPARATOR,
+            self.quote.symbol()
+        )
+    }
+}
+
+/// A pair as written in the config, which is checked by `CurrencyPair::new`.
+#[derive(Deserialize)]
+struct CurrencyPairConfig<Currency> {
+    base: Currency,
+    quote: Currency,
+}
+
+impl<Currency: PartialEq + fmt::Display> TryFrom<CurrencyPairConfig<Currency>> for CurrencyPair<Currency> {
+    type Error = Error;
+
+    fn try_from(config: CurrencyPairConfig<Currency>) -> Result<Self, Self::Error> {
+        Self::new(config.base, config.quote)
+    }
+}
+
+impl<Currency: PartialEq + fmt::Display> CurrencyPair<Currency> {
+    /// Fails if the base and quote are the same currency, which has no price.
+    pub fn new(base: Currency, quote: Currency) -> Result<Self, Error> {
+        if base == quote {
+            return Err(Error::DegeneratePair(base.to_string()));
+        }
+        Ok(Self::new_unchecked(base, quote))
+    }
+}
+
+impl<Currency> CurrencyPair<Currency> {
+    /// Build the pair without checking it, e.g. to test that an invalid
+    /// pair is rejected later on.
+    pub fn new_unchecked(base: Currency, quote: Currency) -> Self {
+        Self { base, quote }
     }
 }
 
@@ -98,8 +243,26 @@ impl<Currency: PartialEq> CurrencyPair<Currency> {
         &self.base == currency || &self.quote == currency
     }
 
-    pub fn has_shared(&self, currency_pair: &Self) -> bool {
-        self.contains(&currency_pair.base) || self.contains(&currency_pair.quote)
+    /// The currency which connects this pair to the `currency_pair`, the
+    /// base is preferred if both currencies are shared.
+    pub fn shared_currency(&self, currency_pair: &Self) -> Option<Currency>
+    where
+        Currency: Clone,
+    {
+        if currency_pair.contains(&self.base) {
+            Some(self.base.clone())
+        } else if currency_pair.contains(&self.quote) {
+            Some(self.quote.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn has_shared(&self, currency_pair: &Self) -> bool
+    where
+        Currency: Clone,
+    {
+        self.shared_currency(currency_pair).is_some()
     }
 
     pub fn invert(self) -> Self {
@@ -110,6 +273,77 @@ impl<Currency: PartialEq> CurrencyPair<Currency> {
     }
 }
 
+impl<Currency: Clone> CurrencyPair<Currency> {
+    /// Copy of this pair with the base replaced.
+    pub fn with_base(&self, base: Currency) -> Self {
+        Self {
+            base,
+            quote: self.quote.clone(),
+        }
+    }
+
+    /// Copy of this pair with the quote replaced.
+    pub fn with_quote(&self, quote: Currency) -> Self {
+        Self {
+            base: self.base.clone(),
+            quote,
+        }
+    }
+
+    /// Copy of this pair with the base and quote swapped.
+    pub fn inverse(&self) -> Self {
+        self.clone().invert()
+    }
+}
+
+impl<Currency: FromStr> CurrencyPair<Currency> {
+    /// Parse a pair in the compact `BASE/QUOTE` form.
+    ///
+    /// By default any whitespace is rejected since e.g. `BTC / USD` is
+    /// ambiguous. If `lenient` is set, whitespace surrounding either side
+    /// is trimmed instead, whitespace within a currency is always rejected.
+    /// Note that this form cannot express currency paths containing `/`.
+    pub fn parse(value: &str, lenient: bool) -> Result<Self, CurrencyPairParseError> {
+        let (base, quote) = match value.split(CURRENCY_PAIR_SEPARATOR).collect::<Vec<_>>()[..] {
+            [base, quote] => (base, quote),
+            _ => return Err(CurrencyPairParseError::InvalidSeparator),
+        };
+
+        let parse_currency = |currency: &str, empty_error| {
+            let trimmed = currency.trim();
+            if trimmed.is_empty() {
+                Err(empty_error)
+            } else if (!lenient && trimmed != currency) || trimmed.contains(char::is_whitespace) {
+                Err(CurrencyPairParseError::UnexpectedWhitespace)
+            } else {
+                trimmed
+                    .parse::<Currency>()
+                    .map_err(|_| CurrencyPairParseError::InvalidCurrency)
+            }
+        };
+
+        Ok(Self {
+            base: parse_currency(base, CurrencyPairParseError::EmptyBase)?,
+            quote: parse_currency(quote, CurrencyPairParseError::EmptyQuote)?,
+        })
+    }
+}
+
+impl<Currency: FromStr> FromStr for CurrencyPair<Currency> {
+    type Err = CurrencyPairParseError;
+
+    /// Strictly parse the `BASE/QUOTE` form, see `CurrencyPair::parse`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse(value, false)
+    }
+}
+
+impl fmt::Display for CurrencyPair<String> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.base, CURRENCY_PAIR_SEPARATOR, self.quote)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct CurrencyPairAndPrice<Currency> {
     pub pair: CurrencyPair<Currency>,
@@ -147,20 +381,26 @@ impl<Currency: Clone + PartialEq + Ord> CurrencyPairAndPrice<Currency> {
     /// 1 * 10**8 Satoshi = 3081 * 10**10 Planck
     /// 1 Satoshi = 3081 * 10**2 Planck
     /// 308100 = 3081 * (10**10 / 10**8) = 3081 * 10**2
+    ///
+    /// The result is rounded once with the `rounding_mode`, scaling up by
+    /// the conversion factor is exact.
     pub fn exchange_rate<Symbol: Ord + ToString + From<Currency>>(
         &self,
         currency_store: &CurrencyStore<Symbol>,
+        rounding_mode: RoundingMode,
     ) -> Result<FixedU128, Error> {
-        let quote_decimals = currency_store
-            .decimals(&self.pair.quote.clone().into())
-            .ok_or(Error::InvalidCurrency)?;
-        let base_decimals = currency_store
-            .decimals(&self.pair.base.clone().into())
-            .ok_or(Error::InvalidCurrency)?;
+        let quote_decimals = currency_store.config(&self.pair.quote.clone().into())?.decimals;
+        let base_decimals = currency_store.config(&self.pair.base.clone().into())?.decimals;
+        if quote_decimals < base_decimals {
+            let scaled = self.price / 10_f64.powi((base_decimals - quote_decimals) as i32);
+            return rounding_mode.to_fixed(scaled).ok_or(Error::InvalidExchangeRate);
+        }
         let conversion_factor =
             FixedU128::checked_from_rational(10_u128.pow(quote_decimals), 10_u128.pow(base_decimals))
                 .ok_or(Error::InvalidExchangeRate)?;
-        FixedU128::from_float(self.price)
+        rounding_mode
+            .to_fixed(self.price)
+            .ok_or(Error::InvalidExchangeRate)?
             .checked_mul(&conversion_factor)
             .ok_or(Error::InvalidExchangeRate)
     }
@@ -196,6 +436,67 @@ impl<Currency: Clone + PartialEq + Ord> CurrencyPairAndPrice<Currency> {
     }
 }
 
+/// The rate of a pair, which is chained along the legs of a route.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rate {
+    pub pair: CurrencyPair<Currency>,
+    /// Units of the quote per unit of the base.
+    pub value: f64,
+}
+
+impl Rate {
+    /// The rate of the inverse pair.
+    pub fn invert(&self) -> Rate {
+        Rate {
+            pair: self.pair.clone().invert(),
+            value: self.value.invert(),
+        }
+    }
+
+    /// Chains two rates which share exactly one currency, each is oriented so
+    /// that the shared currency cancels out, see `CurrencyPairAndPrice::reduce`.
+    /// Fails if the pairs are disjoint or share both currencies.
+    pub fn compose(&self, other: &Rate) -> Result<Rate, Error> {
+        let shares_both = self.pair.contains(&other.pair.base) && self.pair.contains(&other.pair.quote);
+        if !self.pair.has_shared(&other.pair) || shares_both {
+            return Err(Error::CannotCompose(self.pair.clone(), other.pair.clone()));
+        }
+        Ok(CurrencyPairAndPrice::from(self.clone())
+            .reduce(other.clone().into())
+            .into())
+    }
+}
+
+impl From<CurrencyPairAndPrice<Currency>> for Rate {
+    fn from(currency_pair_and_price: CurrencyPairAndPrice<Currency>) -> Self {
+        Self {
+            pair: currency_pair_and_price.pair,
+            value: currency_pair_and_price.price,
+        }
+    }
+}
+
+impl From<Rate> for CurrencyPairAndPrice<Currency> {
+    fn from(rate: Rate) -> Self {
+        Self {
+            pair: rate.pair,
+            price: rate.value,
+        }
+    }
+}
+
+/// The price of `base` in `quote`, e.g. `price("BTC", "USD", 20000.0)`.
+#[cfg(test)]
+pub fn price(base: &str, quote: &str, price: f64) -> CurrencyPairAndPrice<Currency> {
+    CurrencyPairAndPrice {
+        pair: CurrencyPair {
+            base: base.parse().unwrap(),
+            quote: quote.parse().unwrap(),
+        },
+        price,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::config::CurrencyConfig;
@@ -223,6 +524,180 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_compose_rates_in_any_orientation() {
+        let rate = |base, quote, value| Rate::from(price(base, quote, value));
+        // 1 DOT = 4 USD, 1 INTR = 0.03125 USD
+        let dots = [rate("USD", "DOT", 0.25), rate("DOT", "USD", 4.0)];
+        let intrs = [rate("USD", "INTR", 32.0), rate("INTR", "USD", 0.03125)];
+        for dot in &dots {
+            for intr in &intrs {
+                for (left, right) in [(dot, intr), (intr, dot)] {
+                    let mut composed = left.compose(right).unwrap();
+                    if composed.pair.base.symbol() != "DOT" {
+                        composed = composed.invert();
+                    }
+                    assert_eq!(composed, rate("DOT", "INTR", 128.0), "{:?} with {:?}", left, right);
+                }
+            }
+        }
+
+        assert_eq!(rate("USD", "DOT", 0.25).invert(), rate("DOT", "USD", 4.0));
+        assert!(matches!(
+            rate("USD", "DOT", 0.25).compose(&rate("BTC", "INTR", 500000.0)),
+            Err(Error::CannotCompose(..))
+        ));
+        assert!(matches!(
+            rate("USD", "DOT", 0.25).compose(&rate("DOT", "USD", 4.0)),
+            Err(Error::CannotCompose(..))
+        ));
+    }
+
+    #[test]
+    fn should_convert_base_units() {
+        let currency_store = serde_json::from_value::<CurrencyStore<String>>(serde_json::json!({
+            "BTC": { "name": "Bitcoin", "decimals": 8 },
+            "JPY": { "name": "Yen", "decimals": 0 },
+        }))
+        .unwrap();
+        let btc = "BTC".to_string();
+        let jpy = "JPY".to_string();
+
+        for (id, amount, base_units) in [
+            (&btc, 0.5, 50_000_000),
+            (&btc, 0.1, 10_000_000),
+            (&btc, 21_000_000.0, 2_100_000_000_000_000),
+            (&btc, 0.00000001, 1),
+            (&jpy, 0.0, 0),
+            (&jpy, 1234.0, 1234),
+        ] {
+            assert_eq!(currency_store.to_base_units(id, amount).unwrap(), base_units);
+            assert_eq!(currency_store.from_base_units(id, base_units).unwrap(), amount);
+        }
+
+        // half to even
+        assert_eq!(currency_store.to_base_units(&jpy, 2.5).unwrap(), 2);
+        assert_eq!(currency_store.to_base_units(&jpy, 3.5).unwrap(), 4);
+        assert_eq!(currency_store.to_base_units(&btc, 0.000000025).unwrap(), 2);
+
+        assert!(matches!(
+            currency_store.to_base_units(&jpy, -1.0),
+            Err(Error::AmountOutOfRange { .. })
+        ));
+        assert!(matches!(
+            currency_store.to_base_units(&btc, 1e40),
+            Err(Error::AmountOutOfRange { .. })
+        ));
+        assert!(matches!(
+            currency_store.to_base_units(&"DOT".to_string(), 1.0),
+            Err(Error::InvalidCurrency)
+        ));
+        // 10^39 does not fit into a u128
+        assert!(matches!(
+            amount_to_base_units(39, 1.0),
+            Err(Error::AmountOutOfRange { .. })
+        ));
+        assert!(matches!(
+            base_units_to_amount(39, 1),
+            Err(Error::AmountOutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn should_reject_degenerate_pairs() {
+        assert!(matches!(
+            CurrencyPair::new("BTC", "BTC"),
+            Err(Error::DegeneratePair(currency)) if currency == "BTC"
+        ));
+        // the path is ignored when comparing currencies
+        assert!(matches!(
+            CurrencyPair::<Currency>::new("KSM=kusama".parse().unwrap(), "KSM".parse().unwrap()),
+            Err(Error::DegeneratePair(currency)) if currency == "KSM"
+        ));
+        assert_eq!(
+            CurrencyPair::new("BTC", "USD").unwrap(),
+            CurrencyPair {
+                base: "BTC",
+                quote: "USD"
+            }
+        );
+        assert_eq!(
+            CurrencyPair::new_unchecked("BTC", "BTC"),
+            CurrencyPair {
+                base: "BTC",
+                quote: "BTC"
+            }
+        );
+
+        // pairs are checked when the config is parsed
+        let parse = |value| serde_json::from_value::<CurrencyPair<Currency>>(value);
+        assert!(parse(serde_json::json!(["BTC", "USD"])).is_ok());
+        assert!(parse(serde_json::json!({ "base": "BTC", "quote": "USD" })).is_ok());
+        let err = parse(serde_json::json!(["BTC", "BTC"])).unwrap_err();
+        assert_eq!(err.to_string(), "Base and quote are both BTC");
+    }
+
+    #[test]
+    fn should_get_inverse_currency_pair() {
+        let currency_pair = CurrencyPair::<Currency> {
+            base: "KSM=kusama".parse().unwrap(),
+            quote: "BTC".parse().unwrap(),
+        };
+        let inverse = currency_pair.inverse();
+        assert_eq!(inverse.base.symbol(), "BTC");
+        assert_eq!(inverse.quote.path(), Some("kusama".to_string()));
+        assert_eq!(inverse.inverse(), currency_pair);
+    }
+
+    #[test]
+    fn should_display_currency_pair_like_config() {
+        let currency_pair = CurrencyPair::<Currency> {
+            base: "KSM=kusama".parse().unwrap(),
+            quote: "BTC".parse().unwrap(),
+        };
+        assert_eq!(currency_pair.to_string(), "KSM/BTC");
+        assert_eq!(
+            CurrencyPair::<String>::from_str("KSM/BTC").unwrap().to_string(),
+            currency_pair.to_string()
+        );
+    }
+
+    #[test]
+    fn should_replace_base_or_quote() {
+        let currency_pair = CurrencyPair::<Currency> {
+            base: "KSM=kusama".parse().unwrap(),
+            quote: "BTC=bitcoin".parse().unwrap(),
+        };
+
+        let with_base = currency_pair.with_base("DOT=polkadot".parse().unwrap());
+        assert_eq!(with_base.base.symbol(), "DOT");
+        assert_eq!(with_base.base.path(), Some("polkadot".to_string()));
+        assert_eq!(with_base.quote.symbol(), "BTC");
+        assert_eq!(with_base.quote.path(), Some("bitcoin".to_string()));
+
+        let with_quote = currency_pair.with_quote("USD".parse().unwrap());
+        assert_eq!(with_quote.base.symbol(), "KSM");
+        assert_eq!(with_quote.base.path(), Some("kusama".to_string()));
+        assert_eq!(with_quote.quote.symbol(), "USD");
+        assert_eq!(with_quote.quote.path(), None);
+
+        // the original pair is unchanged
+        assert_eq!(currency_pair.base.symbol(), "KSM");
+        assert_eq!(currency_pair.quote.symbol(), "BTC");
+    }
+
+    #[test]
+    fn should_get_shared_currency() {
+        let pair = |base, quote| CurrencyPair { base, quote };
+        assert_eq!(pair("BTC", "USD").shared_currency(&pair("BTC", "DOT")), Some("BTC"));
+        assert_eq!(pair("BTC", "USD").shared_currency(&pair("DOT", "BTC")), Some("BTC"));
+        assert_eq!(pair("DOT", "USD").shared_currency(&pair("BTC", "USD")), Some("USD"));
+        assert_eq!(pair("BTC", "USD").shared_currency(&pair("USD", "BTC")), Some("BTC"));
+        assert_eq!(pair("BTC", "USD").shared_currency(&pair("DOT", "KSM")), None);
+        assert!(pair("BTC", "USD").has_shared(&pair("DOT", "BTC")));
+        assert!(!pair("BTC", "USD").has_shared(&pair("DOT", "KSM")));
+    }
+
     macro_rules! assert_reduce {
         (
             ($left_base:tt / $left_quote:tt @ $left_price:tt)
@@ -299,6 +774,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_parse_currency_pair() {
+        assert_eq!(
+            CurrencyPair::<String>::parse("BTC/USD", false),
+            Ok(CurrencyPair {
+                base: "BTC".to_string(),
+                quote: "USD".to_string()
+            })
+        );
+
+        let currency_pair = CurrencyPair::<Currency>::parse("USD/STDOT=Moonbeam/0xFA36", false);
+        assert_eq!(currency_pair, Err(CurrencyPairParseError::InvalidSeparator));
+
+        let currency_pair = CurrencyPair::<Currency>::parse("USD/KSM=kusama", false).unwrap();
+        assert_eq!(currency_pair.quote.symbol(), "KSM");
+        assert_eq!(currency_pair.quote.path(), Some("kusama".to_string()));
+    }
+
+    #[test]
+    fn should_reject_malformed_currency_pair() {
+        let parse = |value| CurrencyPair::<String>::parse(value, false);
+        assert_eq!(parse("BTCUSD"), Err(CurrencyPairParseError::InvalidSeparator));
+        assert_eq!(parse("BTC/USD/DOT"), Err(CurrencyPairParseError::InvalidSeparator));
+        assert_eq!(parse(" /USD"), Err(CurrencyPairParseError::EmptyBase));
+        assert_eq!(parse("/USD"), Err(CurrencyPairParseError::EmptyBase));
+        assert_eq!(parse("BTC/"), Err(CurrencyPairParseError::EmptyQuote));
+        assert_eq!(parse("BTC / USD"), Err(CurrencyPairParseError::UnexpectedWhitespace));
+        assert_eq!(parse("B TC/USD"), Err(CurrencyPairParseError::UnexpectedWhitespace));
+    }
+
+    #[test]
+    fn should_round_trip_currency_pair() {
+        assert_eq!(
+            CurrencyPair::<String>::from_str("BTC/USD").unwrap().to_string(),
+            "BTC/USD"
+        );
+        assert_eq!(
+            "KSM=kusama/BTC".parse::<CurrencyPair<String>>().unwrap().to_string(),
+            "KSM=kusama/BTC"
+        );
+        // parsing is strict
+        assert_eq!(
+            "BTC / USD".parse::<CurrencyPair<String>>(),
+            Err(CurrencyPairParseError::UnexpectedWhitespace)
+        );
+    }
+
+    #[test]
+    fn should_trim_currency_pair_if_lenient() {
+        let parse = |value| CurrencyPair::<String>::parse(value, true);
+        let expected = Ok(CurrencyPair {
+            base: "BTC".to_string(),
+            quote: "USD".to_string(),
+        });
+        assert_eq!(parse("BTC / USD"), expected);
+        assert_eq!(parse(" BTC/USD\t"), expected);
+        assert_eq!(parse(" /USD"), Err(CurrencyPairParseError::EmptyBase));
+        assert_eq!(parse("BTC/ "), Err(CurrencyPairParseError::EmptyQuote));
+        assert_eq!(parse("B TC/USD"), Err(CurrencyPairParseError::UnexpectedWhitespace));
+    }
+
     #[test]
     fn should_calculate_exchange_rate() {
         let mut currency_store = CurrencyStore::new();
@@ -307,6 +843,7 @@ mod tests {
             CurrencyConfig {
                 name: format!("Bitcoin"),
                 decimals: 8,
+                feed_decimals: Default::default(),
             },
         );
         currency_store.insert(
@@ -314,6 +851,7 @@ mod tests {
             CurrencyConfig {
                 name: format!("Kusama"),
                 decimals: 12,
+                feed_decimals: Default::default(),
             },
         );
 
@@ -325,9 +863,71 @@ mod tests {
                 },
                 price: 453.4139805666768,
             }
-            .exchange_rate(&currency_store)
+            .exchange_rate(&currency_store, RoundingMode::HalfUp)
             .unwrap(),
             FixedU128::from_inner(4534139805666767667200000)
         );
     }
+
+    #[test]
+    fn should_round_boundary_values() {
+        let round = |mode: RoundingMode| [2.5, 3.5, -2.5, 2.4, 2.6].map(|value| mode.round(value));
+        assert_eq!(round(RoundingMode::HalfUp), [3.0, 4.0, -3.0, 2.0, 3.0]);
+        assert_eq!(round(RoundingMode::HalfEven), [2.0, 4.0, -2.0, 2.0, 3.0]);
+        assert_eq!(round(RoundingMode::Down), [2.0, 3.0, -2.0, 2.0, 2.0]);
+        assert_eq!(round(RoundingMode::Up), [3.0, 4.0, -3.0, 3.0, 3.0]);
+
+        // the smallest unit of FixedU128 is 10**-18
+        let to_inner = |mode: RoundingMode| mode.to_fixed(2.5e-18).unwrap().into_inner();
+        assert_eq!(to_inner(RoundingMode::HalfUp), 3);
+        assert_eq!(to_inner(RoundingMode::HalfEven), 2);
+        assert_eq!(to_inner(RoundingMode::Down), 2);
+        assert_eq!(to_inner(RoundingMode::Up), 3);
+
+        assert_eq!(RoundingMode::HalfUp.to_fixed(-1.0), None);
+        assert_eq!(RoundingMode::HalfUp.to_fixed(f64::NAN), None);
+    }
+
+    #[test]
+    fn should_truncate_by_default() {
+        // published values are truncated unless another mode is configured
+        assert_eq!(RoundingMode::default(), RoundingMode::Down);
+        for value in [453.4139805666768, 2.5e-18, 0.1] {
+            assert_eq!(
+                RoundingMode::default().to_fixed(value),
+                Some(FixedU128::from_float(value))
+            );
+        }
+    }
+
+    #[test]
+    fn should_round_exchange_rate_once() {
+        let mut currency_store = CurrencyStore::new();
+        for (symbol, decimals) in [("KSM", 12), ("BTC", 8)] {
+            currency_store.insert(
+                symbol,
+                CurrencyConfig {
+                    name: symbol.to_string(),
+                    decimals,
+                    feed_decimals: Default::default(),
+                },
+            );
+        }
+        // 2.5e-14 KSM per BTC is 2.5 units of 10**-18 per Planck
+        let currency_pair_and_price = CurrencyPairAndPrice {
+            pair: CurrencyPair {
+                base: "KSM",
+                quote: "BTC",
+            },
+            price: 2.5e-14,
+        };
+        let exchange_rate = |mode| {
+            currency_pair_and_price
+                .exchange_rate(&currency_store, mode)
+                .unwrap()
+                .into_inner()
+        };
+        assert_eq!(exchange_rate(RoundingMode::HalfEven), 2);
+        assert_eq!(exchange_rate(RoundingMode::Up), 3);
+    }
 }
@@ -1,15 +1,17 @@
 #![allow(clippy::enum_variant_names)]
 
 use crate::{
+    config::PriceSource,
     currency::{Currency, CurrencyPair},
-    feeds::FeedName,
+    feeds::{FeedName, UnknownMarket},
 };
 use reqwest::Error as ReqwestError;
-use runtime::{Error as RuntimeError, SubxtError};
+use runtime::{prometheus::Error as PrometheusError, Error as RuntimeError, SubxtError};
 use serde_json::Error as SerdeJsonError;
 use std::{
     io::Error as IoError,
     num::{ParseFloatError, ParseIntError},
+    time::{Duration, SystemTime},
 };
 use thiserror::Error;
 
@@ -21,12 +23,78 @@ pub enum ConfigError<Currency> {
     NoEnd,
     #[error("No path from {0} to {1}")]
     NoPath(CurrencyPair<Currency>, CurrencyPair<Currency>),
+    #[error("No adapter for {0}")]
+    NoAdapterForFeed(FeedName),
+    #[error("Unknown feed {0}")]
+    UnknownFeed(String),
+    #[error("Path visits {0} more than once")]
+    CyclicPath(Currency),
+    #[error("The weights do not match the feeds")]
+    WeightsMismatch,
+    #[error("Weight {0} must be positive")]
+    InvalidWeight(f64),
+    #[error("Trim percentage {0} must be at least 0 and below 50")]
+    InvalidTrimPct(f64),
+    #[error("Path yields the reciprocal of the pair")]
+    WrongOrientation,
+    #[error(
+        "Decimals of {currency} must be at most {}, got {decimals}",
+        crate::config::MAX_DECIMALS
+    )]
+    DecimalsOutOfRange { currency: String, decimals: u32 },
+    #[error("Unknown currency {0}")]
+    UnknownCurrency(Currency),
+    #[error("Both a value and feeds are set")]
+    AmbiguousSource,
+    #[error("Neither a value nor feeds are set")]
+    NoSource,
+    #[error("The maximum age must be positive")]
+    ZeroMaxAge,
+    #[error("The minimum publish interval must be positive")]
+    ZeroPublishInterval,
+    #[error("{pair} has {count} paths, at most {max} are allowed")]
+    TooManyFeeds {
+        pair: CurrencyPair<Currency>,
+        count: usize,
+        max: usize,
+    },
+    #[error("No config file")]
+    NoConfigFile,
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    InvalidPrices(Vec<PriceConfigError<crate::currency::Currency>>),
+
+    #[error("SerdeJsonError: {0}")]
+    SerdeJsonError(#[from] SerdeJsonError),
+    #[error("IoError: {0}")]
+    IoError(#[from] IoError),
+}
+
+/// Issues which do not prevent the config from being used.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ConfigWarning {
+    #[error("All feeds are disabled")]
+    AllFeedsDisabled,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CurrencyPairParseError {
+    #[error("Expected exactly one separator")]
+    InvalidSeparator,
+    #[error("Base is empty")]
+    EmptyBase,
+    #[error("Quote is empty")]
+    EmptyQuote,
+    #[error("Unexpected whitespace")]
+    UnexpectedWhitespace,
+    #[error("Invalid currency")]
+    InvalidCurrency,
 }
 
 #[derive(Error, Debug)]
-#[error("{feed}: {pair} => {error}")]
+#[error("{}{pair} => {error}", feed.as_ref().map(|feed| format!("{}: ", feed)).unwrap_or_default())]
 pub struct PriceConfigError<Currency> {
-    pub(crate) feed: FeedName,
+    /// The feed at fault, if the error is not about the price as a whole.
+    pub(crate) feed: Option<FeedName>,
     pub(crate) pair: CurrencyPair<Currency>,
     pub(crate) error: ConfigError<Currency>,
 }
@@ -41,12 +109,46 @@ pub enum Error {
     InvalidExchangeRate,
     #[error("Invalid currency")]
     InvalidCurrency,
+    #[error("{amount} does not fit into base units with {decimals} decimals")]
+    AmountOutOfRange { amount: f64, decimals: u32 },
+    #[error("Base and quote are both {0}")]
+    DegeneratePair(String),
+    #[error("Cannot compose {0} with {1}, they must share exactly one currency")]
+    CannotCompose(CurrencyPair<Currency>, CurrencyPair<Currency>),
     #[error("Invalid config: {0}")]
     InvalidConfig(Box<PriceConfigError<Currency>>),
+    #[error("Invalid currency config: {0}")]
+    InvalidCurrencyConfig(Box<ConfigError<Currency>>),
+    #[error("Invalid config: {0}")]
+    InvalidLayeredConfig(Box<ConfigError<Currency>>),
     #[error("{0} not configured")]
     NotConfigured(FeedName),
+    #[error("{0} timed out")]
+    FeedTimeout(FeedName),
+    #[error("Price source {0:?} not supported")]
+    UnsupportedPriceSource(PriceSource),
+    #[error("Unknown markets: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    UnknownMarkets(Vec<UnknownMarket>),
     #[error("Invalid dia symbol. Base must be USD & quote must be <symbol>=<id>. E.g. STDOT=Moonbeam/0xFA36Fe1dA08C89eC72Ea1F0143a35bFd5DAea108")]
     InvalidDiaSymbol,
+    #[error("Invalid aggregator symbol. Base must be <symbol>=<contract address>. E.g. ETH=0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419")]
+    InvalidAggregatorSymbol,
+    #[error("Trading is halted on {0}")]
+    SymbolHalted(String),
+    #[error("Aggregator answers are denominated in {denomination}, not {quote}")]
+    AggregatorDenominationMismatch { denomination: String, quote: String },
+    #[error("Aggregator answer is stale, last updated at {0}")]
+    StaleAggregatorAnswer(u64),
+    #[error("Timestamp is {0:?} ahead of the local clock")]
+    ClockSkew(Duration),
+    #[error("Aggregated price {0} is not positive")]
+    InvalidAggregate(f64),
+    #[error("All feeds deviate by more than {0}% from the median")]
+    AllFeedsDeviate(f64),
+    #[error("All feed prices were observed before {0:?}")]
+    AllPricesBeforeCutoff(SystemTime),
+    #[error("Price was last updated {age:?} ago, at most {max_age:?} is allowed")]
+    StalePrice { age: Duration, max_age: Duration },
 
     #[error("ReqwestError: {0}")]
     ReqwestError(#[from] ReqwestError),
@@ -62,4 +164,6 @@ pub enum Error {
     SerdeJsonError(#[from] SerdeJsonError),
     #[error("IoError: {0}")]
     IoError(#[from] IoError),
+    #[error("PrometheusError: {0}")]
+    PrometheusError(#[from] PrometheusError),
 }
@@ -1,3 +1,4 @@
+mod aggregator;
 mod blockcypher;
 mod blockstream;
 mod coingecko;
@@ -6,18 +7,32 @@ mod gateio;
 mod kraken;
 
 use crate::{
-    config::{CurrencyStore, PriceConfig},
+    config::{
+        Aggregation, ConversionConfig, CurrencyStore, PathHop, PlausibleRangeConfig, PriceConfig, PriceSource,
+        TimestampConfig,
+    },
     currency::*,
+    identical::IdenticalFeeds,
+    metrics::FeedMetrics,
     Error,
 };
 use async_trait::async_trait;
-use futures::future::join_all;
+use futures::{future::join_all, FutureExt};
 use reqwest::Url;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use statrs::statistics::{Data, OrderStatistics};
-use std::{collections::BTreeMap, fmt};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::Infallible,
+    fmt,
+    panic::AssertUnwindSafe,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use tokio::time::{timeout, Instant};
 
+pub use aggregator::{AggregatorApi, AggregatorCli};
 pub use blockcypher::{BlockCypherApi, BlockCypherCli};
 pub use blockstream::{BlockstreamApi, BlockstreamCli};
 pub use coingecko::{CoinGeckoApi, CoinGeckoCli};
@@ -31,44 +46,276 @@ pub async fn get_http(url: Url) -> Result<Value, Error> {
     Ok(reqwest::get(url).await?.error_for_status()?.json::<Value>().await?)
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
-#[serde(rename_all = "lowercase")]
+/// Names are matched case-insensitively and printed in lower case, an
+/// unrecognized name parses as `Unknown` so that it is reported when
+/// validating the config.
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[serde(from = "String", into = "String")]
 pub enum FeedName {
     Kraken,
     GateIo,
     CoinGecko,
     Dia,
+    OnChainAggregator,
+    Unknown(String),
+}
+
+impl FeedName {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Kraken => "kraken",
+            Self::GateIo => "gateio",
+            Self::CoinGecko => "coingecko",
+            Self::Dia => "dia",
+            Self::OnChainAggregator => "onchainaggregator",
+            Self::Unknown(name) => name,
+        }
+    }
+}
+
+impl FromStr for FeedName {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.to_lowercase();
+        Ok(match name.as_str() {
+            "kraken" => Self::Kraken,
+            "gateio" => Self::GateIo,
+            "coingecko" => Self::CoinGecko,
+            "dia" => Self::Dia,
+            "onchainaggregator" => Self::OnChainAggregator,
+            _ => Self::Unknown(name),
+        })
+    }
+}
+
+impl From<String> for FeedName {
+    fn from(name: String) -> Self {
+        match name.parse() {
+            Ok(feed) => feed,
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl From<FeedName> for String {
+    fn from(name: FeedName) -> Self {
+        name.as_str().to_string()
+    }
 }
 
 impl fmt::Display for FeedName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.as_str())
     }
 }
 
 #[async_trait]
-trait PriceFeed {
+trait PriceFeed: Send + Sync {
     async fn get_price(
         &self,
         currency_pair: CurrencyPair<Currency>,
         currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
     ) -> Result<CurrencyPairAndPrice<Currency>, Error>;
+
+    /// The price and the time at which it was last updated according to the
+    /// feed, `None` if the feed does not report timestamps.
+    async fn get_timestamped_price(
+        &self,
+        currency_pair: CurrencyPair<Currency>,
+        currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
+    ) -> Result<(CurrencyPairAndPrice<Currency>, Option<SystemTime>), Error> {
+        let currency_pair_and_price = self.get_price(currency_pair, currency_store, price_source).await?;
+        Ok((currency_pair_and_price, None))
+    }
+
+    /// The name of the market used to fetch the `currency_pair`.
+    fn market_name(&self, _currency_pair: &CurrencyPair<Currency>) -> Option<String> {
+        None
+    }
+
+    /// All available markets, if the feed has an endpoint to list them.
+    async fn get_markets(&self) -> Result<Option<Vec<String>>, Error> {
+        Ok(None)
+    }
+
+    /// Feeds which do not report timestamps ignore the config.
+    fn set_timestamp_config(&mut self, _timestamp_config: TimestampConfig) {}
+}
+
+/// A price source which only fetches the price of a pair, e.g. a custom
+/// feed registered with `PriceFeeds::register`. Its prices are the last
+/// trade and are rescaled and aggregated like those of any other feed.
+#[async_trait]
+pub trait FeedClient: Send + Sync {
+    async fn fetch(&self, pair: &CurrencyPair<Currency>) -> Result<f64, Error>;
 }
 
-#[derive(Default)]
+/// Serves the prices of a `FeedClient` as a feed.
+struct ClientFeed<C>(C);
+
+#[async_trait]
+impl<C: FeedClient> PriceFeed for ClientFeed<C> {
+    async fn get_price(
+        &self,
+        currency_pair: CurrencyPair<Currency>,
+        _currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
+    ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+        if price_source != PriceSource::LastTrade {
+            return Err(Error::UnsupportedPriceSource(price_source));
+        }
+        let price = self.0.fetch(&currency_pair).await?;
+        Ok(CurrencyPairAndPrice {
+            pair: currency_pair,
+            price,
+        })
+    }
+}
+
+/// The price of a route of the `feed`, observed at the oldest timestamp
+/// reported for its legs, or once all of its legs were received if none
+/// of them report one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampedPrice {
+    pub value: f64,
+    pub observed_at: SystemTime,
+    pub feed: FeedName,
+}
+
+impl TimestampedPrice {
+    pub fn is_older_than(&self, cutoff: SystemTime) -> bool {
+        self.observed_at < cutoff
+    }
+}
+
+/// A market required by the config which is not listed by the feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownMarket {
+    pub feed: FeedName,
+    pub market: String,
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownMarket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: unknown market {}", self.feed, self.market)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean {}?)", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right = right.chars().collect::<Vec<_>>();
+    let mut distances = (0..=right.len()).collect::<Vec<_>>();
+    for (i, left_char) in left.chars().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous + usize::from(left_char != *right_char);
+            previous = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(previous + 1);
+        }
+    }
+    distances[right.len()]
+}
+
+fn closest_match<'a>(value: &str, candidates: &'a [String]) -> Option<&'a String> {
+    candidates
+        .iter()
+        .min_by_key(|candidate| edit_distance(&value.to_uppercase(), &candidate.to_uppercase()))
+}
+
+/// Rescale a price from a feed which reports the base or quote in amounts
+/// of its own precision (see `CurrencyConfig::feed_decimals`) to the
+/// canonical decimals of both currencies.
+fn rescale(
+    currency_store: &CurrencyStore<String>,
+    name: &FeedName,
+    mut currency_pair_and_price: CurrencyPairAndPrice<Currency>,
+) -> CurrencyPairAndPrice<Currency> {
+    let offset = |currency: &Currency| {
+        currency_store.config(&currency.symbol()).map_or(0, |currency_config| {
+            currency_config.decimals_for(name) as i32 - currency_config.decimals as i32
+        })
+    };
+    let exponent = offset(&currency_pair_and_price.pair.base) - offset(&currency_pair_and_price.pair.quote);
+    if exponent != 0 {
+        currency_pair_and_price.price *= 10_f64.powi(exponent);
+    }
+    currency_pair_and_price
+}
+
+/// Default time to wait for a single feed request.
+const DEFAULT_FEED_TIMEOUT: Duration = Duration::from_secs(60);
+
 pub struct PriceFeeds {
     currency_store: CurrencyStore<String>,
     feeds: BTreeMap<FeedName, Box<dyn PriceFeed>>,
+    price_sources: BTreeMap<FeedName, PriceSource>,
+    conversions: BTreeMap<FeedName, ConversionConfig>,
+    feed_timeout: Duration,
+    metrics: Option<FeedMetrics>,
 }
 
 impl PriceFeeds {
     pub fn new(currency_store: CurrencyStore<String>) -> Self {
         Self {
             currency_store,
-            ..Default::default()
+            feeds: BTreeMap::new(),
+            price_sources: BTreeMap::new(),
+            conversions: BTreeMap::new(),
+            feed_timeout: DEFAULT_FEED_TIMEOUT,
+            metrics: None,
         }
     }
 
+    pub fn set_feed_timeout(&mut self, feed_timeout: Duration) {
+        self.feed_timeout = feed_timeout;
+    }
+
+    /// Record the latency and timeouts of each feed request.
+    pub fn set_metrics(&mut self, metrics: FeedMetrics) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Names of all feeds with a registered adapter.
+    pub fn adapters(&self) -> BTreeSet<FeedName> {
+        self.feeds.keys().cloned().collect()
+    }
+
+    pub fn set_currency_store(&mut self, currency_store: CurrencyStore<String>) {
+        self.currency_store = currency_store;
+    }
+
+    /// Feeds without a price source use the last trade.
+    pub fn set_price_sources(&mut self, price_sources: BTreeMap<FeedName, PriceSource>) {
+        self.price_sources = price_sources;
+    }
+
+    /// Feeds without a timestamp config use the defaults.
+    pub fn set_timestamp_configs(&mut self, timestamp_configs: BTreeMap<FeedName, TimestampConfig>) {
+        for (name, feed) in self.feeds.iter_mut() {
+            feed.set_timestamp_config(timestamp_configs.get(name).copied().unwrap_or_default());
+        }
+    }
+
+    /// Feeds without a conversion fail on legs which they cannot price.
+    pub fn set_conversions(&mut self, conversions: BTreeMap<FeedName, ConversionConfig>) {
+        self.conversions = conversions;
+    }
+
+    /// Serve the feed `name` from the `client`, replacing any feed of that
+    /// name. A name which is not built in, such as `binance`, is accepted in
+    /// the config once a client is registered for it.
+    pub fn register(&mut self, name: FeedName, client: impl FeedClient + 'static) {
+        self.feeds.insert(name, Box::new(ClientFeed(client)));
+    }
+
     pub fn maybe_add_coingecko(&mut self, opts: CoinGeckoCli) {
         if let Some(api) = CoinGeckoApi::from_opts(opts) {
             log::info!("🔗 CoinGecko");
@@ -76,10 +323,17 @@ impl PriceFeeds {
         }
     }
 
+    pub fn maybe_add_aggregator(&mut self, opts: AggregatorCli) {
+        if let Some(api) = AggregatorApi::from_opts(opts) {
+            log::info!("🔗 On-chain aggregator");
+            self.feeds.insert(FeedName::OnChainAggregator, Box::new(api));
+        }
+    }
+
     pub fn maybe_add_dia(&mut self, opts: DiaCli) {
         if let Some(api) = DiaApi::from_opts(opts) {
             log::info!("🔗 Dia");
-            self.feeds.insert(FeedName::Dia, Box::new(api));
+            self.register(FeedName::Dia, api);
         }
     }
 
@@ -97,79 +351,459 @@ impl PriceFeeds {
         }
     }
 
-    async fn get_prices(
+    async fn fetch_price(
         &self,
-        price_config: PriceConfig<Currency>,
-    ) -> Result<Vec<CurrencyPairAndPrice<Currency>>, Error> {
-        let currency_pair = price_config.pair;
-        let currency_store = &self.currency_store;
-        Ok(join_all(
-            price_config
-                .feeds
-                .into_iter()
-                .map(|(name, route)| {
-                    self.feeds
-                        .get(&name)
-                        .map(|feed| (name.clone(), route, feed))
-                        .ok_or(Error::NotConfigured(name))
+        name: &FeedName,
+        feed: &dyn PriceFeed,
+        currency_pair: CurrencyPair<Currency>,
+    ) -> Result<(CurrencyPairAndPrice<Currency>, Option<SystemTime>), Error> {
+        let price_source = self.price_sources.get(name).copied().unwrap_or_default();
+        let start = Instant::now();
+        match timeout(
+            self.feed_timeout,
+            feed.get_timestamped_price(currency_pair, &self.currency_store, price_source),
+        )
+        .await
+        {
+            Ok(result) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.observe_latency(name, start.elapsed());
+                }
+                result.map(|(currency_pair_and_price, updated_at)| {
+                    (rescale(&self.currency_store, name, currency_pair_and_price), updated_at)
                 })
-                .collect::<Result<Vec<_>, Error>>()?
-                .into_iter()
-                .map(|(name, route, feed)| {
-                    let currency_pair = currency_pair.clone();
-                    async move {
-                        let mut currency_pair_and_price = if let Some(currency_pair_and_price) = join_all(
-                            route
-                                .into_iter()
-                                .map(|currency_pair| feed.get_price(currency_pair, currency_store)),
-                        )
-                        .await
-                        .into_iter()
-                        .collect::<Result<Vec<_>, Error>>()?
-                        .into_iter()
-                        .reduce(|left, right| left.reduce(right))
-                        {
-                            currency_pair_and_price
-                        } else {
-                            return Ok(None);
-                        };
-
-                        if currency_pair_and_price.pair.base != currency_pair.base {
-                            currency_pair_and_price = currency_pair_and_price.invert()
-                        }
-
-                        log::trace!("Using {:?}: {}", name, currency_pair_and_price);
-                        Ok(Some(currency_pair_and_price))
-                    }
-                }),
+            }
+            Err(_) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.inc_timeouts(name);
+                }
+                Err(Error::FeedTimeout(name.clone()))
+            }
+        }
+    }
+
+    /// Fetch a leg of a route, if the feed fails to price it and has a conversion
+    /// for one of its currencies the leg is priced in the vehicle currency and
+    /// converted with the price from the conversion feed. A converted leg was
+    /// updated when the older of its two prices was.
+    async fn fetch_leg(
+        &self,
+        name: &FeedName,
+        feed: &dyn PriceFeed,
+        currency_pair: CurrencyPair<Currency>,
+    ) -> Result<(CurrencyPairAndPrice<Currency>, Option<SystemTime>), Error> {
+        let err = match self.fetch_price(name, feed, currency_pair.clone()).await {
+            Ok(leg) => return Ok(leg),
+            Err(err) => err,
+        };
+        let (conversion, conversion_feed) = match self
+            .conversions
+            .get(name)
+            .filter(|conversion| currency_pair.contains(&conversion.currency))
+            .and_then(|conversion| Some((conversion, self.feeds.get(&conversion.feed)?)))
+        {
+            Some(conversion) => conversion,
+            None => return Err(err),
+        };
+
+        let to_vehicle = |currency: &Currency| {
+            if currency == &conversion.currency {
+                conversion.vehicle.clone()
+            } else {
+                currency.clone()
+            }
+        };
+        let vehicle_pair = CurrencyPair {
+            base: to_vehicle(&currency_pair.base),
+            quote: to_vehicle(&currency_pair.quote),
+        };
+        let conversion_pair = CurrencyPair {
+            base: conversion.vehicle.clone(),
+            quote: conversion.currency.clone(),
+        };
+        log::debug!(
+            "Failed to get {} from {:?} ({}), converting {} with {} from {:?}",
+            currency_pair,
+            name,
+            err,
+            vehicle_pair,
+            conversion_pair,
+            conversion.feed
+        );
+        let ((vehicle_price, vehicle_updated_at), (conversion_price, conversion_updated_at)) = futures::try_join!(
+            self.fetch_price(name, feed, vehicle_pair),
+            self.fetch_price(&conversion.feed, conversion_feed.as_ref(), conversion_pair)
+        )?;
+        log::trace!(
+            "Converting {} from {:?} with {} from {:?}",
+            vehicle_price,
+            name,
+            conversion_price,
+            conversion.feed
+        );
+
+        let mut rate = Rate::from(vehicle_price).compose(&conversion_price.into())?;
+        if rate.pair.base != currency_pair.base {
+            rate = rate.invert();
+        }
+        let updated_at = vehicle_updated_at.into_iter().chain(conversion_updated_at).min();
+        Ok((rate.into(), updated_at))
+    }
+
+    /// Get the price of a route of the feed `name`, each leg is read from
+    /// the feed of its hop.
+    async fn get_route_price(
+        &self,
+        name: &FeedName,
+        currency_pair: &CurrencyPair<Currency>,
+        route: Vec<PathHop<Currency>>,
+        plausible_range: Option<&PlausibleRangeConfig>,
+    ) -> Result<Option<TimestampedPrice>, Error> {
+        let legs = route
+            .iter()
+            .map(|hop| {
+                let name = hop.feed_or(name);
+                let feed = self.feeds.get(name).ok_or_else(|| Error::NotConfigured(name.clone()))?;
+                Ok((name, feed.as_ref(), hop.pair.clone()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let (leg_prices, updated_at): (Vec<_>, Vec<_>) = join_all(
+            legs.into_iter()
+                .map(|(name, feed, currency_pair)| self.fetch_leg(name, feed, currency_pair)),
         )
         .await
         .into_iter()
         .collect::<Result<Vec<_>, Error>>()?
         .into_iter()
-        .flatten()
-        .collect::<Vec<_>>())
+        .unzip();
+        // legs without a reported timestamp were observed just now
+        let received_at = SystemTime::now();
+        let observed_at = updated_at
+            .into_iter()
+            .map(|updated_at| updated_at.unwrap_or(received_at))
+            .min()
+            .unwrap_or(received_at);
+        let mut leg_prices = leg_prices.into_iter().map(Rate::from);
+        let mut rate = match leg_prices.next() {
+            Some(first) => leg_prices.try_fold(first, |left, right| left.compose(&right))?,
+            None => return Ok(None),
+        };
+
+        if rate.pair.base != currency_pair.base {
+            rate = rate.invert()
+        }
+        let mut currency_pair_and_price = CurrencyPairAndPrice::from(rate);
+
+        if let Some(plausible_range) = plausible_range {
+            let pairs = route.into_iter().map(|hop| hop.pair).collect::<Vec<_>>();
+            if check_orientation(name, &pairs, &currency_pair_and_price, plausible_range)
+                && plausible_range.auto_correct
+            {
+                currency_pair_and_price.price = currency_pair_and_price.price.invert();
+            }
+        }
+
+        log::trace!("Using {:?}: {}", name, currency_pair_and_price);
+        Ok(Some(TimestampedPrice {
+            value: currency_pair_and_price.price,
+            observed_at,
+            feed: name.clone(),
+        }))
+    }
+
+    /// Get the prices of each route.
+    async fn get_prices(&self, price_config: PriceConfig<Currency>) -> Result<Vec<TimestampedPrice>, Error> {
+        let currency_pair = price_config.pair.clone();
+        let currency_pair = &currency_pair;
+        let plausible_range = price_config.plausible_range.clone();
+        let plausible_range = plausible_range.as_ref();
+        join_all(price_config.routes().into_iter().map(|(name, routes)| async move {
+            let prices = join_all(
+                routes
+                    .into_iter()
+                    .map(|route| self.get_route_price(&name, currency_pair, route, plausible_range)),
+            )
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, Error>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+            Ok::<_, Error>(prices)
+        }))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, Error>>()
+        .map(|prices| prices.into_iter().flatten().collect())
     }
 
     pub async fn get_value_or_median(
         &self,
         price_config: PriceConfig<Currency>,
     ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+        Ok(self.get_value_or_median_by_feed(price_config).await?.0)
+    }
+
+    /// Get the value or median, and the median of each feed.
+    async fn get_value_or_median_by_feed(
+        &self,
+        price_config: PriceConfig<Currency>,
+    ) -> Result<(CurrencyPairAndPrice<Currency>, BTreeMap<FeedName, f64>), Error> {
         let pair = price_config.pair.clone();
-        let price = if let Some(value) = price_config.value {
-            value
-        } else {
-            Data::new(
-                self.get_prices(price_config)
-                    .await?
-                    .into_iter()
-                    .map(|cup| cup.price)
-                    .collect::<Vec<f64>>(),
-            )
-            .median()
+        if let Some(price) = price_config.value {
+            return Ok((CurrencyPairAndPrice { pair, price }, BTreeMap::new()));
+        }
+        let aggregation = price_config.aggregation.clone();
+        let max_deviation_pct = price_config.max_deviation_pct;
+        let max_age = price_config.max_age();
+        let prices = self.get_prices(price_config).await?;
+        let cutoff = max_age.and_then(|max_age| SystemTime::now().checked_sub(max_age));
+        let groups = group_by_feed(&pair, prices, cutoff)?;
+        let groups = match max_deviation_pct {
+            Some(max_deviation_pct) => reject_outliers(&pair, groups, max_deviation_pct)?,
+            None => groups,
         };
-        Ok(CurrencyPairAndPrice { pair, price })
+        let feed_prices = groups
+            .iter()
+            .filter(|(_, group)| !group.is_empty())
+            .map(|(name, group)| (name.clone(), Data::new(group.clone()).median()))
+            .collect();
+        let price = aggregate(
+            &aggregation,
+            groups.into_iter().map(|(_, group)| group).collect(),
+            &feed_prices,
+        );
+        // fail this price rather than the submission of all prices
+        if !(price.is_finite() && price > 0.0) {
+            return Err(Error::InvalidAggregate(price));
+        }
+        Ok((CurrencyPairAndPrice { pair, price }, feed_prices))
+    }
+
+    /// Check that every market required by the `price_configs` is listed
+    /// by its feed, skipping feeds which cannot list their markets.
+    pub async fn validate_markets(&self, price_configs: &[PriceConfig<Currency>]) -> Result<Vec<UnknownMarket>, Error> {
+        let mut required = BTreeMap::<FeedName, BTreeSet<String>>::new();
+        for price_config in price_configs.iter().filter(|price_config| price_config.value.is_none()) {
+            for (name, routes) in price_config.clone().routes() {
+                for hop in routes.iter().flatten() {
+                    let name = hop.feed_or(&name);
+                    if let Some(feed) = self.feeds.get(name) {
+                        required
+                            .entry(name.clone())
+                            .or_default()
+                            .extend(feed.market_name(&hop.pair));
+                    }
+                }
+            }
+        }
+
+        let mut unknown_markets = Vec::new();
+        for (name, markets) in required {
+            let available = match self.feeds.get(&name) {
+                Some(feed) => match feed.get_markets().await? {
+                    Some(available) => available,
+                    None => continue,
+                },
+                None => continue,
+            };
+            unknown_markets.extend(
+                markets
+                    .into_iter()
+                    .filter(|market| !available.contains(market))
+                    .map(|market| UnknownMarket {
+                        feed: name.clone(),
+                        suggestion: closest_match(&market, &available).cloned(),
+                        market,
+                    }),
+            );
+        }
+        Ok(unknown_markets)
+    }
+
+    /// Get the value or median for each price, failures (including panics)
+    /// are logged and isolated so that they do not prevent other prices
+    /// from being set. The price of each feed is recorded in `identical_feeds`.
+    pub async fn get_values_or_medians(
+        &self,
+        price_configs: Vec<PriceConfig<Currency>>,
+        identical_feeds: Option<&mut IdenticalFeeds>,
+    ) -> Vec<CurrencyPairAndPrice<Currency>> {
+        let results = join_all(price_configs.into_iter().map(|price_config| {
+            let pair = price_config.pair.clone();
+            AssertUnwindSafe(self.get_value_or_median_by_feed(price_config))
+                .catch_unwind()
+                .map(move |result| {
+                    match result {
+                        Ok(Ok(currency_pair_and_price)) => return Some(currency_pair_and_price),
+                        Ok(Err(err)) => log::error!("Failed to get price for {}: {}", pair, err),
+                        Err(_) => log::error!("Panicked while getting price for {}", pair),
+                    }
+                    if let Some(metrics) = &self.metrics {
+                        metrics.inc_price_failures(&pair);
+                    }
+                    None
+                })
+        }))
+        .await
+        .into_iter()
+        .flatten();
+
+        match identical_feeds {
+            Some(identical_feeds) => results
+                .map(|(currency_pair_and_price, feed_prices)| {
+                    identical_feeds.record(&currency_pair_and_price.pair, feed_prices, self.metrics.as_ref());
+                    currency_pair_and_price
+                })
+                .collect(),
+            None => results
+                .map(|(currency_pair_and_price, _)| currency_pair_and_price)
+                .collect(),
+        }
+    }
+}
+
+/// Warn if the price of the `route` is outside the plausible range but its
+/// reciprocal is inside, which suggests that a leg of the route is written in
+/// the wrong orientation, e.g. USD/BTC instead of BTC/USD. Returns whether
+/// the warning was logged.
+fn check_orientation(
+    name: &FeedName,
+    route: &[CurrencyPair<Currency>],
+    currency_pair_and_price: &CurrencyPairAndPrice<Currency>,
+    plausible_range: &PlausibleRangeConfig,
+) -> bool {
+    if !plausible_range.is_inverted(currency_pair_and_price.price) {
+        return false;
+    }
+    let suggestion = match route {
+        [currency_pair] => format!("should it be {}?", currency_pair.inverse()),
+        _ => "check the orientation of each leg".to_string(),
+    };
+    log::warn!(
+        "Likely orientation error in {} route {} for {}: {} is outside [{}, {}] but its reciprocal is not, {}",
+        name,
+        route.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "),
+        currency_pair_and_price.pair,
+        currency_pair_and_price.price,
+        plausible_range.min,
+        plausible_range.max,
+        suggestion
+    );
+    true
+}
+
+/// Group the values of the `prices` by feed, prices observed before the
+/// `cutoff` are ignored, fails if that ignores every price.
+fn group_by_feed(
+    pair: &CurrencyPair<Currency>,
+    prices: Vec<TimestampedPrice>,
+    cutoff: Option<SystemTime>,
+) -> Result<Vec<(FeedName, Vec<f64>)>, Error> {
+    let observed = !prices.is_empty();
+    let mut groups = BTreeMap::<FeedName, Vec<f64>>::new();
+    for price in prices {
+        if cutoff.map_or(false, |cutoff| price.is_older_than(cutoff)) {
+            log::warn!(
+                "Ignoring {} for {} from {}: observed before the cutoff",
+                price.value,
+                pair,
+                price.feed
+            );
+            continue;
+        }
+        groups.entry(price.feed).or_default().push(price.value);
+    }
+    match cutoff {
+        Some(cutoff) if observed && groups.is_empty() => Err(Error::AllPricesBeforeCutoff(cutoff)),
+        _ => Ok(groups.into_iter().collect()),
+    }
+}
+
+/// Median across the medians of each group, empty groups are ignored.
+fn median_of_medians(groups: Vec<Vec<f64>>) -> f64 {
+    Data::new(
+        groups
+            .into_iter()
+            .filter(|group| !group.is_empty())
+            .map(|group| Data::new(group).median())
+            .collect::<Vec<f64>>(),
+    )
+    .median()
+}
+
+/// Drop the groups whose median deviates from the median of all groups by
+/// more than `max_deviation_pct` percent, fails if no group is left.
+fn reject_outliers(
+    pair: &CurrencyPair<Currency>,
+    groups: Vec<(FeedName, Vec<f64>)>,
+    max_deviation_pct: f64,
+) -> Result<Vec<(FeedName, Vec<f64>)>, Error> {
+    let groups = groups
+        .into_iter()
+        .filter(|(_, group)| !group.is_empty())
+        .map(|(name, group)| (name, Data::new(group.clone()).median(), group))
+        .collect::<Vec<_>>();
+    if groups.is_empty() {
+        return Ok(Vec::new());
+    }
+    let median = Data::new(groups.iter().map(|(_, price, _)| *price).collect::<Vec<_>>()).median();
+    let max_deviation = median.abs() * max_deviation_pct / 100.0;
+
+    let survivors = groups
+        .into_iter()
+        .filter(|(name, price, _)| {
+            let is_outlier = (price - median).abs() > max_deviation;
+            if is_outlier {
+                log::warn!(
+                    "Discarding {} for {}: {} deviates by more than {}% from the median {}",
+                    name,
+                    pair,
+                    price,
+                    max_deviation_pct,
+                    median
+                );
+            }
+            !is_outlier
+        })
+        .map(|(name, _, group)| (name, group))
+        .collect::<Vec<_>>();
+    if survivors.is_empty() {
+        return Err(Error::AllFeedsDeviate(max_deviation_pct));
     }
+    Ok(survivors)
+}
+
+/// Combine the prices of all feeds, `feed_prices` are the medians of the
+/// non-empty `groups`.
+fn aggregate(aggregation: &Aggregation, groups: Vec<Vec<f64>>, feed_prices: &BTreeMap<FeedName, f64>) -> f64 {
+    match aggregation {
+        Aggregation::Median => median_of_medians(groups),
+        Aggregation::Mean => feed_prices.values().sum::<f64>() / feed_prices.len() as f64,
+        Aggregation::WeightedMean(weights) => {
+            let (weighted_sum, total_weight) =
+                feed_prices
+                    .iter()
+                    .fold((0.0, 0.0), |(weighted_sum, total_weight), (name, price)| {
+                        // feeds without a weight have been rejected by the validation
+                        let weight = weights.get(name).copied().unwrap_or_default();
+                        (weighted_sum + weight * price, total_weight + weight)
+                    });
+            weighted_sum / total_weight
+        }
+        Aggregation::TrimmedMean { trim_pct } => trimmed_mean(feed_prices.values().copied().collect(), *trim_pct),
+    }
+}
+
+/// Mean of the `prices` after dropping `trim_pct` percent from each end, rounded down.
+fn trimmed_mean(mut prices: Vec<f64>, trim_pct: f64) -> f64 {
+    prices.sort_by(f64::total_cmp);
+    let trimmed = (prices.len() as f64 * trim_pct / 100.0) as usize;
+    if trimmed * 2 >= prices.len() {
+        return Data::new(prices).median();
+    }
+    let remaining = &prices[trimmed..prices.len() - trimmed];
+    remaining.iter().sum::<f64>() / remaining.len() as f64
 }
 
 #[async_trait]
@@ -213,3 +847,840 @@ impl BitcoinFeeds {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::OracleConfig,
+        error::{ConfigError, PriceConfigError},
+    };
+    use runtime::prometheus::core::Metric;
+    use serde_json::json;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn should_parse_feed_names_ignoring_case() {
+        for name in ["kraken", "Kraken", "KRAKEN"] {
+            assert_eq!(name.parse::<FeedName>(), Ok(FeedName::Kraken));
+        }
+        assert_eq!("GateIO".parse::<FeedName>(), Ok(FeedName::GateIo));
+        assert_eq!("OnChainAggregator".parse::<FeedName>(), Ok(FeedName::OnChainAggregator));
+        assert_eq!(
+            "Binance".parse::<FeedName>(),
+            Ok(FeedName::Unknown("binance".to_string()))
+        );
+
+        assert_eq!(
+            serde_json::from_value::<Vec<FeedName>>(json!(["CoinGecko", "dia", "binance"])).unwrap(),
+            vec![
+                FeedName::CoinGecko,
+                FeedName::Dia,
+                FeedName::Unknown("binance".to_string())
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(vec![FeedName::CoinGecko, FeedName::Unknown("binance".to_string())]).unwrap(),
+            json!(["coingecko", "binance"])
+        );
+        assert_eq!(FeedName::Unknown("binance".to_string()).to_string(), "binance");
+        assert_eq!(FeedName::CoinGecko.to_string(), "coingecko");
+    }
+
+    struct MockPriceFeed<F>(F);
+
+    #[async_trait]
+    impl<F> PriceFeed for MockPriceFeed<F>
+    where
+        F: Fn(&CurrencyPair<Currency>) -> Result<f64, Error> + Send + Sync,
+    {
+        async fn get_price(
+            &self,
+            currency_pair: CurrencyPair<Currency>,
+            _currency_store: &CurrencyStore<String>,
+            _price_source: PriceSource,
+        ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+            let price = (self.0)(&currency_pair)?;
+            Ok(CurrencyPairAndPrice {
+                pair: currency_pair,
+                price,
+            })
+        }
+    }
+
+    /// Prices by base currency, counting the fetches.
+    struct InMemoryClient {
+        prices: BTreeMap<String, f64>,
+        fetches: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl FeedClient for InMemoryClient {
+        async fn fetch(&self, pair: &CurrencyPair<Currency>) -> Result<f64, Error> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            self.prices
+                .get(&pair.base.symbol())
+                .copied()
+                .ok_or(Error::InvalidResponse)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_dispatch_to_registered_clients() {
+        let oracle_config = serde_json::from_value::<OracleConfig>(json!({
+            "currencies": {
+                "BTC": { "name": "Bitcoin", "decimals": 8 },
+                "USD": { "name": "Dollar", "decimals": 6 }
+            },
+            "prices": [{ "pair": ["BTC", "USD"], "feeds": { "Memory": [["BTC", "USD"]] } }]
+        }))
+        .unwrap();
+        let memory = FeedName::Unknown("memory".to_string());
+
+        let mut price_feeds = PriceFeeds::new(oracle_config.currencies.clone());
+        assert!(matches!(
+            oracle_config.validate(&price_feeds.adapters()),
+            Err(PriceConfigError {
+                error: ConfigError::UnknownFeed(_),
+                ..
+            })
+        ));
+
+        let fetches = Arc::new(AtomicUsize::new(0));
+        price_feeds.register(
+            memory.clone(),
+            InMemoryClient {
+                prices: vec![("BTC".to_string(), 20000.0)].into_iter().collect(),
+                fetches: fetches.clone(),
+            },
+        );
+        oracle_config.validate(&price_feeds.adapters()).unwrap();
+
+        let prices = price_feeds
+            .get_values_or_medians(oracle_config.prices.clone(), None)
+            .await;
+        assert_eq!(prices.len(), 1);
+        assert_eq!(prices[0].price, 20000.0);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // only the last trade is supported
+        price_feeds.set_price_sources(vec![(memory, PriceSource::Mid)].into_iter().collect());
+        assert!(price_feeds
+            .get_values_or_medians(oracle_config.prices, None)
+            .await
+            .is_empty());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    struct DelayedPriceFeed(Duration);
+
+    #[async_trait]
+    impl PriceFeed for DelayedPriceFeed {
+        async fn get_price(
+            &self,
+            currency_pair: CurrencyPair<Currency>,
+            _currency_store: &CurrencyStore<String>,
+            _price_source: PriceSource,
+        ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+            tokio::time::sleep(self.0).await;
+            Ok(CurrencyPairAndPrice {
+                pair: currency_pair,
+                price: 1.0,
+            })
+        }
+    }
+
+    /// Reports every price as last updated at the given time.
+    struct TimestampedPriceFeed(SystemTime);
+
+    #[async_trait]
+    impl PriceFeed for TimestampedPriceFeed {
+        async fn get_price(
+            &self,
+            currency_pair: CurrencyPair<Currency>,
+            _currency_store: &CurrencyStore<String>,
+            _price_source: PriceSource,
+        ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+            Ok(CurrencyPairAndPrice {
+                pair: currency_pair,
+                price: 1.0,
+            })
+        }
+
+        async fn get_timestamped_price(
+            &self,
+            currency_pair: CurrencyPair<Currency>,
+            currency_store: &CurrencyStore<String>,
+            price_source: PriceSource,
+        ) -> Result<(CurrencyPairAndPrice<Currency>, Option<SystemTime>), Error> {
+            let currency_pair_and_price = self.get_price(currency_pair, currency_store, price_source).await?;
+            Ok((currency_pair_and_price, Some(self.0)))
+        }
+    }
+
+    fn price_config(value: Value) -> PriceConfig<Currency> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_drop_prices_updated_before_the_cutoff() {
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.feeds.insert(
+            FeedName::OnChainAggregator,
+            Box::new(TimestampedPriceFeed(SystemTime::now() - Duration::from_secs(3600))),
+        );
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(2.0))),
+        );
+        let price_feeds = &price_feeds;
+        let get_price = |feeds: Value| async move {
+            price_feeds
+                .get_value_or_median(price_config(
+                    json!({ "pair": ["BTC", "USD"], "feeds": feeds, "max_age_secs": 60 }),
+                ))
+                .await
+        };
+
+        // only the price without a timestamp is recent
+        let price = get_price(json!({
+            "onchainaggregator": [["BTC=0x1234", "USD"]],
+            "kraken": [["BTC", "USD"]]
+        }))
+        .await
+        .unwrap();
+        assert_eq!(price.price, 2.0);
+
+        assert!(matches!(
+            get_price(json!({ "onchainaggregator": [["BTC=0x1234", "USD"]] })).await,
+            Err(Error::AllPricesBeforeCutoff(_))
+        ));
+
+        // a route is as old as its oldest leg
+        assert!(matches!(
+            get_price(json!({ "kraken": [["BTC", "ETH"], ["onchainaggregator", ["ETH=0x1234", "USD"]]] })).await,
+            Err(Error::AllPricesBeforeCutoff(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_aggregates() {
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(2.0))),
+        );
+        price_feeds.feeds.insert(
+            FeedName::GateIo,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(6.0))),
+        );
+        // the weights are not validated here
+        let price_config = |kraken: f64, gateio: f64| PriceConfig {
+            aggregation: Aggregation::WeightedMean(
+                vec![(FeedName::Kraken, kraken), (FeedName::GateIo, gateio)]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..price_config(json!({
+                "pair": ["BTC", "USD"],
+                "feeds": { "kraken": [["BTC", "USD"]], "gateio": [["BTC", "USD"]] }
+            }))
+        };
+
+        assert_eq!(
+            price_feeds
+                .get_value_or_median(price_config(1.0, 1.0))
+                .await
+                .unwrap()
+                .price,
+            4.0
+        );
+        assert!(matches!(
+            price_feeds.get_value_or_median(price_config(0.0, 0.0)).await,
+            Err(Error::InvalidAggregate(price)) if price.is_nan()
+        ));
+        assert!(matches!(
+            price_feeds.get_value_or_median(price_config(2.0, -1.0)).await,
+            Err(Error::InvalidAggregate(price)) if price == -2.0
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_isolate_failing_prices() {
+        let metrics = FeedMetrics::new().unwrap();
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.set_metrics(metrics.clone());
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(
+                |currency_pair: &CurrencyPair<Currency>| match currency_pair.base.symbol().as_str() {
+                    "KINT" => panic!("Bad extractor"),
+                    "INTR" => Err(Error::InvalidResponse),
+                    _ => Ok(2.0),
+                },
+            )),
+        );
+
+        let prices = price_feeds
+            .get_values_or_medians(
+                vec![
+                    price_config(json!({ "pair": ["KSM", "BTC"], "feeds": { "kraken": [["KSM", "BTC"]] } })),
+                    price_config(json!({ "pair": ["KINT", "BTC"], "feeds": { "kraken": [["KINT", "BTC"]] } })),
+                    price_config(json!({ "pair": ["INTR", "BTC"], "feeds": { "kraken": [["INTR", "BTC"]] } })),
+                    price_config(json!({ "pair": ["DOT", "BTC"], "value": 3.0 })),
+                ],
+                None,
+            )
+            .await;
+
+        assert_eq!(
+            prices
+                .iter()
+                .map(|currency_pair_and_price| (
+                    currency_pair_and_price.pair.base.symbol(),
+                    currency_pair_and_price.price
+                ))
+                .collect::<Vec<_>>(),
+            vec![("KSM".to_string(), 2.0), ("DOT".to_string(), 3.0)]
+        );
+        // both the error and the panic are counted
+        for (pair, failures) in [("KSM/BTC", 0), ("KINT/BTC", 1), ("INTR/BTC", 1), ("DOT/BTC", 0)] {
+            assert_eq!(metrics.price_failures.with_label_values(&[pair]).get(), failures);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_record_feed_latency_and_timeouts() {
+        let metrics = FeedMetrics::new().unwrap();
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.set_feed_timeout(Duration::from_secs(1));
+        price_feeds.set_metrics(metrics.clone());
+        price_feeds
+            .feeds
+            .insert(FeedName::Kraken, Box::new(DelayedPriceFeed(Duration::from_millis(30))));
+        price_feeds
+            .feeds
+            .insert(FeedName::GateIo, Box::new(DelayedPriceFeed(Duration::from_secs(2))));
+
+        price_feeds
+            .get_value_or_median(price_config(
+                json!({ "pair": ["KSM", "BTC"], "feeds": { "kraken": [["KSM", "BTC"]] } }),
+            ))
+            .await
+            .unwrap();
+        assert!(matches!(
+            price_feeds
+                .get_value_or_median(price_config(
+                    json!({ "pair": ["KSM", "BTC"], "feeds": { "gateio": [["KSM", "BTC"]] } })
+                ))
+                .await,
+            Err(Error::FeedTimeout(FeedName::GateIo))
+        ));
+
+        let kraken = metrics.latency.with_label_values(&["kraken"]).get_sample_count();
+        assert_eq!(kraken, 1);
+        let buckets = metrics
+            .latency
+            .with_label_values(&["kraken"])
+            .metric()
+            .get_histogram()
+            .get_bucket()
+            .iter()
+            .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+            .collect::<Vec<_>>();
+        // 30ms falls into the 50ms bucket
+        assert!(buckets.contains(&(0.025, 0)));
+        assert!(buckets.contains(&(0.05, 1)));
+
+        // timeouts are counted but not observed
+        assert_eq!(metrics.latency.with_label_values(&["gateio"]).get_sample_count(), 0);
+        assert_eq!(metrics.timeouts.with_label_values(&["gateio"]).get(), 1);
+        assert_eq!(metrics.timeouts.with_label_values(&["kraken"]).get(), 0);
+    }
+
+    #[test]
+    fn should_compute_median_of_medians() {
+        assert_eq!(
+            median_of_medians(vec![vec![1.0, 2.0, 3.0], vec![], vec![4.0, 100.0]]),
+            27.0
+        );
+        // a single group degrades to its median
+        assert_eq!(median_of_medians(vec![vec![], vec![1.0, 5.0, 6.0]]), 5.0);
+    }
+
+    #[test]
+    fn should_aggregate_feed_prices() {
+        let groups = vec![vec![1.0, 3.0], vec![], vec![6.0], vec![11.0]];
+        let feed_prices = vec![
+            (FeedName::Kraken, 2.0),
+            (FeedName::GateIo, 6.0),
+            (FeedName::CoinGecko, 11.0),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(aggregate(&Aggregation::Median, groups.clone(), &feed_prices), 6.0);
+        assert_eq!(aggregate(&Aggregation::Mean, groups.clone(), &feed_prices), 19.0 / 3.0);
+        let weights = vec![
+            (FeedName::Kraken, 3.0),
+            (FeedName::GateIo, 1.0),
+            (FeedName::CoinGecko, 0.0),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            aggregate(&Aggregation::WeightedMean(weights), groups, &feed_prices),
+            3.0
+        );
+    }
+
+    #[test]
+    fn should_compare_observation_times() {
+        let observed_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1665000000);
+        let price = TimestampedPrice {
+            value: 2.0,
+            observed_at,
+            feed: FeedName::Kraken,
+        };
+
+        assert_eq!(price.clone(), price);
+        assert_ne!(
+            TimestampedPrice {
+                observed_at: observed_at + Duration::from_millis(1),
+                ..price.clone()
+            },
+            price
+        );
+        assert!(price.is_older_than(observed_at + Duration::from_secs(1)));
+        // a price observed at the cutoff is still used
+        assert!(!price.is_older_than(observed_at));
+        assert!(!price.is_older_than(observed_at - Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn should_ignore_prices_before_cutoff() {
+        let pair = CurrencyPair {
+            base: "BTC".parse().unwrap(),
+            quote: "USD".parse().unwrap(),
+        };
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1665000000);
+        let price = |feed: FeedName, value: f64, age_secs: u64| TimestampedPrice {
+            value,
+            observed_at: now - Duration::from_secs(age_secs),
+            feed,
+        };
+        let prices = vec![
+            price(FeedName::Kraken, 1.0, 0),
+            price(FeedName::Kraken, 3.0, 10),
+            price(FeedName::GateIo, 100.0, 120),
+            price(FeedName::CoinGecko, 5.0, 59),
+        ];
+
+        let groups = group_by_feed(&pair, prices.clone(), None).unwrap();
+        assert_eq!(
+            groups,
+            vec![
+                (FeedName::CoinGecko, vec![5.0]),
+                (FeedName::GateIo, vec![100.0]),
+                (FeedName::Kraken, vec![1.0, 3.0]),
+            ]
+        );
+
+        let groups = group_by_feed(&pair, prices.clone(), Some(now - Duration::from_secs(60))).unwrap();
+        assert_eq!(
+            groups,
+            vec![(FeedName::CoinGecko, vec![5.0]), (FeedName::Kraken, vec![1.0, 3.0])]
+        );
+        let feed_prices = vec![(FeedName::CoinGecko, 5.0), (FeedName::Kraken, 2.0)]
+            .into_iter()
+            .collect();
+        let groups = groups.into_iter().map(|(_, group)| group).collect::<Vec<_>>();
+        assert_eq!(aggregate(&Aggregation::Median, groups.clone(), &feed_prices), 3.5);
+        assert_eq!(aggregate(&Aggregation::Mean, groups, &feed_prices), 3.5);
+
+        assert!(matches!(
+            group_by_feed(&pair, prices, Some(now + Duration::from_secs(1))),
+            Err(Error::AllPricesBeforeCutoff(_))
+        ));
+        assert!(group_by_feed(&pair, vec![], Some(now)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_reject_outliers() {
+        let pair = CurrencyPair {
+            base: "BTC".parse().unwrap(),
+            quote: "USD".parse().unwrap(),
+        };
+        let groups = vec![
+            (FeedName::Kraken, vec![20000.0]),
+            (FeedName::GateIo, vec![20100.0]),
+            (FeedName::CoinGecko, vec![2000.0]),
+        ];
+
+        assert_eq!(
+            reject_outliers(&pair, groups.clone(), 5.0).unwrap(),
+            vec![(FeedName::Kraken, vec![20000.0]), (FeedName::GateIo, vec![20100.0])]
+        );
+        // a wide enough deviation keeps every feed
+        assert_eq!(reject_outliers(&pair, groups, 100.0).unwrap().len(), 3);
+
+        // the median of two feeds can be far from both
+        let groups = vec![(FeedName::Kraken, vec![100.0]), (FeedName::GateIo, vec![200.0])];
+        assert!(matches!(
+            reject_outliers(&pair, groups, 10.0),
+            Err(Error::AllFeedsDeviate(_))
+        ));
+    }
+
+    #[test]
+    fn should_compute_trimmed_mean() {
+        let prices = vec![64.0, 1.0, 1024.0, 4.0, 0.0, 8.0, 128.0, 2.0, 32.0, 16.0];
+        // nothing to drop
+        assert_eq!(trimmed_mean(prices.clone(), 0.0), 127.9);
+        // drops 0.0 and 1024.0
+        assert_eq!(trimmed_mean(prices.clone(), 10.0), 31.875);
+        // 25% of 10 rounds down to 2
+        assert_eq!(trimmed_mean(prices.clone(), 25.0), 21.0);
+        assert_eq!(trimmed_mean(prices.clone(), 40.0), 12.0);
+        // would drop everything so use the median
+        assert_eq!(trimmed_mean(prices, 50.0), 12.0);
+        assert_eq!(trimmed_mean(vec![1.0, 2.0, 10.0], 40.0), 2.0);
+        assert_eq!(trimmed_mean(vec![1.0, 2.0, 10.0], 50.0), 2.0);
+    }
+
+    #[tokio::test]
+    async fn should_only_use_the_feeds_of_each_price() {
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(1.0))),
+        );
+        price_feeds.feeds.insert(
+            FeedName::GateIo,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(2.0))),
+        );
+        price_feeds.feeds.insert(
+            FeedName::CoinGecko,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(100.0))),
+        );
+
+        let prices = price_feeds
+            .get_values_or_medians(
+                vec![
+                    price_config(json!({
+                        "pair": ["BTC", "USD"],
+                        "feeds": { "kraken": [["BTC", "USD"]], "gateio": [["BTC", "USD"]] }
+                    })),
+                    // evaluate a new feed on a single pair
+                    price_config(json!({ "pair": ["KSM", "USD"], "feeds": { "coingecko": [["KSM", "USD"]] } })),
+                ],
+                None,
+            )
+            .await;
+
+        assert_eq!(
+            prices
+                .iter()
+                .map(|currency_pair_and_price| currency_pair_and_price.price)
+                .collect::<Vec<_>>(),
+            vec![1.5, 100.0]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_read_hops_from_their_feeds() {
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(0.5))),
+        );
+        price_feeds.feeds.insert(
+            FeedName::GateIo,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(20000.0))),
+        );
+
+        // BTC/USDT from gate.io * USDT/USD from Kraken
+        let price = price_feeds
+            .get_value_or_median(price_config(json!({
+                "pair": ["BTC", "USD"],
+                "feeds": { "kraken": [["gateio", ["BTC", "USDT"]], ["USDT", "USD"]] }
+            })))
+            .await
+            .unwrap();
+        assert_eq!(price.price, 10000.0);
+
+        let err = price_feeds
+            .get_value_or_median(price_config(json!({
+                "pair": ["BTC", "USD"],
+                "feeds": { "kraken": [["coingecko", ["BTC", "USDT"]], ["USDT", "USD"]] }
+            })))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::NotConfigured(FeedName::CoinGecko)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn should_detect_inverted_routes() {
+        let btc_usd = CurrencyPair::<Currency> {
+            base: "BTC".parse().unwrap(),
+            quote: "USD".parse().unwrap(),
+        };
+        let plausible_range: PlausibleRangeConfig =
+            serde_json::from_value(json!({ "min": 1000.0, "max": 1000000.0 })).unwrap();
+        let check = |price| {
+            check_orientation(
+                &FeedName::Kraken,
+                &[btc_usd.inverse()],
+                &CurrencyPairAndPrice {
+                    pair: btc_usd.clone(),
+                    price,
+                },
+                &plausible_range,
+            )
+        };
+        assert!(check(1.0 / 20000.0));
+        assert!(!check(20000.0));
+        // implausible either way
+        assert!(!check(5.0));
+
+        // the feed returns the USD/BTC leg as 20000, which is then inverted
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(20000.0))),
+        );
+        let inverted_price_config = |auto_correct| {
+            price_config(json!({
+                "pair": ["BTC", "USD"],
+                "feeds": { "kraken": [["USD", "BTC"]] },
+                "plausible_range": { "min": 1000.0, "max": 1000000.0, "auto_correct": auto_correct }
+            }))
+        };
+
+        // only a diagnostic by default
+        let price = price_feeds
+            .get_value_or_median(inverted_price_config(false))
+            .await
+            .unwrap()
+            .price;
+        assert_eq!(price, 1.0 / 20000.0);
+
+        let price = price_feeds
+            .get_value_or_median(inverted_price_config(true))
+            .await
+            .unwrap()
+            .price;
+        assert_eq!(price, 20000.0);
+    }
+
+    #[tokio::test]
+    async fn should_rescale_feed_decimals() {
+        let currency_store = serde_json::from_value::<CurrencyStore<String>>(json!({
+            "BTC": { "name": "Bitcoin", "decimals": 8, "feed_decimals": { "kraken": 10 } },
+            "USD": { "name": "Dollar", "decimals": 2 }
+        }))
+        .unwrap();
+        let mut price_feeds = PriceFeeds::new(currency_store);
+        // kraken reports the BTC amount with two more decimals
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(200.0))),
+        );
+        price_feeds.feeds.insert(
+            FeedName::GateIo,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(20000.0))),
+        );
+
+        for feed in ["kraken", "gateio"] {
+            let price_config = price_config(json!({ "pair": ["BTC", "USD"], "feeds": { feed: [["BTC", "USD"]] } }));
+            assert_eq!(
+                price_feeds.get_value_or_median(price_config).await.unwrap().price,
+                20000.0
+            );
+        }
+
+        // the offset of the quote is applied in the opposite direction
+        let price_config = price_config(json!({ "pair": ["USD", "BTC"], "feeds": { "kraken": [["USD", "BTC"]] } }));
+        let price = price_feeds.get_value_or_median(price_config).await.unwrap().price;
+        assert!((price - 2.0).abs() < 1e-12, "{}", price);
+    }
+
+    #[tokio::test]
+    async fn should_convert_via_vehicle_currency() {
+        let oracle_config = serde_json::from_value::<OracleConfig>(json!({
+            "currencies": {},
+            "prices": [],
+            "feeds": {
+                "kraken": { "conversion": { "currency": "USD", "vehicle": "EUR", "feed": "gateio" } }
+            }
+        }))
+        .unwrap();
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.set_conversions(oracle_config.conversions());
+        // kraken only has EUR markets
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MockPriceFeed(|currency_pair: &CurrencyPair<Currency>| {
+                match (
+                    currency_pair.base.symbol().as_str(),
+                    currency_pair.quote.symbol().as_str(),
+                ) {
+                    ("BTC", "EUR") => Ok(18000.0),
+                    ("EUR", "BTC") => Ok(1.0 / 18000.0),
+                    _ => Err(Error::InvalidResponse),
+                }
+            })),
+        );
+        price_feeds.feeds.insert(
+            FeedName::GateIo,
+            Box::new(MockPriceFeed(|currency_pair: &CurrencyPair<Currency>| {
+                assert_eq!(currency_pair.to_string(), "EUR/USD");
+                Ok(1.25)
+            })),
+        );
+
+        let price_feeds = &price_feeds;
+        let get_price = |price_config| async move { price_feeds.get_value_or_median(price_config).await };
+        let price = get_price(price_config(
+            json!({ "pair": ["BTC", "USD"], "feeds": { "kraken": [["BTC", "USD"]] } }),
+        ))
+        .await
+        .unwrap();
+        assert_eq!(price.price, 22500.0);
+
+        let price = get_price(price_config(
+            json!({ "pair": ["USD", "BTC"], "feeds": { "kraken": [["USD", "BTC"]] } }),
+        ))
+        .await
+        .unwrap();
+        assert!((price.price * 22500.0 - 1.0).abs() < 1e-9, "{}", price.price);
+
+        // legs without the converted currency still fail
+        assert!(matches!(
+            get_price(price_config(
+                json!({ "pair": ["BTC", "DOT"], "feeds": { "kraken": [["BTC", "DOT"]] } })
+            ))
+            .await,
+            Err(Error::InvalidResponse)
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_get_median_of_grouped_feeds() {
+        // the quote encodes the price, e.g. P3 => 3.0
+        let feed = |currency_pair: &CurrencyPair<Currency>| -> Result<f64, Error> {
+            Ok(currency_pair.quote.symbol()[1..].parse()?)
+        };
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds
+            .feeds
+            .insert(FeedName::Kraken, Box::new(MockPriceFeed(feed)));
+        price_feeds
+            .feeds
+            .insert(FeedName::GateIo, Box::new(MockPriceFeed(feed)));
+        price_feeds
+            .feeds
+            .insert(FeedName::CoinGecko, Box::new(MockPriceFeed(feed)));
+
+        let currency_pair_and_price = price_feeds
+            .get_value_or_median(price_config(json!({
+                "pair": ["KSM", "BTC"],
+                "feeds": { "coingecko": [["KSM", "P10"]] },
+                "groups": {
+                    "kraken": [[["KSM", "P1"]], [["KSM", "P2"]], [["KSM", "P3"]]],
+                    "gateio": [[["KSM", "P4"]], [["KSM", "P100"]]]
+                }
+            })))
+            .await
+            .unwrap();
+
+        // median(median(1, 2, 3), median(4, 100), median(10)) = median(2, 52, 10)
+        assert_eq!(currency_pair_and_price.price, 10.0);
+    }
+
+    struct MarketsPriceFeed(Vec<String>);
+
+    #[async_trait]
+    impl PriceFeed for MarketsPriceFeed {
+        async fn get_price(
+            &self,
+            _currency_pair: CurrencyPair<Currency>,
+            _currency_store: &CurrencyStore<String>,
+            _price_source: PriceSource,
+        ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+            Err(Error::InvalidResponse)
+        }
+
+        fn market_name(&self, currency_pair: &CurrencyPair<Currency>) -> Option<String> {
+            Some(format!(
+                "{}{}",
+                currency_pair.base.path().unwrap_or_else(|| currency_pair.base.symbol()),
+                currency_pair
+                    .quote
+                    .path()
+                    .unwrap_or_else(|| currency_pair.quote.symbol())
+            ))
+        }
+
+        async fn get_markets(&self) -> Result<Option<Vec<String>>, Error> {
+            Ok(Some(self.0.clone()))
+        }
+    }
+
+    #[test]
+    fn should_compute_edit_distance() {
+        assert_eq!(edit_distance("XBTUSD", "XBTUSD"), 0);
+        assert_eq!(edit_distance("XBTUSD", "XXBTZUSD"), 2);
+        assert_eq!(edit_distance("", "USD"), 3);
+        assert_eq!(edit_distance("KSM", "DOT"), 3);
+    }
+
+    #[tokio::test]
+    async fn should_report_unknown_markets_with_suggestion() {
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.feeds.insert(
+            FeedName::Kraken,
+            Box::new(MarketsPriceFeed(vec![
+                "XXBTZUSD".to_string(),
+                "XETHZUSD".to_string(),
+                "DOTUSD".to_string(),
+            ])),
+        );
+        // feeds without a markets endpoint are skipped
+        price_feeds.feeds.insert(
+            FeedName::GateIo,
+            Box::new(MockPriceFeed(|_: &CurrencyPair<Currency>| Ok(1.0))),
+        );
+
+        let unknown_markets = price_feeds
+            .validate_markets(&[
+                price_config(json!({
+                    "pair": ["BTC", "DOT"],
+                    "feeds": {
+                        "kraken": [["BTC=XXBTZ", "USD"], ["DOT", "USD"]],
+                        "gateio": [["BTC", "DOT"]]
+                    }
+                })),
+                price_config(json!({ "pair": ["BTC", "KSM"], "feeds": { "kraken": [["BTC=XXBT", "USD"]] } })),
+                price_config(json!({ "pair": ["BTC", "INTR"], "value": 1.0 })),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            unknown_markets,
+            vec![UnknownMarket {
+                feed: FeedName::Kraken,
+                market: "XXBTUSD".to_string(),
+                suggestion: Some("XXBTZUSD".to_string()),
+            }]
+        );
+        assert_eq!(
+            unknown_markets[0].to_string(),
+            "kraken: unknown market XXBTUSD (did you mean XXBTZUSD?)"
+        );
+    }
+}
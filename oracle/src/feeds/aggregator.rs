@@ -0,0 +1,273 @@
+use super::PriceFeed;
+use crate::{
+    config::{CurrencyStore, PriceSource, TimestampConfig},
+    currency::*,
+    timestamp, Error,
+};
+use async_trait::async_trait;
+use clap::Parser;
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::{
+    convert::TryInto,
+    time::{Duration, SystemTime},
+};
+
+/// `latestRoundData()`
+const LATEST_ROUND_DATA_SELECTOR: &str = "0xfeaf968c";
+/// `decimals()`
+const DECIMALS_SELECTOR: &str = "0x313ce567";
+const WORD_SIZE: usize = 32;
+
+#[derive(Parser, Debug, Clone)]
+pub struct AggregatorCli {
+    /// Fetch the exchange rate from on-chain (Chainlink) aggregator contracts through this Ethereum JSON-RPC endpoint
+    #[clap(long)]
+    aggregator_rpc_url: Option<Url>,
+
+    /// Reject aggregator answers which have not been updated for this many seconds
+    #[clap(long, default_value = "3600")]
+    aggregator_max_age_secs: u64,
+
+    /// Currency the answers of the aggregator contracts are denominated in, pairs with another quote are rejected
+    #[clap(long, default_value = "USD")]
+    aggregator_denomination: String,
+}
+
+/// The latest answer of an aggregator, scaled by its decimals.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AggregatorAnswer {
+    price: f64,
+    /// Timestamp of the round as reported by the contract
+    updated_at: u64,
+}
+
+pub struct AggregatorApi {
+    url: Url,
+    max_age_secs: u64,
+    denomination: String,
+    timestamp_config: TimestampConfig,
+}
+
+/// Returns the 32-byte words of the `eth_call` result.
+fn extract_words(value: &Value) -> Option<Vec<[u8; WORD_SIZE]>> {
+    let result = hex::decode(value.get("result")?.as_str()?.strip_prefix("0x")?).ok()?;
+    if result.is_empty() || result.len() % WORD_SIZE != 0 {
+        return None;
+    }
+    result.chunks(WORD_SIZE).map(|word| word.try_into().ok()).collect()
+}
+
+/// Reads an unsigned word which fits into a `u128`, negative answers are rejected.
+fn word_to_u128(word: &[u8; WORD_SIZE]) -> Option<u128> {
+    let (high, low) = word.split_at(WORD_SIZE - 16);
+    if high.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    Some(u128::from_be_bytes(low.try_into().ok()?))
+}
+
+fn extract_decimals(value: &Value) -> Option<u32> {
+    word_to_u128(extract_words(value)?.first()?)?.try_into().ok()
+}
+
+/// Decodes `(roundId, answer, startedAt, updatedAt, answeredInRound)`.
+fn extract_answer(value: &Value, decimals: u32) -> Option<AggregatorAnswer> {
+    let words = extract_words(value)?;
+    let answer = word_to_u128(words.get(1)?)?;
+    let updated_at = word_to_u128(words.get(3)?)?.try_into().ok()?;
+    Some(AggregatorAnswer {
+        price: base_units_to_amount(decimals, answer).ok()?,
+        updated_at,
+    })
+}
+
+fn is_stale(updated_at: SystemTime, now: SystemTime, max_age_secs: u64) -> bool {
+    now.duration_since(updated_at)
+        .map_or(false, |age| age > Duration::from_secs(max_age_secs))
+}
+
+/// The answer is in the denomination of the contract, which cannot be read
+/// from the contract itself, so the quote of the pair must match it.
+fn check_denomination(denomination: &str, currency_pair: &CurrencyPair<Currency>) -> Result<(), Error> {
+    if currency_pair.quote.symbol() == denomination {
+        Ok(())
+    } else {
+        Err(Error::AggregatorDenominationMismatch {
+            denomination: denomination.to_string(),
+            quote: currency_pair.quote.symbol(),
+        })
+    }
+}
+
+impl AggregatorApi {
+    pub fn from_opts(opts: AggregatorCli) -> Option<Self> {
+        opts.aggregator_rpc_url.map(|url| Self {
+            denomination: opts.aggregator_denomination,
+            ..Self::new(url, opts.aggregator_max_age_secs)
+        })
+    }
+
+    pub fn new(url: Url, max_age_secs: u64) -> Self {
+        Self {
+            url,
+            max_age_secs,
+            denomination: "USD".to_string(),
+            timestamp_config: Default::default(),
+        }
+    }
+
+    async fn eth_call(&self, contract: &str, data: &str) -> Result<Value, Error> {
+        log::debug!("{} eth_call {} {}", self.url, contract, data);
+        Ok(reqwest::Client::new()
+            .post(self.url.clone())
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_call",
+                "params": [{ "to": contract, "data": data }, "latest"],
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?)
+    }
+
+    /// The price of the `currency_pair` and the time at which its answer was
+    /// last updated.
+    async fn get_exchange_rate(
+        &self,
+        currency_pair: CurrencyPair<Currency>,
+        _currency_store: &CurrencyStore<String>,
+    ) -> Result<(CurrencyPairAndPrice<Currency>, SystemTime), Error> {
+        // the contract address is the path of the base, e.g. ETH=0x5f4e...8419
+        let contract = currency_pair.base.path().ok_or(Error::InvalidAggregatorSymbol)?;
+        check_denomination(&self.denomination, &currency_pair)?;
+
+        // https://docs.chain.link/data-feeds/api-reference
+        let decimals =
+            extract_decimals(&self.eth_call(&contract, DECIMALS_SELECTOR).await?).ok_or(Error::InvalidResponse)?;
+        let answer = extract_answer(&self.eth_call(&contract, LATEST_ROUND_DATA_SELECTOR).await?, decimals)
+            .ok_or(Error::InvalidResponse)?;
+
+        let now = SystemTime::now();
+        let updated_at =
+            timestamp::normalize(&self.timestamp_config, answer.updated_at, now).ok_or(Error::InvalidResponse)?;
+        timestamp::check_skew(&self.timestamp_config, updated_at, now)?;
+        if is_stale(updated_at, now, self.max_age_secs) {
+            return Err(Error::StaleAggregatorAnswer(answer.updated_at));
+        }
+
+        Ok((
+            CurrencyPairAndPrice {
+                pair: currency_pair,
+                price: answer.price,
+            },
+            updated_at,
+        ))
+    }
+}
+
+#[async_trait]
+impl PriceFeed for AggregatorApi {
+    async fn get_price(
+        &self,
+        currency_pair: CurrencyPair<Currency>,
+        currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
+    ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+        let (currency_pair_and_price, _updated_at) = self
+            .get_timestamped_price(currency_pair, currency_store, price_source)
+            .await?;
+        Ok(currency_pair_and_price)
+    }
+
+    async fn get_timestamped_price(
+        &self,
+        currency_pair: CurrencyPair<Currency>,
+        currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
+    ) -> Result<(CurrencyPairAndPrice<Currency>, Option<SystemTime>), Error> {
+        if price_source != PriceSource::LastTrade {
+            return Err(Error::UnsupportedPriceSource(price_source));
+        }
+        let (currency_pair_and_price, updated_at) = self.get_exchange_rate(currency_pair, currency_store).await?;
+        Ok((currency_pair_and_price, Some(updated_at)))
+    }
+
+    fn set_timestamp_config(&mut self, timestamp_config: TimestampConfig) {
+        self.timestamp_config = timestamp_config;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(value: u128) -> String {
+        format!("{:064x}", value)
+    }
+
+    fn response(words: &[String]) -> Value {
+        json!({ "jsonrpc": "2.0", "id": 1, "result": format!("0x{}", words.concat()) })
+    }
+
+    #[test]
+    fn should_extract_answer() {
+        let round_data = response(&[
+            word(110680464442257320247),
+            word(200012345678),
+            word(1665000000),
+            word(1665000012),
+            word(110680464442257320247),
+        ]);
+        assert_eq!(extract_decimals(&response(&[word(8)])), Some(8));
+        assert_eq!(
+            extract_answer(&round_data, 8),
+            Some(AggregatorAnswer {
+                price: 2000.12345678,
+                updated_at: 1665000012
+            })
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_answers() {
+        // negative answer
+        let negative = "f".repeat(64);
+        let round_data = response(&[word(1), negative, word(0), word(0), word(1)]);
+        assert_eq!(extract_answer(&round_data, 8), None);
+        // truncated result
+        assert_eq!(extract_answer(&response(&[word(1), word(2)]), 8), None);
+        assert_eq!(extract_decimals(&json!({ "result": "0x" })), None);
+        assert_eq!(
+            extract_decimals(&json!({ "error": { "code": -32000, "message": "execution reverted" } })),
+            None
+        );
+    }
+
+    #[test]
+    fn should_detect_stale_answers() {
+        let at = |secs| std::time::UNIX_EPOCH + Duration::from_secs(secs);
+        assert!(!is_stale(at(1665000000), at(1665003600), 3600));
+        assert!(is_stale(at(1665000000), at(1665003601), 3600));
+        // clock skew
+        assert!(!is_stale(at(1665000010), at(1665000000), 3600));
+    }
+
+    #[test]
+    fn should_reject_other_denominations() {
+        let pair = |quote: &str| CurrencyPair {
+            base: "ETH=0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419".parse().unwrap(),
+            quote: quote.parse().unwrap(),
+        };
+        assert!(check_denomination("USD", &pair("USD")).is_ok());
+        assert!(check_denomination("ETH", &pair("ETH")).is_ok());
+        assert!(matches!(
+            check_denomination("USD", &pair("EUR")),
+            Err(Error::AggregatorDenominationMismatch { denomination, quote })
+                if denomination == "USD" && quote == "EUR"
+        ));
+    }
+}
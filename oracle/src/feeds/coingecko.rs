@@ -1,5 +1,9 @@
 use super::{get_http, PriceFeed};
-use crate::{config::CurrencyStore, currency::*, Error};
+use crate::{
+    config::{CurrencyStore, PriceSource},
+    currency::*,
+    Error,
+};
 use async_trait::async_trait;
 use clap::Parser;
 use reqwest::Url;
@@ -96,7 +100,11 @@ impl PriceFeed for CoinGeckoApi {
         &self,
         currency_pair: CurrencyPair<Currency>,
         currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
     ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+        if price_source != PriceSource::LastTrade {
+            return Err(Error::UnsupportedPriceSource(price_source));
+        }
         self.get_exchange_rate(currency_pair, currency_store).await
     }
 }
@@ -119,4 +127,20 @@ mod tests {
             Some(19148.24)
         )
     }
+
+    #[tokio::test]
+    async fn should_reject_order_book_price_sources() {
+        let currency_pair = CurrencyPair {
+            base: "BTC".parse().unwrap(),
+            quote: "USD".parse().unwrap(),
+        };
+        for price_source in [PriceSource::Mid, PriceSource::Bid, PriceSource::Ask] {
+            assert!(matches!(
+                CoinGeckoApi::default()
+                    .get_price(currency_pair.clone(), &CurrencyStore::new(), price_source)
+                    .await,
+                Err(Error::UnsupportedPriceSource(source)) if source == price_source
+            ));
+        }
+    }
 }
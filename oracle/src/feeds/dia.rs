@@ -1,5 +1,5 @@
-use super::{get_http, PriceFeed};
-use crate::{config::CurrencyStore, currency::*, Error};
+use super::{get_http, FeedClient};
+use crate::{currency::*, Error};
 use async_trait::async_trait;
 use clap::Parser;
 use reqwest::Url;
@@ -37,11 +37,7 @@ impl DiaApi {
         Self { url }
     }
 
-    async fn get_exchange_rate(
-        &self,
-        currency_pair: CurrencyPair<Currency>,
-        _currency_store: &CurrencyStore<String>,
-    ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
+    async fn get_exchange_rate(&self, currency_pair: &CurrencyPair<Currency>) -> Result<f64, Error> {
         if currency_pair.base.symbol() != "USD" {
             return Err(Error::InvalidDiaSymbol);
         }
@@ -51,23 +47,14 @@ impl DiaApi {
         let mut url = self.url.clone();
         url.set_path(&format!("{}/assetQuotation/{}", url.path(), token_path));
         let data = get_http(url).await?;
-        let price = extract_response(data).ok_or(Error::InvalidResponse)?;
-
-        Ok(CurrencyPairAndPrice {
-            pair: currency_pair,
-            price,
-        })
+        extract_response(data).ok_or(Error::InvalidResponse)
     }
 }
 
 #[async_trait]
-impl PriceFeed for DiaApi {
-    async fn get_price(
-        &self,
-        currency_pair: CurrencyPair<Currency>,
-        currency_store: &CurrencyStore<String>,
-    ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
-        self.get_exchange_rate(currency_pair, currency_store).await
+impl FeedClient for DiaApi {
+    async fn fetch(&self, pair: &CurrencyPair<Currency>) -> Result<f64, Error> {
+        self.get_exchange_rate(pair).await
     }
 }
 
@@ -1,7 +1,12 @@
 use super::{get_http, PriceFeed};
-use crate::{config::CurrencyStore, currency::*, Error};
+use crate::{
+    config::{CurrencyStore, PriceSource},
+    currency::*,
+    Error,
+};
 use async_trait::async_trait;
 use clap::Parser;
+use futures::future::try_join;
 use reqwest::Url;
 use serde_json::Value;
 
@@ -10,59 +15,123 @@ pub struct GateIoCli {
     /// Fetch the exchange rate from gate.io
     #[clap(long)]
     gateio_url: Option<Url>,
+
+    /// Treat gate.io markets which are not tradable as failed
+    #[clap(long)]
+    gateio_check_trading_status: bool,
 }
 
 pub struct GateIoApi {
     url: Url,
+    check_trading_status: bool,
 }
 
 impl Default for GateIoApi {
     fn default() -> Self {
         Self {
             url: Url::parse("https://api.gateio.ws/api/v4").unwrap(),
+            check_trading_status: false,
         }
     }
 }
 
-fn extract_response(value: &Value) -> Option<&'_ str> {
-    // uses the last trading price
-    value.get(0)?.get("last")?.as_str()
+fn extract_price(ticker: &Value, key: &str) -> Option<f64> {
+    ticker.get(key)?.as_str()?.parse().ok()
+}
+
+fn extract_response(value: &Value, price_source: PriceSource) -> Option<f64> {
+    let ticker = value.get(0)?;
+    match price_source {
+        PriceSource::LastTrade => extract_price(ticker, "last"),
+        PriceSource::Mid => Some((extract_price(ticker, "highest_bid")? + extract_price(ticker, "lowest_ask")?) / 2.0),
+        PriceSource::Bid => extract_price(ticker, "highest_bid"),
+        PriceSource::Ask => extract_price(ticker, "lowest_ask"),
+    }
+}
+
+/// Fails if the market cannot be traded in both directions, its last price is stale.
+fn check_trading_status(value: &Value, currency_pair_name: &str) -> Result<(), Error> {
+    match value.get("trade_status").and_then(Value::as_str) {
+        Some("tradable") => Ok(()),
+        // e.g. untradable, buyable or sellable
+        Some(_) => Err(Error::SymbolHalted(currency_pair_name.to_string())),
+        None => Err(Error::InvalidResponse),
+    }
+}
+
+fn extract_markets(value: &Value) -> Option<Vec<String>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|currency_pair| currency_pair.get("id")?.as_str().map(String::from))
+        .collect()
+}
+
+fn currency_pair_name(currency_pair: &CurrencyPair<Currency>) -> String {
+    format!(
+        "{}_{}",
+        currency_pair.base.path().unwrap_or_else(|| currency_pair.base.symbol()),
+        currency_pair
+            .quote
+            .path()
+            .unwrap_or_else(|| currency_pair.quote.symbol()),
+    )
 }
 
 impl GateIoApi {
     pub fn from_opts(opts: GateIoCli) -> Option<Self> {
-        opts.gateio_url.map(Self::new)
+        opts.gateio_url.map(|url| Self {
+            check_trading_status: opts.gateio_check_trading_status,
+            ..Self::new(url)
+        })
     }
 
     pub fn new(url: Url) -> Self {
-        Self { url }
+        Self {
+            url,
+            check_trading_status: false,
+        }
+    }
+
+    async fn get_trading_status(&self, currency_pair_name: &str) -> Result<(), Error> {
+        let mut url = self.url.clone();
+        url.set_path(&format!("{}/spot/currency_pairs/{}", url.path(), currency_pair_name));
+        check_trading_status(&get_http(url).await?, currency_pair_name)
     }
 
     async fn get_exchange_rate(
         &self,
         currency_pair: CurrencyPair<Currency>,
         _currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
     ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
         // https://www.gate.io/docs/developers/apiv4/en/
         let mut url = self.url.clone();
         url.set_path(&format!("{}/spot/tickers", url.path()));
-        url.set_query(Some(&format!(
-            "currency_pair={}_{}",
-            currency_pair.base.path().unwrap_or_else(|| currency_pair.base.symbol()),
-            currency_pair
-                .quote
-                .path()
-                .unwrap_or_else(|| currency_pair.quote.symbol()),
-        )));
+        let currency_pair_name = currency_pair_name(&currency_pair);
+        url.set_query(Some(&format!("currency_pair={}", currency_pair_name)));
 
-        let data = get_http(url).await?;
-        let exchange_rate = extract_response(&data).ok_or(Error::InvalidResponse)?.parse::<f64>()?;
+        let data = if self.check_trading_status {
+            try_join(get_http(url), self.get_trading_status(&currency_pair_name))
+                .await?
+                .0
+        } else {
+            get_http(url).await?
+        };
+        let exchange_rate = extract_response(&data, price_source).ok_or(Error::InvalidResponse)?;
 
         Ok(CurrencyPairAndPrice {
             pair: currency_pair,
             price: exchange_rate,
         })
     }
+
+    async fn get_currency_pairs(&self) -> Result<Vec<String>, Error> {
+        let mut url = self.url.clone();
+        url.set_path(&format!("{}/spot/currency_pairs", url.path()));
+        let data = get_http(url).await?;
+        extract_markets(&data).ok_or(Error::InvalidResponse)
+    }
 }
 
 #[async_trait]
@@ -71,8 +140,18 @@ impl PriceFeed for GateIoApi {
         &self,
         currency_pair: CurrencyPair<Currency>,
         currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
     ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
-        self.get_exchange_rate(currency_pair, currency_store).await
+        self.get_exchange_rate(currency_pair, currency_store, price_source)
+            .await
+    }
+
+    fn market_name(&self, currency_pair: &CurrencyPair<Currency>) -> Option<String> {
+        Some(currency_pair_name(currency_pair))
+    }
+
+    async fn get_markets(&self) -> Result<Option<Vec<String>>, Error> {
+        Ok(Some(self.get_currency_pairs().await?))
     }
 }
 
@@ -81,21 +160,56 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    fn ticker() -> Value {
+        json!([{
+            "currency_pair":"BTC_USD",
+            "last":"19137.68",
+            "lowest_ask":"19138.02",
+            "highest_bid":"19137.54",
+            "change_percentage":"0.21",
+            "base_volume":"2664.70331089",
+            "quote_volume":"50862972.351463",
+            "high_24h":"19265.75",
+            "low_24h":"18857.43"
+        }])
+    }
+
     #[test]
     fn should_extract_response() {
+        assert_eq!(extract_response(&ticker(), PriceSource::LastTrade), Some(19137.68))
+    }
+
+    #[test]
+    fn should_extract_each_price_source() {
+        assert_eq!(extract_response(&ticker(), PriceSource::Mid), Some(19137.78));
+        assert_eq!(extract_response(&ticker(), PriceSource::Bid), Some(19137.54));
+        assert_eq!(extract_response(&ticker(), PriceSource::Ask), Some(19138.02));
+    }
+
+    #[test]
+    fn should_fail_halted_markets() {
+        let currency_pair = |trade_status: &str| json!({ "id": "BTC_USDT", "base": "BTC", "quote": "USDT", "trade_status": trade_status });
+        assert!(check_trading_status(&currency_pair("tradable"), "BTC_USDT").is_ok());
+        for trade_status in ["untradable", "sellable"] {
+            assert!(matches!(
+                check_trading_status(&currency_pair(trade_status), "BTC_USDT"),
+                Err(Error::SymbolHalted(market)) if market == "BTC_USDT"
+            ));
+        }
+        assert!(matches!(
+            check_trading_status(&json!({ "label": "INVALID_CURRENCY_PAIR" }), "BTC_USDT"),
+            Err(Error::InvalidResponse)
+        ));
+    }
+
+    #[test]
+    fn should_extract_markets() {
         assert_eq!(
-            extract_response(&json!([{
-                "currency_pair":"BTC_USD",
-                "last":"19137.68",
-                "lowest_ask":"19138.02",
-                "highest_bid":"19137.54",
-                "change_percentage":"0.21",
-                "base_volume":"2664.70331089",
-                "quote_volume":"50862972.351463",
-                "high_24h":"19265.75",
-                "low_24h":"18857.43"
-            }])),
-            Some("19137.68")
+            extract_markets(&json!([
+                { "id": "BTC_USDT", "base": "BTC", "quote": "USDT", "trade_status": "tradable" },
+                { "id": "DOT_USDT", "base": "DOT", "quote": "USDT", "trade_status": "tradable" }
+            ])),
+            Some(vec!["BTC_USDT".to_string(), "DOT_USDT".to_string()])
         )
     }
 }
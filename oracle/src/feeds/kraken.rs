@@ -1,80 +1,160 @@
 use super::{get_http, PriceFeed};
-use crate::{config::CurrencyStore, currency::*, Error};
+use crate::{
+    config::{CurrencyStore, PriceSource},
+    currency::*,
+    Error,
+};
 use async_trait::async_trait;
 use clap::Parser;
+use futures::future::try_join;
 use reqwest::Url;
 use serde_json::Value;
+use std::collections::BTreeSet;
 
 #[derive(Parser, Debug, Clone)]
 pub struct KrakenCli {
     /// Fetch the exchange rate from Kraken
     #[clap(long)]
     kraken_url: Option<Url>,
+
+    /// Treat Kraken markets which are not open for new orders as failed
+    #[clap(long)]
+    kraken_check_trading_status: bool,
 }
 
 pub struct KrakenApi {
     url: Url,
+    check_trading_status: bool,
 }
 
 impl Default for KrakenApi {
     fn default() -> Self {
         Self {
             url: Url::parse("https://api.kraken.com/0").unwrap(),
+            check_trading_status: false,
         }
     }
 }
 
-fn extract_response(value: &Value) -> Option<&'_ str> {
-    value
+fn extract_price(ticker: &Value, key: &str) -> Option<f64> {
+    ticker.get(key)?.as_array()?.get(0)?.as_str()?.parse().ok()
+}
+
+fn extract_response(value: &Value, price_source: PriceSource) -> Option<f64> {
+    let ticker = value
         .get("result")?
         .as_object()?
         .iter()
         .last()? // we are only fetching one anyway
-        .1
-        .get("p")?
-        .as_array()?
-        .get(0)?
-        .as_str()
+        .1;
+    match price_source {
+        // today's VWAP
+        PriceSource::LastTrade => extract_price(ticker, "p"),
+        PriceSource::Mid => Some((extract_price(ticker, "b")? + extract_price(ticker, "a")?) / 2.0),
+        PriceSource::Bid => extract_price(ticker, "b"),
+        PriceSource::Ask => extract_price(ticker, "a"),
+    }
+}
+
+/// Fails if the market does not accept new orders, its last price is stale.
+fn check_trading_status(value: &Value, asset_pair_name: &str) -> Result<(), Error> {
+    let status = value
+        .get("result")
+        .and_then(Value::as_object)
+        .and_then(|result| result.values().last()) // we are only fetching one anyway
+        .and_then(|asset_pair| asset_pair.get("status")?.as_str())
+        .ok_or(Error::InvalidResponse)?;
+    match status {
+        "online" | "post_only" | "limit_only" => Ok(()),
+        // e.g. cancel_only or reduce_only
+        _ => Err(Error::SymbolHalted(asset_pair_name.to_string())),
+    }
+}
+
+fn extract_markets(value: &Value) -> Option<BTreeSet<String>> {
+    // pairs can be requested by their name or altname, e.g. XXBTZUSD or XBTUSD,
+    // which are often the same
+    Some(
+        value
+            .get("result")?
+            .as_object()?
+            .iter()
+            .flat_map(|(name, asset_pair)| {
+                std::iter::once(name.clone()).chain(asset_pair.get("altname").and_then(Value::as_str).map(String::from))
+            })
+            .collect(),
+    )
+}
+
+// NOTE: Kraken prefixes older cryptocurrencies with "X" and fiat with "Z"
+fn asset_pair_name(currency_pair: &CurrencyPair<Currency>) -> String {
+    format!(
+        "{}{}",
+        currency_pair.base.path().unwrap_or_else(|| currency_pair.base.symbol()),
+        currency_pair
+            .quote
+            .path()
+            .unwrap_or_else(|| currency_pair.quote.symbol()),
+    )
 }
 
 impl KrakenApi {
     pub fn from_opts(opts: KrakenCli) -> Option<Self> {
-        opts.kraken_url.map(Self::new)
+        opts.kraken_url.map(|url| Self {
+            check_trading_status: opts.kraken_check_trading_status,
+            ..Self::new(url)
+        })
     }
 
     pub fn new(url: Url) -> Self {
-        Self { url }
+        Self {
+            url,
+            check_trading_status: false,
+        }
+    }
+
+    async fn get_trading_status(&self, asset_pair_name: &str) -> Result<(), Error> {
+        let mut url = self.url.clone();
+        url.set_path(&format!("{}/public/AssetPairs", url.path()));
+        url.set_query(Some(&format!("pair={}", asset_pair_name)));
+        check_trading_status(&get_http(url).await?, asset_pair_name)
     }
 
     async fn get_exchange_rate(
         &self,
         currency_pair: CurrencyPair<Currency>,
         _currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
     ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
-        // NOTE: Kraken prefixes older cryptocurrencies with "X" and fiat with "Z"
-        let asset_pair_name = format!(
-            "{}{}",
-            currency_pair.base.path().unwrap_or_else(|| currency_pair.base.symbol()),
-            currency_pair
-                .quote
-                .path()
-                .unwrap_or_else(|| currency_pair.quote.symbol()),
-        );
+        let asset_pair_name = asset_pair_name(&currency_pair);
 
         // https://docs.kraken.com/rest/
         let mut url = self.url.clone();
         url.set_path(&format!("{}/public/Ticker", url.path()));
         url.set_query(Some(&format!("pair={}", asset_pair_name)));
 
-        // get today's VWAP
-        let data = get_http(url).await?;
-        let exchange_rate = extract_response(&data).ok_or(Error::InvalidResponse)?.parse::<f64>()?;
+        let data = if self.check_trading_status {
+            try_join(get_http(url), self.get_trading_status(&asset_pair_name))
+                .await?
+                .0
+        } else {
+            get_http(url).await?
+        };
+        let exchange_rate = extract_response(&data, price_source).ok_or(Error::InvalidResponse)?;
 
         Ok(CurrencyPairAndPrice {
             pair: currency_pair,
             price: exchange_rate,
         })
     }
+
+    async fn get_asset_pairs(&self) -> Result<Vec<String>, Error> {
+        let mut url = self.url.clone();
+        url.set_path(&format!("{}/public/AssetPairs", url.path()));
+        let data = get_http(url).await?;
+        let markets = extract_markets(&data).ok_or(Error::InvalidResponse)?;
+        Ok(markets.into_iter().collect())
+    }
 }
 
 #[async_trait]
@@ -83,8 +163,18 @@ impl PriceFeed for KrakenApi {
         &self,
         currency_pair: CurrencyPair<Currency>,
         currency_store: &CurrencyStore<String>,
+        price_source: PriceSource,
     ) -> Result<CurrencyPairAndPrice<Currency>, Error> {
-        self.get_exchange_rate(currency_pair, currency_store).await
+        self.get_exchange_rate(currency_pair, currency_store, price_source)
+            .await
+    }
+
+    fn market_name(&self, currency_pair: &CurrencyPair<Currency>) -> Option<String> {
+        Some(asset_pair_name(currency_pair))
+    }
+
+    async fn get_markets(&self) -> Result<Option<Vec<String>>, Error> {
+        Ok(Some(self.get_asset_pairs().await?))
     }
 }
 
@@ -93,27 +183,71 @@ mod tests {
     use super::*;
     use serde_json::json;
 
-    #[test]
-    fn should_extract_response() {
-        assert_eq!(
-            extract_response(&json!({
-                "error":[],
-                "result": {
-                        "XXBTZUSD": {
-                                "a":["19141.50000","3","3.000"],
-                                "b":["19141.40000","7","7.000"],
-                                "c":["19145.00000","0.01022591"],
-                                "v":["647.22057875","2415.97751491"],
-                                "p":["19105.89558","19068.90458"],
-                                "t":[4359,13327],
-                                "l":["19028.50000","18860.00000"],
-                                "h":["19190.00000","19259.40000"],
-                                "o":"19050.00000"
-                        }
+    fn ticker() -> Value {
+        json!({
+            "error":[],
+            "result": {
+                    "XXBTZUSD": {
+                            "a":["19141.50000","3","3.000"],
+                            "b":["19141.40000","7","7.000"],
+                            "c":["19145.00000","0.01022591"],
+                            "v":["647.22057875","2415.97751491"],
+                            "p":["19105.89558","19068.90458"],
+                            "t":[4359,13327],
+                            "l":["19028.50000","18860.00000"],
+                            "h":["19190.00000","19259.40000"],
+                            "o":"19050.00000"
                     }
                 }
-            )),
-            Some("19105.89558")
+            }
         )
     }
+
+    #[test]
+    fn should_extract_response() {
+        assert_eq!(extract_response(&ticker(), PriceSource::LastTrade), Some(19105.89558))
+    }
+
+    #[test]
+    fn should_extract_each_price_source() {
+        assert_eq!(extract_response(&ticker(), PriceSource::Mid), Some(19141.45));
+        assert_eq!(extract_response(&ticker(), PriceSource::Bid), Some(19141.4));
+        assert_eq!(extract_response(&ticker(), PriceSource::Ask), Some(19141.5));
+    }
+
+    #[test]
+    fn should_fail_halted_markets() {
+        let asset_pair = |status: &str| {
+            json!({
+                "error": [],
+                "result": { "XXBTZUSD": { "altname": "XBTUSD", "wsname": "XBT/USD", "status": status } }
+            })
+        };
+        assert!(check_trading_status(&asset_pair("online"), "XXBTZUSD").is_ok());
+        assert!(check_trading_status(&asset_pair("limit_only"), "XXBTZUSD").is_ok());
+        assert!(matches!(
+            check_trading_status(&asset_pair("cancel_only"), "XXBTZUSD"),
+            Err(Error::SymbolHalted(market)) if market == "XXBTZUSD"
+        ));
+        assert!(matches!(
+            check_trading_status(&json!({ "error": ["EQuery:Unknown asset pair"] }), "XXBTZUSD"),
+            Err(Error::InvalidResponse)
+        ));
+    }
+
+    #[test]
+    fn should_extract_markets() {
+        let markets = extract_markets(&json!({
+            "error": [],
+            "result": {
+                "XXBTZUSD": { "altname": "XBTUSD", "wsname": "XBT/USD" },
+                "DOTUSD": { "altname": "DOTUSD", "wsname": "DOT/USD" }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            markets.into_iter().collect::<Vec<_>>(),
+            vec!["DOTUSD", "XBTUSD", "XXBTZUSD"]
+        );
+    }
 }
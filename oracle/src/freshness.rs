@@ -0,0 +1,107 @@
+use crate::{currency::*, Error};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// The last successful update of each pair, so that a price which has not
+/// been updated within the maximum age of its pair is not published.
+#[derive(Debug, Default)]
+pub struct PriceFreshness {
+    updates: BTreeMap<(String, String), (Instant, Option<Duration>)>,
+}
+
+impl PriceFreshness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a successful update of the price, pairs without a maximum age
+    /// never become stale.
+    pub fn record(&mut self, now: Instant, price: &CurrencyPairAndPrice<Currency>, max_age: Option<Duration>) {
+        self.updates
+            .insert((price.pair.base.symbol(), price.pair.quote.symbol()), (now, max_age));
+    }
+
+    /// Fail if the last update of the price is older than the maximum age, a
+    /// price which was never recorded has no known age and is not rejected.
+    pub fn check(&self, now: Instant, price: &CurrencyPairAndPrice<Currency>) -> Result<(), Error> {
+        match self.updates.get(&(price.pair.base.symbol(), price.pair.quote.symbol())) {
+            Some((updated_at, Some(max_age))) => {
+                let age = now.saturating_duration_since(*updated_at);
+                if age > *max_age {
+                    Err(Error::StalePrice { age, max_age: *max_age })
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The prices which are fresh, stale prices are logged and left out.
+    pub fn fresh_prices(
+        &self,
+        now: Instant,
+        prices: &[CurrencyPairAndPrice<Currency>],
+    ) -> Vec<CurrencyPairAndPrice<Currency>> {
+        prices
+            .iter()
+            .filter(|price| match self.check(now, price) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::error!("Not publishing {}: {}", price.pair, err);
+                    false
+                }
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_reject_stale_prices() {
+        let start = Instant::now();
+        let mut freshness = PriceFreshness::new();
+        freshness.record(start, &price("BTC", "USD", 20000.0), Some(Duration::from_secs(60)));
+        freshness.record(start, &price("DOT", "USD", 5.0), None);
+
+        assert!(freshness
+            .check(start + Duration::from_secs(60), &price("BTC", "USD", 20000.0))
+            .is_ok());
+        assert!(matches!(
+            freshness.check(start + Duration::from_secs(61), &price("BTC", "USD", 20000.0)),
+            Err(Error::StalePrice { age, max_age })
+                if age == Duration::from_secs(61) && max_age == Duration::from_secs(60)
+        ));
+        // pairs without a maximum age and unknown pairs are not stale
+        assert!(freshness
+            .check(start + Duration::from_secs(3600), &price("DOT", "USD", 5.0))
+            .is_ok());
+        assert!(freshness
+            .check(start + Duration::from_secs(3600), &price("KSM", "USD", 30.0))
+            .is_ok());
+
+        assert_eq!(
+            freshness.fresh_prices(
+                start + Duration::from_secs(61),
+                &[price("BTC", "USD", 20000.0), price("DOT", "USD", 5.0)]
+            ),
+            vec![price("DOT", "USD", 5.0)]
+        );
+
+        // a new update resets the age
+        freshness.record(
+            start + Duration::from_secs(61),
+            &price("BTC", "USD", 20100.0),
+            Some(Duration::from_secs(60)),
+        );
+        assert!(freshness
+            .check(start + Duration::from_secs(61), &price("BTC", "USD", 20100.0))
+            .is_ok());
+    }
+}
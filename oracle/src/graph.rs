@@ -0,0 +1,141 @@
+use crate::currency::*;
+
+/// The pairs which are available from the feeds, where two pairs are
+/// connected if they share a currency. Used to suggest a path for a price
+/// which is not configured yet.
+#[derive(Debug, Clone)]
+pub struct CurrencyGraph<Currency> {
+    pairs: Vec<CurrencyPair<Currency>>,
+}
+
+impl<Currency: Clone + PartialEq> CurrencyGraph<Currency> {
+    /// Build the graph from the `pairs`, duplicates are ignored.
+    pub fn new(pairs: impl IntoIterator<Item = CurrencyPair<Currency>>) -> Self {
+        let mut graph = Self { pairs: Vec::new() };
+        for pair in pairs {
+            if pair.base != pair.quote && !graph.pairs.contains(&pair) {
+                graph.pairs.push(pair);
+            }
+        }
+        graph
+    }
+
+    /// The fewest pairs leading from `from` to `to`, in order, found by a
+    /// breadth-first search. Each currency is visited at most once, so the
+    /// path is valid for a price of `from` in `to`.
+    pub fn shortest_path(&self, from: &Currency, to: &Currency) -> Option<Vec<CurrencyPair<Currency>>> {
+        if from == to {
+            return None;
+        }
+        // every currency which was reached, with the index of the pair and
+        // of the currency it was reached from
+        let mut reached: Vec<(Currency, Option<(usize, usize)>)> = vec![(from.clone(), None)];
+        let mut next = 0;
+        while let Some((currency, _)) = reached.get(next) {
+            let currency = currency.clone();
+            for (index, pair) in self.pairs.iter().enumerate() {
+                let other = if pair.base == currency {
+                    &pair.quote
+                } else if pair.quote == currency {
+                    &pair.base
+                } else {
+                    continue;
+                };
+                if reached.iter().any(|(visited, _)| visited == other) {
+                    continue;
+                }
+                reached.push((other.clone(), Some((index, next))));
+                if other == to {
+                    return Some(self.path_to(&reached, reached.len() - 1));
+                }
+            }
+            next += 1;
+        }
+        None
+    }
+
+    fn path_to(
+        &self,
+        reached: &[(Currency, Option<(usize, usize)>)],
+        mut current: usize,
+    ) -> Vec<CurrencyPair<Currency>> {
+        let mut path = Vec::new();
+        while let (_, Some((pair, previous))) = &reached[current] {
+            path.push(self.pairs[*pair].clone());
+            current = *previous;
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{Aggregation, PathHop, PriceConfig},
+        feeds::FeedName,
+    };
+    use std::collections::BTreeMap;
+
+    fn pair(value: &str) -> CurrencyPair<Currency> {
+        value.parse().unwrap()
+    }
+
+    fn currency(value: &str) -> Currency {
+        value.parse().unwrap()
+    }
+
+    fn price_config(pair: CurrencyPair<Currency>, path: Vec<CurrencyPair<Currency>>) -> PriceConfig<Currency> {
+        PriceConfig {
+            pair,
+            value: None,
+            feeds: vec![(FeedName::Kraken, path.into_iter().map(PathHop::from).collect())]
+                .into_iter()
+                .collect(),
+            groups: BTreeMap::new(),
+            min_publish_interval_secs: None,
+            reference_check: None,
+            plausible_range: None,
+            aggregation: Aggregation::Median,
+            max_age_secs: None,
+            max_deviation_pct: None,
+        }
+    }
+
+    #[test]
+    fn should_find_shortest_paths() {
+        let graph = CurrencyGraph::new(vec![
+            pair("BTC/USD"),
+            pair("KSM/BTC"),
+            pair("KSM/DOT"),
+            pair("DOT/USDT"),
+            pair("USDT/USD"),
+            pair("USDT/USD"),
+            pair("ETH/EUR"),
+        ]);
+
+        for (from, to, expected) in [
+            ("BTC", "USD", vec![pair("BTC/USD")]),
+            ("USD", "BTC", vec![pair("BTC/USD")]),
+            ("KSM", "USD", vec![pair("KSM/BTC"), pair("BTC/USD")]),
+            // shorter than through the dollar
+            ("DOT", "BTC", vec![pair("KSM/DOT"), pair("KSM/BTC")]),
+            ("USDT", "BTC", vec![pair("USDT/USD"), pair("BTC/USD")]),
+        ] {
+            let path = graph.shortest_path(&currency(from), &currency(to)).unwrap();
+            assert_eq!(path, expected);
+            // the path can be used as is
+            let price_pair = CurrencyPair {
+                base: currency(from),
+                quote: currency(to),
+            };
+            assert!(price_config(price_pair, path).validate().is_ok());
+        }
+
+        // no pair connects to the euro
+        assert_eq!(graph.shortest_path(&currency("KSM"), &currency("EUR")), None);
+        assert_eq!(graph.shortest_path(&currency("KSM"), &currency("LTC")), None);
+        assert_eq!(graph.shortest_path(&currency("KSM"), &currency("KSM")), None);
+    }
+}
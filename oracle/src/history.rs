@@ -0,0 +1,164 @@
+use crate::{currency::*, metrics::FeedMetrics};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Interval of `value ± k·σ` where σ is the sample standard deviation
+/// of the recently published values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    pub value: f64,
+    pub std_dev: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl fmt::Display for ConfidenceInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}, {}] (σ = {})",
+            self.value, self.lower, self.upper, self.std_dev
+        )
+    }
+}
+
+/// Rolling window of the recently published values of each pair.
+#[derive(Debug)]
+pub struct PriceHistory {
+    window: usize,
+    k: f64,
+    /// Values older than this are considered stale and discarded
+    max_gap: Duration,
+    values: BTreeMap<(String, String), VecDeque<(Instant, f64)>>,
+}
+
+impl PriceHistory {
+    pub fn new(window: usize, k: f64, max_gap: Duration) -> Self {
+        Self {
+            window,
+            k,
+            max_gap,
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Record the published price and return the updated confidence interval,
+    /// this requires at least two values in the window.
+    pub fn record(&mut self, now: Instant, price: &CurrencyPairAndPrice<Currency>) -> Option<ConfidenceInterval> {
+        let values = self
+            .values
+            .entry((price.pair.base.symbol(), price.pair.quote.symbol()))
+            .or_default();
+
+        // reset after a long gap, old values do not reflect the current variance
+        if matches!(values.back(), Some((at, _)) if now.saturating_duration_since(*at) > self.max_gap) {
+            values.clear();
+        }
+        values.push_back((now, price.price));
+        while values.len() > self.window {
+            values.pop_front();
+        }
+
+        let std_dev = std_dev(values.iter().map(|(_, value)| *value))?;
+        Some(ConfidenceInterval {
+            value: price.price,
+            std_dev,
+            lower: price.price - self.k * std_dev,
+            upper: price.price + self.k * std_dev,
+        })
+    }
+
+    /// Record the published `prices`, their confidence intervals are logged
+    /// and exported to the `metrics`.
+    pub fn record_all(
+        &mut self,
+        now: Instant,
+        prices: &[CurrencyPairAndPrice<Currency>],
+        metrics: Option<&FeedMetrics>,
+    ) {
+        for price in prices {
+            if let Some(confidence_interval) = self.record(now, price) {
+                log::info!("Confidence interval for {}: {}", price.pair, confidence_interval);
+                if let Some(metrics) = metrics {
+                    metrics.set_confidence_interval(&price.pair, &confidence_interval);
+                }
+            }
+        }
+    }
+}
+
+/// Sample standard deviation, `None` if there are fewer than two values.
+fn std_dev(values: impl Iterator<Item = f64> + Clone) -> Option<f64> {
+    let count = values.clone().count();
+    if count < 2 {
+        return None;
+    }
+    let mean = values.clone().sum::<f64>() / count as f64;
+    let variance = values.map(|value| (value - mean).powi(2)).sum::<f64>() / (count - 1) as f64;
+    Some(variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_compute_confidence_interval() {
+        let start = Instant::now();
+        let mut history = PriceHistory::new(4, 2.0, Duration::from_secs(60));
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert_eq!(history.record(at(0), &price("BTC", "DOT", 10.0)), None);
+        // mean 11, variance ((10 - 11)^2 + (12 - 11)^2) / 1 = 2
+        let interval = history.record(at(10), &price("BTC", "DOT", 12.0)).unwrap();
+        assert_eq!(interval.std_dev, 2f64.sqrt());
+        assert_eq!(interval.lower, 12.0 - 2.0 * 2f64.sqrt());
+        assert_eq!(interval.upper, 12.0 + 2.0 * 2f64.sqrt());
+
+        history.record(at(20), &price("BTC", "DOT", 14.0));
+        history.record(at(30), &price("BTC", "DOT", 16.0));
+        // 10 falls out of the window: mean 15, variance (9 + 1 + 1 + 9) / 3
+        let interval = history.record(at(40), &price("BTC", "DOT", 18.0)).unwrap();
+        assert_eq!(interval.std_dev, (20.0f64 / 3.0).sqrt());
+        assert_eq!(interval.lower, 18.0 - 2.0 * (20.0f64 / 3.0).sqrt());
+    }
+
+    #[test]
+    fn should_reset_after_gap() {
+        let start = Instant::now();
+        let mut history = PriceHistory::new(10, 2.0, Duration::from_secs(60));
+        history.record(start, &price("BTC", "DOT", 10.0));
+        assert!(history
+            .record(start + Duration::from_secs(60), &price("BTC", "DOT", 20.0))
+            .is_some());
+        assert_eq!(
+            history.record(start + Duration::from_secs(121), &price("BTC", "DOT", 30.0)),
+            None
+        );
+        // mean 35, variance (25 + 25) / 1
+        let interval = history
+            .record(start + Duration::from_secs(130), &price("BTC", "DOT", 40.0))
+            .unwrap();
+        assert_eq!(interval.std_dev, 50f64.sqrt());
+    }
+
+    #[test]
+    fn should_export_confidence_intervals() {
+        let start = Instant::now();
+        let metrics = FeedMetrics::new().unwrap();
+        let mut history = PriceHistory::new(4, 2.0, Duration::from_secs(60));
+        history.record_all(start, &[price("BTC", "DOT", 10.0)], Some(&metrics));
+        history.record_all(
+            start + Duration::from_secs(10),
+            &[price("BTC", "DOT", 12.0)],
+            Some(&metrics),
+        );
+
+        let bound = |bound: &str| metrics.confidence_interval.with_label_values(&["BTC/DOT", bound]).get();
+        assert_eq!(bound("lower"), 12.0 - 2.0 * 2f64.sqrt());
+        assert_eq!(bound("upper"), 12.0 + 2.0 * 2f64.sqrt());
+    }
+}
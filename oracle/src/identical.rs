@@ -0,0 +1,149 @@
+use crate::{currency::*, feeds::FeedName, metrics::FeedMetrics};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Rolling window of the price each feed returned for each pair, to detect
+/// feeds which are likely proxying the same upstream source.
+#[derive(Debug)]
+pub struct IdenticalFeeds {
+    window: usize,
+    ticks: BTreeMap<(String, String), VecDeque<BTreeMap<FeedName, f64>>>,
+}
+
+impl IdenticalFeeds {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            ticks: BTreeMap::new(),
+        }
+    }
+
+    /// Record the price of each feed for the `pair` in this tick and return the
+    /// feeds whose prices were identical in every tick of a full window. Prices
+    /// which did not change within the window are expected to match, so these
+    /// are not flagged.
+    pub fn record(
+        &mut self,
+        pair: &CurrencyPair<Currency>,
+        feed_prices: BTreeMap<FeedName, f64>,
+        metrics: Option<&FeedMetrics>,
+    ) -> Vec<(FeedName, FeedName)> {
+        let ticks = self.ticks.entry((pair.base.symbol(), pair.quote.symbol())).or_default();
+        let feeds = feed_prices.keys().cloned().collect::<Vec<_>>();
+        ticks.push_back(feed_prices);
+        while ticks.len() > self.window {
+            ticks.pop_front();
+        }
+        if ticks.len() < self.window {
+            return Vec::new();
+        }
+
+        let mut identical = Vec::new();
+        for (index, feed) in feeds.iter().enumerate() {
+            for other_feed in &feeds[index + 1..] {
+                let is_identical = match ticks
+                    .iter()
+                    .map(|tick| Some((*tick.get(feed)?, *tick.get(other_feed)?)))
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(prices) => {
+                        prices
+                            .iter()
+                            .all(|(price, other_price)| price.to_bits() == other_price.to_bits())
+                            && prices.windows(2).any(|prices| prices[0].0 != prices[1].0)
+                    }
+                    // one of the feeds failed within the window
+                    None => false,
+                };
+                if let Some(metrics) = metrics {
+                    metrics.set_identical_feeds(pair, feed, other_feed, is_identical);
+                }
+                if is_identical {
+                    log::warn!(
+                        "{} and {} returned identical prices for {} in the last {} ticks",
+                        feed,
+                        other_feed,
+                        pair,
+                        self.window
+                    );
+                    identical.push((feed.clone(), other_feed.clone()));
+                }
+            }
+        }
+        identical
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> CurrencyPair<Currency> {
+        CurrencyPair {
+            base: "BTC".parse().unwrap(),
+            quote: "USD".parse().unwrap(),
+        }
+    }
+
+    fn feed_prices(kraken: f64, gateio: f64, coingecko: f64) -> BTreeMap<FeedName, f64> {
+        vec![
+            (FeedName::Kraken, kraken),
+            (FeedName::GateIo, gateio),
+            (FeedName::CoinGecko, coingecko),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn should_flag_identical_feeds() {
+        let metrics = FeedMetrics::new().unwrap();
+        let mut identical_feeds = IdenticalFeeds::new(3);
+
+        // the window is not full yet
+        assert!(identical_feeds
+            .record(&pair(), feed_prices(100.0, 100.0, 101.0), Some(&metrics))
+            .is_empty());
+        assert!(identical_feeds
+            .record(&pair(), feed_prices(102.5, 102.5, 102.0), Some(&metrics))
+            .is_empty());
+        assert_eq!(
+            identical_feeds.record(&pair(), feed_prices(99.25, 99.25, 99.0), Some(&metrics)),
+            vec![(FeedName::Kraken, FeedName::GateIo)]
+        );
+        assert_eq!(
+            metrics
+                .identical_feeds
+                .with_label_values(&["BTC/USD", "kraken", "gateio"])
+                .get(),
+            1
+        );
+
+        // clears once the feeds differ
+        assert!(identical_feeds
+            .record(&pair(), feed_prices(99.5, 99.4, 99.0), Some(&metrics))
+            .is_empty());
+        assert_eq!(
+            metrics
+                .identical_feeds
+                .with_label_values(&["BTC/USD", "kraken", "gateio"])
+                .get(),
+            0
+        );
+    }
+
+    #[test]
+    fn should_not_flag_unchanged_or_missing_prices() {
+        let mut identical_feeds = IdenticalFeeds::new(2);
+        for _ in 0..3 {
+            assert!(identical_feeds
+                .record(&pair(), feed_prices(1.0, 1.0, 1.0), None)
+                .is_empty());
+        }
+
+        let mut identical_feeds = IdenticalFeeds::new(2);
+        identical_feeds.record(&pair(), vec![(FeedName::Kraken, 1.0)].into_iter().collect(), None);
+        assert!(identical_feeds
+            .record(&pair(), feed_prices(2.0, 2.0, 3.0), None)
+            .is_empty());
+    }
+}
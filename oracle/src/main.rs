@@ -1,21 +1,47 @@
+mod api;
 mod config;
+mod control;
 mod currency;
 mod error;
 mod feeds;
-
+mod freshness;
+mod graph;
+mod history;
+mod identical;
+mod metrics;
+mod reference;
+mod schedule;
+mod shadow;
+mod sinks;
+mod timestamp;
+mod watcher;
+
+use api::RoutesApi;
 use backoff::{future::retry_notify, ExponentialBackoff};
 use clap::Parser;
-use config::{CurrencyStore, OracleConfig};
+use config::{OracleConfig, PriceConfig};
+use control::PublishControl;
 use currency::*;
 use error::Error;
-use futures::future::join_all;
+use freshness::PriceFreshness;
 use git_version::git_version;
+use history::PriceHistory;
+use identical::IdenticalFeeds;
+use metrics::FeedMetrics;
 use runtime::{
     cli::{parse_duration_ms, ProviderUserOpts},
-    CurrencyId, FixedU128, InterBtcParachain, InterBtcSigner, OracleKey, OraclePallet, ShutdownSender, TryFromSymbol,
+    InterBtcParachain, InterBtcSigner, OraclePallet, ShutdownSender,
+};
+use schedule::{Schedule, ScheduleKey};
+use sinks::{ParachainSink, PriceSinks};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{path::PathBuf, time::Duration};
 use tokio::{join, time::sleep};
+use watcher::ConfigWatcher;
 
 const VERSION: &str = git_version!(args = ["--tags"]);
 const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
@@ -67,9 +93,89 @@ struct Opts {
     #[clap(flatten)]
     kraken: feeds::KrakenCli,
 
+    /// Connection settings for on-chain aggregators
+    #[clap(flatten)]
+    aggregator: feeds::AggregatorCli,
+
+    /// Timeout in milliseconds for a single price feed request
+    #[clap(long, value_parser = parse_duration_ms, default_value = "60000")]
+    feed_timeout_ms: Duration,
+
+    /// Serve per-feed latency and timeout metrics on this port
+    #[clap(long)]
+    feed_metrics_port: Option<u16>,
+
+    /// Serve the routes of each price in the active config as JSON at `/routes` on this port
+    #[clap(long)]
+    routes_port: Option<u16>,
+
+    /// Serve controls to pause and resume publishing at `/publishing/{pause,resume,status}` on this port
+    #[clap(long)]
+    control_port: Option<u16>,
+
+    /// Token for the publishing controls, passed as `Authorization: Bearer <token>`
+    #[clap(long)]
+    control_token: Option<String>,
+
+    /// Start with publishing paused, prices are computed but not published until resumed
+    #[clap(long)]
+    start_paused: bool,
+
+    /// Check that all configured markets are listed by their feeds on startup
+    #[clap(long)]
+    validate_markets: bool,
+
+    /// Print the requests made to each feed as JSON and exit
+    #[clap(long)]
+    print_request_plan: bool,
+
+    /// Print all currencies used by the prices as JSON and exit
+    #[clap(long)]
+    print_currencies: bool,
+
+    /// Print all errors and warnings of the config (with its overrides) as JSON and exit
+    #[clap(long)]
+    print_validation_report: bool,
+
+    /// Print the shortest path for a price such as `KSM/USD` over the pairs
+    /// read by the enabled feeds as JSON and exit
+    #[clap(long)]
+    suggest_path: Option<CurrencyPair<Currency>>,
+
+    /// Number of recently published values per price used for the confidence interval
+    #[clap(long, default_value = "20")]
+    confidence_window: usize,
+
+    /// Width of the confidence interval in standard deviations
+    #[clap(long, default_value = "2")]
+    confidence_k: f64,
+
+    /// Discard the published values of a price if it has not been published for this long
+    #[clap(long, value_parser = parse_duration_ms, default_value = "3600000")]
+    confidence_max_gap_ms: Duration,
+
+    /// Warn if two feeds return identical prices for a pair this many times in a row
+    #[clap(long)]
+    identical_feeds_window: Option<usize>,
+
     /// Feed / price config.
     #[clap(long, default_value = "./oracle-config.json")]
     oracle_config: PathBuf,
+
+    /// Layer this config on top of the feed / price config, replacing its
+    /// currencies by key and its prices by pair. Can be repeated, later
+    /// files take precedence
+    #[clap(long)]
+    oracle_config_override: Vec<PathBuf>,
+
+    /// How often to check the feed / price config and its overrides for changes
+    #[clap(long, value_parser = parse_duration_ms, default_value = "1000")]
+    config_poll_interval_ms: Duration,
+
+    /// Compute the prices of this config alongside the live config and report
+    /// their divergence without publishing them
+    #[clap(long)]
+    shadow_oracle_config: Option<PathBuf>,
 }
 
 fn get_exponential_backoff() -> ExponentialBackoff {
@@ -81,7 +187,11 @@ fn get_exponential_backoff() -> ExponentialBackoff {
     }
 }
 
-async fn submit_bitcoin_fees(parachain_rpc: &InterBtcParachain, maybe_bitcoin_fee: Option<f64>) -> Result<(), Error> {
+async fn submit_bitcoin_fees(
+    parachain_rpc: &InterBtcParachain,
+    maybe_bitcoin_fee: Option<f64>,
+    rounding_mode: RoundingMode,
+) -> Result<(), Error> {
     let bitcoin_fee = if let Some(bitcoin_fee) = maybe_bitcoin_fee {
         bitcoin_fee
     } else {
@@ -96,7 +206,7 @@ async fn submit_bitcoin_fees(parachain_rpc: &InterBtcParachain, maybe_bitcoin_fe
     );
 
     parachain_rpc
-        .set_bitcoin_fees(FixedU128::from_float(bitcoin_fee))
+        .set_bitcoin_fees(rounding_mode.to_fixed(bitcoin_fee).ok_or(Error::InvalidResponse)?)
         .await?;
 
     log::info!(
@@ -108,30 +218,59 @@ async fn submit_bitcoin_fees(parachain_rpc: &InterBtcParachain, maybe_bitcoin_fe
     Ok(())
 }
 
-async fn submit_exchange_rate(
-    parachain_rpc: &InterBtcParachain,
-    currency_pair_and_price: &CurrencyPairAndPrice<Currency>,
-    currency_store: &CurrencyStore<String>,
-) -> Result<(), Error> {
-    log::info!(
-        "Attempting to set exchange rate: {} ({})",
-        currency_pair_and_price,
-        chrono::offset::Local::now()
-    );
+fn schedule_key(price_config: &PriceConfig<Currency>) -> ScheduleKey {
+    ScheduleKey::Price(price_config.pair.base.symbol(), price_config.pair.quote.symbol())
+}
 
-    let currency_id =
-        CurrencyId::try_from_symbol(currency_pair_and_price.pair.quote.symbol()).map_err(Error::RuntimeError)?;
-    let key = OracleKey::ExchangeRate(currency_id);
-    let exchange_rate = currency_pair_and_price.exchange_rate(currency_store)?;
-    parachain_rpc.feed_values(vec![(key, exchange_rate)]).await?;
+fn schedule_intervals(prices: &[PriceConfig<Currency>], interval: Duration) -> Vec<(ScheduleKey, Duration)> {
+    std::iter::once((ScheduleKey::BitcoinFees, interval))
+        .chain(
+            prices
+                .iter()
+                .map(|price_config| (schedule_key(price_config), price_config.publish_interval(interval))),
+        )
+        .collect()
+}
 
-    log::info!(
-        "Successfully set exchange rate: {} ({})",
-        currency_pair_and_price,
-        chrono::offset::Local::now()
-    );
+/// The config file followed by the files which are layered on top of it.
+fn config_paths<'a>(path: &'a Path, overrides: &'a [PathBuf]) -> Vec<&'a Path> {
+    std::iter::once(path)
+        .chain(overrides.iter().map(PathBuf::as_path))
+        .collect()
+}
 
-    Ok(())
+fn load_oracle_config(path: &Path, overrides: &[PathBuf]) -> Result<OracleConfig, Error> {
+    let oracle_config = if overrides.is_empty() {
+        OracleConfig::from_path(path)?
+    } else {
+        OracleConfig::load_layered(&config_paths(path, overrides))
+            .map_err(|err| Error::InvalidLayeredConfig(Box::new(err)))?
+    };
+    log::info!("Loaded config {}", oracle_config.hash);
+    Ok(oracle_config)
+}
+
+fn reload_oracle_config(
+    path: &Path,
+    overrides: &[PathBuf],
+    adapters: &BTreeSet<feeds::FeedName>,
+) -> Result<OracleConfig, Error> {
+    let oracle_config = load_oracle_config(path, overrides)?;
+    oracle_config
+        .validate(adapters)
+        .map_err(|err| Error::InvalidConfig(Box::new(err)))?;
+    Ok(oracle_config)
+}
+
+fn new_price_feeds(opts: &Opts, currency_store: config::CurrencyStore<String>) -> feeds::PriceFeeds {
+    let mut price_feeds = feeds::PriceFeeds::new(currency_store);
+    price_feeds.maybe_add_coingecko(opts.coingecko.clone());
+    price_feeds.maybe_add_dia(opts.dia.clone());
+    price_feeds.maybe_add_gateio(opts.gateio.clone());
+    price_feeds.maybe_add_kraken(opts.kraken.clone());
+    price_feeds.maybe_add_aggregator(opts.aggregator.clone());
+    price_feeds.set_feed_timeout(opts.feed_timeout_ms);
+    price_feeds
 }
 
 #[tokio::main]
@@ -149,22 +288,103 @@ async fn _main() -> Result<(), Error> {
     );
     let opts: Opts = Opts::parse();
 
-    // read price configs from file
-    let data = std::fs::read_to_string(opts.oracle_config)?;
-    let oracle_config = serde_json::from_str::<OracleConfig>(&data)?;
-    // validate routes
-    for price_config in &oracle_config.prices {
-        price_config
-            .validate()
-            .map_err(|err| Error::InvalidConfig(Box::new(err)))?
+    if opts.print_validation_report {
+        // merge without validating so that all errors are reported
+        let oracle_config =
+            OracleConfig::read_layered(&config_paths(&opts.oracle_config, &opts.oracle_config_override))
+                .map_err(|err| Error::InvalidLayeredConfig(Box::new(err)))?;
+        let adapters = new_price_feeds(&opts, oracle_config.currencies.clone()).adapters();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&oracle_config.validation_report(&adapters))?
+        );
+        return Ok(());
+    }
+
+    let mut oracle_config = load_oracle_config(&opts.oracle_config, &opts.oracle_config_override)?;
+    if opts.print_request_plan {
+        println!("{}", serde_json::to_string_pretty(&oracle_config.request_plan())?);
+        return Ok(());
+    }
+    if opts.print_currencies {
+        let currencies = oracle_config
+            .all_currencies()
+            .iter()
+            .map(config::currency_name)
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&currencies)?);
+        return Ok(());
+    }
+    if let Some(pair) = &opts.suggest_path {
+        // written like a path of the config so that it can be pasted
+        let path = oracle_config
+            .currency_graph()
+            .shortest_path(&pair.base, &pair.quote)
+            .map(|path| {
+                path.iter()
+                    .map(|hop| [config::currency_name(&hop.base), config::currency_name(&hop.quote)])
+                    .collect::<Vec<_>>()
+            });
+        println!("{}", serde_json::to_string_pretty(&path)?);
+        return Ok(());
+    }
+
+    let mut price_feeds = new_price_feeds(&opts, oracle_config.currencies.clone());
+    price_feeds.set_price_sources(oracle_config.price_sources());
+    price_feeds.set_conversions(oracle_config.conversions());
+    price_feeds.set_timestamp_configs(oracle_config.timestamps());
+    let metrics = match opts.feed_metrics_port {
+        Some(port) => {
+            let metrics = FeedMetrics::new()?;
+            metrics.serve(port);
+            price_feeds.set_metrics(metrics.clone());
+            Some(metrics)
+        }
+        None => None,
+    };
+
+    oracle_config
+        .validate(&price_feeds.adapters())
+        .map_err(|err| Error::InvalidConfig(Box::new(err)))?;
+
+    let routes_api = opts.routes_port.map(|port| {
+        let routes_api = RoutesApi::default();
+        routes_api.set_routes(oracle_config.routes());
+        routes_api.serve(port);
+        routes_api
+    });
+
+    let control = Arc::new(PublishControl::new(opts.start_paused));
+    control.set_config_hash(&oracle_config.hash);
+    if let Some(port) = opts.control_port {
+        control::serve(control.clone(), opts.control_token.clone(), port);
     }
 
-    let currency_store = &oracle_config.currencies;
-    let mut price_feeds = feeds::PriceFeeds::new(currency_store.clone());
-    price_feeds.maybe_add_coingecko(opts.coingecko);
-    price_feeds.maybe_add_dia(opts.dia);
-    price_feeds.maybe_add_gateio(opts.gateio);
-    price_feeds.maybe_add_kraken(opts.kraken);
+    if opts.validate_markets {
+        let unknown_markets = price_feeds.validate_markets(&oracle_config.enabled_prices()).await?;
+        if !unknown_markets.is_empty() {
+            return Err(Error::UnknownMarkets(unknown_markets));
+        }
+    }
+
+    let mut shadow_config = opts
+        .shadow_oracle_config
+        .as_deref()
+        .map(|path| reload_oracle_config(path, &[], &price_feeds.adapters()))
+        .transpose()?;
+
+    let mut config_updates = {
+        let (path, overrides, adapters) = (
+            opts.oracle_config.clone(),
+            opts.oracle_config_override.clone(),
+            price_feeds.adapters(),
+        );
+        let paths = std::iter::once(path.clone()).chain(overrides.clone()).collect();
+        ConfigWatcher::new(paths, oracle_config.clone(), move || {
+            reload_oracle_config(&path, &overrides, &adapters)
+        })
+        .spawn(opts.config_poll_interval_ms)
+    };
 
     let mut bitcoin_feeds = feeds::BitcoinFeeds::new();
     bitcoin_feeds.maybe_add_blockstream(opts.blockstream);
@@ -173,19 +393,52 @@ async fn _main() -> Result<(), Error> {
     let (key_pair, _) = opts.account_info.get_key_pair()?;
     let signer = InterBtcSigner::new(key_pair);
 
+    let mut schedule = Schedule::new();
+    let mut history = PriceHistory::new(opts.confidence_window, opts.confidence_k, opts.confidence_max_gap_ms);
+    let mut identical_feeds = opts.identical_feeds_window.map(IdenticalFeeds::new);
+    let mut freshness = PriceFreshness::new();
     loop {
+        // prices without their own interval use the global interval
+        let enabled_prices = oracle_config.enabled_prices();
+        let intervals = schedule_intervals(&enabled_prices, opts.interval_ms);
+        let due = schedule.tick(Instant::now(), &intervals);
+        let fees_due = due.contains(&ScheduleKey::BitcoinFees);
+        // fetch all prices which are due together to share the feed requests
+        let due_prices = enabled_prices
+            .into_iter()
+            .filter(|price_config| due.contains(&schedule_key(price_config)))
+            .collect::<Vec<_>>();
+
         // TODO: retry these calls on failure
-        let fee_estimate = bitcoin_feeds.maybe_get_median(CONFIRMATION_TARGET).await?;
-        let prices = join_all(
-            oracle_config
-                .prices
-                .clone()
-                .into_iter()
-                .map(|price_config| price_feeds.get_value_or_median(price_config)),
-        )
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>, _>>()?;
+        let fee_estimate = if fees_due {
+            bitcoin_feeds.maybe_get_median(CONFIRMATION_TARGET).await?
+        } else {
+            None
+        };
+        // the shadow config is only computed for prices which are due in the live config
+        let shadow_due_prices = shadow_config
+            .iter()
+            .flat_map(|shadow_config| shadow_config.enabled_prices())
+            .filter(|shadow_price| {
+                due_prices
+                    .iter()
+                    .any(|price_config| price_config.pair == shadow_price.pair)
+            })
+            .collect::<Vec<_>>();
+        let (prices, shadow_prices) = join!(
+            price_feeds.get_values_or_medians(due_prices.clone(), identical_feeds.as_mut()),
+            price_feeds.get_values_or_medians(shadow_due_prices, None)
+        );
+        shadow::compare_shadow(&prices, &shadow_prices, metrics.as_ref());
+        let prices = reference::check_references(&due_prices, prices, metrics.as_ref()).await;
+        for currency_pair_and_price in &prices {
+            let max_age = due_prices
+                .iter()
+                .find(|price_config| price_config.pair == currency_pair_and_price.pair)
+                .and_then(PriceConfig::max_age);
+            freshness.record(Instant::now(), currency_pair_and_price, max_age);
+        }
+        history.record_all(Instant::now(), &prices, metrics.as_ref());
 
         // get prices above first to prevent websocket timeout
         let shutdown_tx = ShutdownSender::new();
@@ -197,29 +450,43 @@ async fn _main() -> Result<(), Error> {
         )
         .await?;
 
+        let mut sinks = PriceSinks::new();
+        sinks.set_control(control.clone());
+        sinks.add(ParachainSink::new(
+            &parachain_rpc,
+            &oracle_config.currencies,
+            &oracle_config.hash,
+            oracle_config.rounding_mode,
+        ));
         let (left, right) = join!(
+            async {
+                if !fees_due || control.is_paused() {
+                    return Ok(());
+                }
+                retry_notify(
+                    get_exponential_backoff(),
+                    || async {
+                        submit_bitcoin_fees(&parachain_rpc, fee_estimate, oracle_config.rounding_mode)
+                            .await
+                            .map_err(Into::into)
+                    },
+                    |err, _| log::error!("Error: {}", err),
+                )
+                .await
+            },
             retry_notify(
                 get_exponential_backoff(),
                 || async {
-                    submit_bitcoin_fees(&parachain_rpc, fee_estimate)
+                    // prices may have become stale while retrying
+                    sinks
+                        .publish(&freshness.fresh_prices(Instant::now(), &prices))
                         .await
+                        .into_iter() // turn vec<result> into result
+                        .find(|x| x.is_err())
+                        .transpose()
                         .map_err(Into::into)
                 },
                 |err, _| log::error!("Error: {}", err),
-            ),
-            retry_notify(
-                get_exponential_backoff(),
-                || async {
-                    join_all(prices.iter().map(|currency_pair_and_price| {
-                        submit_exchange_rate(&parachain_rpc, currency_pair_and_price, currency_store)
-                    }))
-                    .await
-                    .into_iter() // turn vec<result> into result
-                    .find(|x| x.is_err())
-                    .transpose()
-                    .map_err(Into::into)
-                },
-                |err, _| log::error!("Error: {}", err),
             )
         );
 
@@ -229,6 +496,28 @@ async fn _main() -> Result<(), Error> {
             return Err(Error::Shutdown);
         }
 
-        sleep(opts.interval_ms).await;
+        if let Some(next_due) = schedule.next_due(Instant::now(), &intervals) {
+            sleep(next_due).await;
+        }
+
+        // pick up changes, e.g. feeds which have been disabled
+        if config_updates.has_changed().unwrap_or_default() {
+            let new_oracle_config = OracleConfig::clone(&config_updates.borrow_and_update());
+            price_feeds.set_currency_store(new_oracle_config.currencies.clone());
+            price_feeds.set_price_sources(new_oracle_config.price_sources());
+            price_feeds.set_conversions(new_oracle_config.conversions());
+            price_feeds.set_timestamp_configs(new_oracle_config.timestamps());
+            if let Some(routes_api) = &routes_api {
+                routes_api.set_routes(new_oracle_config.routes());
+            }
+            control.set_config_hash(&new_oracle_config.hash);
+            oracle_config = new_oracle_config;
+        }
+        if let Some(path) = &opts.shadow_oracle_config {
+            match reload_oracle_config(path, &[], &price_feeds.adapters()) {
+                Ok(new_shadow_config) => shadow_config = Some(new_shadow_config),
+                Err(err) => log::error!("Failed to reload shadow config, keeping the previous one: {}", err),
+            }
+        }
     }
 }
@@ -0,0 +1,199 @@
+use crate::{
+    currency::{Currency, CurrencyPair},
+    feeds::FeedName,
+    history::ConfidenceInterval,
+    Error,
+};
+use runtime::prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+    DEFAULT_BUCKETS,
+};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+use warp::{Filter, Rejection};
+
+/// Buckets of the reference divergence histogram, in percent.
+const REFERENCE_DIVERGENCE_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 25.0];
+
+const FEED_LABEL: &str = "feed";
+const OTHER_FEED_LABEL: &str = "other_feed";
+const PAIR_LABEL: &str = "pair";
+const BOUND_LABEL: &str = "bound";
+
+fn pair_label(pair: &CurrencyPair<Currency>) -> String {
+    format!("{}/{}", pair.base.symbol(), pair.quote.symbol())
+}
+
+/// Request latencies and timeouts of each price feed, the failures and
+/// confidence interval of each price and its divergence from its reference
+/// and shadow price.
+/// Feeds which return identical prices for a pair are also flagged.
+#[derive(Clone)]
+pub struct FeedMetrics {
+    registry: Registry,
+    pub(crate) latency: HistogramVec,
+    pub(crate) timeouts: IntCounterVec,
+    pub(crate) price_failures: IntCounterVec,
+    pub(crate) confidence_interval: GaugeVec,
+    pub(crate) reference_divergence: GaugeVec,
+    pub(crate) reference_divergence_percent: HistogramVec,
+    pub(crate) shadow_divergence: GaugeVec,
+    pub(crate) identical_feeds: IntGaugeVec,
+}
+
+impl FeedMetrics {
+    pub fn new() -> Result<Self, Error> {
+        let registry = Registry::new();
+        let latency = HistogramVec::new(
+            HistogramOpts::new("feed_request_latency_seconds", "Duration of each price feed request")
+                .buckets(DEFAULT_BUCKETS.to_vec()),
+            &[FEED_LABEL],
+        )?;
+        let timeouts = IntCounterVec::new(
+            Opts::new("feed_request_timeouts", "Number of timed out price feed requests"),
+            &[FEED_LABEL],
+        )?;
+        let price_failures = IntCounterVec::new(
+            Opts::new(
+                "price_failures",
+                "Number of times a price could not be computed, including panics",
+            ),
+            &[PAIR_LABEL],
+        )?;
+        let confidence_interval = GaugeVec::new(
+            Opts::new(
+                "price_confidence_interval",
+                "Lower and upper bound of the confidence interval of the published price",
+            ),
+            &[PAIR_LABEL, BOUND_LABEL],
+        )?;
+        let reference_divergence = GaugeVec::new(
+            Opts::new(
+                "price_reference_divergence",
+                "Relative divergence of the computed price from its reference",
+            ),
+            &[PAIR_LABEL],
+        )?;
+        let reference_divergence_percent = HistogramVec::new(
+            HistogramOpts::new(
+                "price_reference_divergence_percent",
+                "Distribution of the divergence of the computed price from its reference, in percent",
+            )
+            .buckets(REFERENCE_DIVERGENCE_BUCKETS.to_vec()),
+            &[PAIR_LABEL],
+        )?;
+        let shadow_divergence = GaugeVec::new(
+            Opts::new(
+                "price_shadow_divergence",
+                "Relative divergence of the price computed from the shadow config from the live price",
+            ),
+            &[PAIR_LABEL],
+        )?;
+        let identical_feeds = IntGaugeVec::new(
+            Opts::new(
+                "identical_feed_prices",
+                "Whether two feeds returned identical prices for a pair throughout the window",
+            ),
+            &[PAIR_LABEL, FEED_LABEL, OTHER_FEED_LABEL],
+        )?;
+        registry.register(Box::new(latency.clone()))?;
+        registry.register(Box::new(timeouts.clone()))?;
+        registry.register(Box::new(price_failures.clone()))?;
+        registry.register(Box::new(confidence_interval.clone()))?;
+        registry.register(Box::new(reference_divergence.clone()))?;
+        registry.register(Box::new(reference_divergence_percent.clone()))?;
+        registry.register(Box::new(shadow_divergence.clone()))?;
+        registry.register(Box::new(identical_feeds.clone()))?;
+        Ok(Self {
+            registry,
+            latency,
+            timeouts,
+            price_failures,
+            confidence_interval,
+            reference_divergence,
+            reference_divergence_percent,
+            shadow_divergence,
+            identical_feeds,
+        })
+    }
+
+    pub fn observe_latency(&self, feed: &FeedName, latency: Duration) {
+        self.latency
+            .with_label_values(&[&feed.to_string()])
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn inc_timeouts(&self, feed: &FeedName) {
+        self.timeouts.with_label_values(&[&feed.to_string()]).inc();
+    }
+
+    pub fn inc_price_failures(&self, pair: &CurrencyPair<Currency>) {
+        self.price_failures.with_label_values(&[&pair_label(pair)]).inc();
+    }
+
+    pub fn set_confidence_interval(&self, pair: &CurrencyPair<Currency>, confidence_interval: &ConfidenceInterval) {
+        let pair = pair_label(pair);
+        for (bound, value) in [
+            ("lower", confidence_interval.lower),
+            ("upper", confidence_interval.upper),
+        ] {
+            self.confidence_interval.with_label_values(&[&pair, bound]).set(value);
+        }
+    }
+
+    /// Set the latest relative divergence and add it to the distribution.
+    pub fn record_reference_divergence(&self, pair: &CurrencyPair<Currency>, divergence: f64) {
+        let pair = pair_label(pair);
+        self.reference_divergence.with_label_values(&[&pair]).set(divergence);
+        self.reference_divergence_percent
+            .with_label_values(&[&pair])
+            .observe(divergence * 100.0);
+    }
+
+    pub fn set_shadow_divergence(&self, pair: &CurrencyPair<Currency>, divergence: f64) {
+        self.shadow_divergence
+            .with_label_values(&[&pair_label(pair)])
+            .set(divergence);
+    }
+
+    pub fn set_identical_feeds(
+        &self,
+        pair: &CurrencyPair<Currency>,
+        feed: &FeedName,
+        other_feed: &FeedName,
+        is_identical: bool,
+    ) {
+        self.identical_feeds
+            .with_label_values(&[&pair_label(pair), &feed.to_string(), &other_feed.to_string()])
+            .set(is_identical as i64);
+    }
+
+    fn serialize(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            log::error!("Failed to encode metrics: {}", err);
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Serve the metrics at `/metrics` on the given port until the process exits.
+    pub fn serve(&self, port: u16) {
+        let metrics = self.clone();
+        let metrics_route = warp::path("metrics").and_then(move || {
+            let metrics = metrics.clone();
+            async move { Ok::<_, Rejection>(metrics.serialize()) }
+        });
+        log::info!(
+            "Starting Prometheus exporter at http://{}:{}",
+            Ipv4Addr::LOCALHOST,
+            port
+        );
+        tokio::task::spawn(async move {
+            warp::serve(metrics_route)
+                .run(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port))
+                .await;
+        });
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    config::{PriceConfig, ReferenceCheckConfig},
+    currency::*,
+    feeds::get_http,
+    metrics::FeedMetrics,
+    Error,
+};
+use futures::future::join_all;
+use serde_json::Value;
+
+/// Read the reference price at the JSON pointer `extractor`, some APIs
+/// return numbers as strings so these are also accepted.
+fn extract_reference(value: &Value, extractor: &str) -> Option<f64> {
+    match value.pointer(extractor)? {
+        Value::Number(number) => number.as_f64(),
+        Value::String(number) => number.parse().ok(),
+        _ => None,
+    }
+}
+
+async fn get_reference(reference_check: &ReferenceCheckConfig) -> Result<f64, Error> {
+    let value = get_http(reference_check.url.clone()).await?;
+    match extract_reference(&value, &reference_check.extractor) {
+        Some(reference) if reference > 0.0 => Ok(reference),
+        _ => Err(Error::InvalidResponse),
+    }
+}
+
+/// Relative divergence of the `price` from the `reference`.
+pub(crate) fn divergence(price: f64, reference: f64) -> f64 {
+    (price - reference).abs() / reference
+}
+
+/// Compare the `price` against the `reference` and return whether it
+/// may be published, which is only refused under a strict check.
+fn check_reference(
+    reference_check: &ReferenceCheckConfig,
+    price: &CurrencyPairAndPrice<Currency>,
+    reference: f64,
+    metrics: Option<&FeedMetrics>,
+) -> bool {
+    let divergence = divergence(price.price, reference);
+    if let Some(metrics) = metrics {
+        metrics.record_reference_divergence(&price.pair, divergence);
+    }
+    if divergence <= reference_check.tolerance {
+        return true;
+    }
+    if reference_check.strict {
+        log::error!(
+            "Refusing to publish {}: diverges from reference {} by {:.2}%",
+            price,
+            reference,
+            divergence * 100.0
+        );
+        false
+    } else {
+        log::warn!(
+            "Price {} diverges from reference {} by {:.2}%",
+            price,
+            reference,
+            divergence * 100.0
+        );
+        true
+    }
+}
+
+/// Cross-check each price which has a reference configured, prices
+/// which fail a strict check are removed. The reference is only used
+/// for monitoring so prices are kept if it cannot be fetched.
+pub async fn check_references(
+    price_configs: &[PriceConfig<Currency>],
+    prices: Vec<CurrencyPairAndPrice<Currency>>,
+    metrics: Option<&FeedMetrics>,
+) -> Vec<CurrencyPairAndPrice<Currency>> {
+    join_all(prices.into_iter().map(|price| async move {
+        let reference_check = match price_configs
+            .iter()
+            .find(|price_config| price_config.pair == price.pair)
+            .and_then(|price_config| price_config.reference_check.as_ref())
+        {
+            Some(reference_check) => reference_check,
+            None => return Some(price),
+        };
+        match get_reference(reference_check).await {
+            Ok(reference) => Some(price).filter(|price| check_reference(reference_check, price, reference, metrics)),
+            Err(err) => {
+                log::warn!("Failed to get reference for {}: {}", price.pair, err);
+                Some(price)
+            }
+        }
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runtime::prometheus::core::Metric;
+    use serde_json::json;
+
+    fn reference_check(strict: bool) -> ReferenceCheckConfig {
+        serde_json::from_value(json!({
+            "url": "https://example.com/price",
+            "extractor": "/bitcoin/usd",
+            "tolerance": 0.05,
+            "strict": strict,
+        }))
+        .expect("Config is valid")
+    }
+
+    #[test]
+    fn should_extract_reference() {
+        let value = json!({ "bitcoin": { "usd": 19000.5, "eur": "18000" } });
+        assert_eq!(extract_reference(&value, "/bitcoin/usd"), Some(19000.5));
+        assert_eq!(extract_reference(&value, "/bitcoin/eur"), Some(18000.0));
+        assert_eq!(extract_reference(&value, "/bitcoin/gbp"), None);
+        assert_eq!(extract_reference(&value, "/bitcoin"), None);
+    }
+
+    #[test]
+    fn should_publish_within_tolerance() {
+        for strict in [false, true] {
+            let reference_check = reference_check(strict);
+            assert!(check_reference(
+                &reference_check,
+                &price("BTC", "USD", 20000.0),
+                20000.0,
+                None
+            ));
+            assert!(check_reference(
+                &reference_check,
+                &price("BTC", "USD", 20900.0),
+                20000.0,
+                None
+            ));
+            assert!(check_reference(
+                &reference_check,
+                &price("BTC", "USD", 19100.0),
+                20000.0,
+                None
+            ));
+        }
+    }
+
+    #[test]
+    fn should_alert_or_refuse_beyond_tolerance() {
+        let metrics = FeedMetrics::new().unwrap();
+
+        // only alerts by default
+        assert!(check_reference(
+            &reference_check(false),
+            &price("BTC", "USD", 22000.0),
+            20000.0,
+            Some(&metrics)
+        ));
+        assert!(!check_reference(
+            &reference_check(true),
+            &price("BTC", "USD", 18000.0),
+            20000.0,
+            Some(&metrics)
+        ));
+
+        let divergence = metrics.reference_divergence.with_label_values(&["BTC/USD"]).get();
+        assert!((divergence - 0.1).abs() < f64::EPSILON, "{}", divergence);
+    }
+
+    #[test]
+    fn should_record_divergence_distribution() {
+        let metrics = FeedMetrics::new().unwrap();
+        let reference_check = reference_check(false);
+        // 0.05%, 0.2%, 0.75%, 3% and 30%
+        for price_value in [20010.0, 19960.0, 20150.0, 20600.0, 26000.0] {
+            check_reference(
+                &reference_check,
+                &price("BTC", "USD", price_value),
+                20000.0,
+                Some(&metrics),
+            );
+        }
+
+        let histogram = metrics
+            .reference_divergence_percent
+            .with_label_values(&["BTC/USD"])
+            .metric();
+        let buckets = histogram
+            .get_histogram()
+            .get_bucket()
+            .iter()
+            .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            buckets,
+            vec![
+                (0.1, 1),
+                (0.25, 2),
+                (0.5, 2),
+                (1.0, 3),
+                (2.0, 3),
+                (5.0, 4),
+                (10.0, 4),
+                (25.0, 4)
+            ]
+        );
+        assert_eq!(histogram.get_histogram().get_sample_count(), 5);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// Anything the oracle publishes on its own interval.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScheduleKey {
+    BitcoinFees,
+    /// Base and quote symbol of a price
+    Price(String, String),
+}
+
+/// Tracks when each item was last published so that every item can
+/// run on its own interval. Items which are due at the same time are
+/// returned together so that they can share a single update.
+#[derive(Debug)]
+pub struct Schedule<K> {
+    last_published: BTreeMap<K, Instant>,
+}
+
+impl<K: Ord + Clone> Default for Schedule<K> {
+    fn default() -> Self {
+        Self {
+            last_published: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Ord + Clone> Schedule<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn due_in(&self, key: &K, interval: Duration, now: Instant) -> Duration {
+        self.last_published.get(key).map_or(Duration::ZERO, |last_published| {
+            interval.saturating_sub(now.saturating_duration_since(*last_published))
+        })
+    }
+
+    /// Returns the keys which are due at `now` and marks them as published.
+    pub fn tick(&mut self, now: Instant, items: &[(K, Duration)]) -> Vec<K> {
+        let due = items
+            .iter()
+            .filter(|(key, interval)| self.due_in(key, *interval, now).is_zero())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in &due {
+            self.last_published.insert(key.clone(), now);
+        }
+        due
+    }
+
+    /// Time to wait until the next item is due.
+    pub fn next_due(&self, now: Instant, items: &[(K, Duration)]) -> Option<Duration> {
+        items
+            .iter()
+            .map(|(key, interval)| self.due_in(key, *interval, now))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_fire_on_own_cadence() {
+        let items = [("fast", Duration::from_secs(2)), ("slow", Duration::from_secs(5))];
+        let start = Instant::now();
+        let mut schedule = Schedule::new();
+
+        let mut now = start;
+        let mut fired = Vec::new();
+        while now <= start + Duration::from_secs(10) {
+            for key in schedule.tick(now, &items) {
+                fired.push((now.duration_since(start).as_secs(), key));
+            }
+            now += schedule.next_due(now, &items).unwrap();
+        }
+
+        assert_eq!(
+            fired,
+            vec![
+                // both are due initially and fire together
+                (0, "fast"),
+                (0, "slow"),
+                (2, "fast"),
+                (4, "fast"),
+                (5, "slow"),
+                (6, "fast"),
+                (8, "fast"),
+                (10, "fast"),
+                (10, "slow"),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_pick_up_new_items() {
+        let start = Instant::now();
+        let mut schedule = Schedule::new();
+        assert_eq!(schedule.tick(start, &[("a", Duration::from_secs(60))]), vec!["a"]);
+
+        let items = [("a", Duration::from_secs(60)), ("b", Duration::from_secs(60))];
+        let now = start + Duration::from_secs(1);
+        assert_eq!(schedule.next_due(now, &items), Some(Duration::ZERO));
+        assert_eq!(schedule.tick(now, &items), vec!["b"]);
+        assert_eq!(schedule.next_due(now, &items), Some(Duration::from_secs(59)));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{currency::*, metrics::FeedMetrics, reference::divergence};
+
+/// Compare the prices computed from the shadow config with the live
+/// prices of the same pairs and return the divergence of each, shadow
+/// prices without a live price are skipped. Nothing is published.
+pub fn compare_shadow(
+    live_prices: &[CurrencyPairAndPrice<Currency>],
+    shadow_prices: &[CurrencyPairAndPrice<Currency>],
+    metrics: Option<&FeedMetrics>,
+) -> Vec<(CurrencyPair<Currency>, f64)> {
+    shadow_prices
+        .iter()
+        .filter_map(|shadow_price| {
+            let live_price = live_prices
+                .iter()
+                .find(|live_price| live_price.pair == shadow_price.pair)?;
+            let divergence = divergence(shadow_price.price, live_price.price);
+            log::info!(
+                "Shadow price {} diverges from live {} by {:.2}%",
+                shadow_price,
+                live_price.price,
+                divergence * 100.0
+            );
+            if let Some(metrics) = metrics {
+                metrics.set_shadow_divergence(&shadow_price.pair, divergence);
+            }
+            Some((shadow_price.pair.clone(), divergence))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{CurrencyStore, PriceConfig},
+        feeds::{FeedClient, FeedName, PriceFeeds},
+        sinks::{PriceSinks, RecordingSink},
+        Error,
+    };
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    struct FixedPrice(f64);
+
+    #[async_trait]
+    impl FeedClient for FixedPrice {
+        async fn fetch(&self, _pair: &CurrencyPair<Currency>) -> Result<f64, Error> {
+            Ok(self.0)
+        }
+    }
+
+    #[test]
+    fn should_skip_shadow_prices_without_live_price() {
+        let live_prices = vec![price("BTC", "USD", 20000.0), price("DOT", "USD", 5.0)];
+        let shadow_prices = vec![price("BTC", "USD", 21000.0), price("KSM", "USD", 30.0)];
+
+        let divergences = compare_shadow(&live_prices, &shadow_prices, None);
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].0, price("BTC", "USD", 0.0).pair);
+        assert!((divergences[0].1 - 0.05).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn should_report_shadow_divergence() {
+        let metrics = FeedMetrics::new().unwrap();
+        let mut price_feeds = PriceFeeds::new(CurrencyStore::new());
+        price_feeds.register(FeedName::Kraken, FixedPrice(1.0));
+        price_feeds.register(FeedName::GateIo, FixedPrice(2.0));
+        price_feeds.register(FeedName::CoinGecko, FixedPrice(6.0));
+        let price_config = |aggregation: &str| {
+            serde_json::from_value::<PriceConfig<Currency>>(json!({
+                "pair": ["BTC", "USD"],
+                "feeds": {
+                    "kraken": [["BTC", "USD"]],
+                    "gateio": [["BTC", "USD"]],
+                    "coingecko": [["BTC", "USD"]]
+                },
+                "aggregation": aggregation
+            }))
+            .unwrap()
+        };
+
+        let live_prices = price_feeds
+            .get_values_or_medians(vec![price_config("median")], None)
+            .await;
+        let shadow_prices = price_feeds
+            .get_values_or_medians(vec![price_config("mean")], None)
+            .await;
+        compare_shadow(&live_prices, &shadow_prices, Some(&metrics));
+
+        // median(1, 2, 6) = 2 and mean(1, 2, 6) = 3
+        let divergence = metrics.shadow_divergence.with_label_values(&["BTC/USD"]).get();
+        assert!((divergence - 0.5).abs() < f64::EPSILON, "{}", divergence);
+
+        let published = Mutex::new(Vec::new());
+        let mut sinks = PriceSinks::new();
+        sinks.add(RecordingSink(&published));
+        assert!(matches!(sinks.publish(&live_prices).await[..], [Ok(())]));
+        assert_eq!(*published.lock().unwrap(), vec!["BTC/USD => 2".to_string()]);
+    }
+}
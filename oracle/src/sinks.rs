@@ -0,0 +1,192 @@
+use crate::{config::CurrencyStore, control::PublishControl, currency::*, Error};
+use async_trait::async_trait;
+use futures::future::join_all;
+use runtime::{CurrencyId, InterBtcParachain, OracleKey, OraclePallet, TryFromSymbol};
+use std::sync::Arc;
+
+/// A destination for computed prices.
+#[async_trait]
+pub trait PriceSink: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn publish(&self, result: &CurrencyPairAndPrice<Currency>) -> Result<(), Error>;
+}
+
+/// Sets the exchange rates on the parachain.
+pub struct ParachainSink<'a> {
+    parachain_rpc: &'a InterBtcParachain,
+    currency_store: &'a CurrencyStore<String>,
+    /// Hash of the config which produced the prices.
+    config_hash: &'a str,
+    rounding_mode: RoundingMode,
+}
+
+impl<'a> ParachainSink<'a> {
+    pub fn new(
+        parachain_rpc: &'a InterBtcParachain,
+        currency_store: &'a CurrencyStore<String>,
+        config_hash: &'a str,
+        rounding_mode: RoundingMode,
+    ) -> Self {
+        Self {
+            parachain_rpc,
+            currency_store,
+            config_hash,
+            rounding_mode,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSink for ParachainSink<'_> {
+    fn name(&self) -> &'static str {
+        "parachain"
+    }
+
+    async fn publish(&self, currency_pair_and_price: &CurrencyPairAndPrice<Currency>) -> Result<(), Error> {
+        log::info!(
+            "Attempting to set exchange rate: {} ({})",
+            currency_pair_and_price,
+            chrono::offset::Local::now()
+        );
+
+        let currency_id =
+            CurrencyId::try_from_symbol(currency_pair_and_price.pair.quote.symbol()).map_err(Error::RuntimeError)?;
+        let key = OracleKey::ExchangeRate(currency_id);
+        let exchange_rate = currency_pair_and_price.exchange_rate(self.currency_store, self.rounding_mode)?;
+        self.parachain_rpc.feed_values(vec![(key, exchange_rate)]).await?;
+
+        log::info!(
+            "Successfully set exchange rate: {} (config {}, {})",
+            currency_pair_and_price,
+            self.config_hash,
+            chrono::offset::Local::now()
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct PriceSinks<'a> {
+    sinks: Vec<Box<dyn PriceSink + 'a>>,
+    control: Option<Arc<PublishControl>>,
+}
+
+impl<'a> PriceSinks<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, sink: impl PriceSink + 'a) {
+        self.sinks.push(Box::new(sink));
+    }
+
+    /// Withhold the prices from all sinks while publishing is paused.
+    pub fn set_control(&mut self, control: Arc<PublishControl>) {
+        self.control = Some(control);
+    }
+
+    async fn publish_to(sink: &dyn PriceSink, prices: &[CurrencyPairAndPrice<Currency>]) -> Result<(), Error> {
+        join_all(
+            prices
+                .iter()
+                .map(|currency_pair_and_price| sink.publish(currency_pair_and_price)),
+        )
+        .await
+        .into_iter()
+        .find(|result| result.is_err())
+        .unwrap_or(Ok(()))
+    }
+
+    /// Publish all prices to every sink, a failing sink does not prevent
+    /// the other sinks from receiving the prices. Returns the first error
+    /// of each sink, which is empty if publishing is paused.
+    pub async fn publish(&self, prices: &[CurrencyPairAndPrice<Currency>]) -> Vec<Result<(), Error>> {
+        if let Some(control) = self.control.as_ref().filter(|control| control.is_paused()) {
+            control.withhold(prices);
+            return Vec::new();
+        }
+        join_all(self.sinks.iter().map(|sink| async move {
+            let result = Self::publish_to(sink.as_ref(), prices).await;
+            if let Err(err) = &result {
+                log::error!("Failed to publish to {}: {}", sink.name(), err);
+            }
+            result
+        }))
+        .await
+    }
+}
+
+/// Records every published price.
+#[cfg(test)]
+pub struct RecordingSink<'a>(pub &'a std::sync::Mutex<Vec<String>>);
+
+#[cfg(test)]
+#[async_trait]
+impl PriceSink for RecordingSink<'_> {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    async fn publish(&self, result: &CurrencyPairAndPrice<Currency>) -> Result<(), Error> {
+        self.0.lock().unwrap().push(result.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct FailingSink;
+
+    #[async_trait]
+    impl PriceSink for FailingSink {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn publish(&self, _result: &CurrencyPairAndPrice<Currency>) -> Result<(), Error> {
+            Err(Error::InvalidResponse)
+        }
+    }
+
+    #[tokio::test]
+    async fn should_isolate_failing_sinks() {
+        let published = Mutex::new(Vec::new());
+        let mut sinks = PriceSinks::new();
+        sinks.add(FailingSink);
+        sinks.add(RecordingSink(&published));
+
+        let results = sinks
+            .publish(&[price("BTC", "KSM", 2.0), price("BTC", "DOT", 3.0)])
+            .await;
+
+        assert!(matches!(results[..], [Err(Error::InvalidResponse), Ok(())]));
+        assert_eq!(
+            *published.lock().unwrap(),
+            vec!["BTC/KSM => 2".to_string(), "BTC/DOT => 3".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_withhold_prices_while_paused() {
+        let published = Mutex::new(Vec::new());
+        let control = Arc::new(PublishControl::new(true));
+        let mut sinks = PriceSinks::new();
+        sinks.add(RecordingSink(&published));
+        sinks.set_control(control.clone());
+
+        assert!(sinks.publish(&[price("BTC", "KSM", 2.0)]).await.is_empty());
+        assert!(published.lock().unwrap().is_empty());
+        // the computed prices can still be inspected
+        assert_eq!(control.withheld(), vec!["BTC/KSM => 2".to_string()]);
+
+        control.resume_all();
+        assert!(matches!(sinks.publish(&[price("BTC", "KSM", 3.0)]).await[..], [Ok(())]));
+        assert_eq!(*published.lock().unwrap(), vec!["BTC/KSM => 3".to_string()]);
+        assert!(control.withheld().is_empty());
+    }
+}
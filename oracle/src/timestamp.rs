@@ -0,0 +1,79 @@
+use crate::{
+    config::{TimestampConfig, TimestampSource, TimestampUnit},
+    Error,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The time of the `raw` Unix timestamp reported by a feed in its configured
+/// unit, or the time at which the response was received if the feed's clock
+/// is not used. Returns `None` if the timestamp is out of range.
+pub fn normalize(timestamp_config: &TimestampConfig, raw: u64, received_at: SystemTime) -> Option<SystemTime> {
+    match timestamp_config.source {
+        TimestampSource::Received => Some(received_at),
+        TimestampSource::Server => UNIX_EPOCH.checked_add(match timestamp_config.unit {
+            TimestampUnit::Seconds => Duration::from_secs(raw),
+            TimestampUnit::Milliseconds => Duration::from_millis(raw),
+        }),
+    }
+}
+
+/// Fail if the `timestamp` is further ahead of the local clock than the
+/// maximum skew, which suggests a wrong unit or a misconfigured clock.
+/// Timestamps in the past are left to the staleness checks of each feed.
+pub fn check_skew(timestamp_config: &TimestampConfig, timestamp: SystemTime, now: SystemTime) -> Result<(), Error> {
+    match timestamp.duration_since(now) {
+        Ok(ahead) if ahead > Duration::from_secs(timestamp_config.max_skew_secs) => Err(Error::ClockSkew(ahead)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn timestamp_config(value: serde_json::Value) -> TimestampConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn should_normalize_seconds_and_milliseconds() {
+        let received_at = UNIX_EPOCH + Duration::from_secs(1665000100);
+        let expected = Some(UNIX_EPOCH + Duration::from_secs(1665000000));
+
+        assert_eq!(
+            normalize(&TimestampConfig::default(), 1665000000, received_at),
+            expected
+        );
+        let milliseconds = timestamp_config(json!({ "unit": "milliseconds" }));
+        assert_eq!(normalize(&milliseconds, 1665000000000, received_at), expected);
+        assert_eq!(
+            normalize(&milliseconds, 1665000000123, received_at),
+            Some(UNIX_EPOCH + Duration::from_millis(1665000000123))
+        );
+
+        // the reported timestamp is ignored
+        let received = timestamp_config(json!({ "source": "received", "unit": "milliseconds" }));
+        assert_eq!(normalize(&received, 0, received_at), Some(received_at));
+    }
+
+    #[test]
+    fn should_detect_clock_skew() {
+        let now = UNIX_EPOCH + Duration::from_secs(1665000000);
+        let timestamp_config = TimestampConfig::default();
+
+        assert!(check_skew(&timestamp_config, now + Duration::from_secs(60), now).is_ok());
+        assert!(check_skew(&timestamp_config, now - Duration::from_secs(3600), now).is_ok());
+        assert!(matches!(
+            check_skew(&timestamp_config, now + Duration::from_secs(61), now),
+            Err(Error::ClockSkew(ahead)) if ahead == Duration::from_secs(61)
+        ));
+
+        // milliseconds read as seconds are far in the future
+        let timestamp = normalize(&timestamp_config, 1665000000000, now).unwrap();
+        assert!(matches!(
+            check_skew(&timestamp_config, timestamp, now),
+            Err(Error::ClockSkew(_))
+        ));
+    }
+}
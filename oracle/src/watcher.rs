@@ -0,0 +1,138 @@
+use crate::{config::OracleConfig, Error};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tokio::sync::watch;
+
+/// Reloads the config when one of its files changes, which is detected by
+/// polling their modification times and sizes. The new config is only sent
+/// to subscribers if it loads and validates, an invalid edit is logged and
+/// the previous config is kept.
+pub struct ConfigWatcher<F> {
+    paths: Vec<PathBuf>,
+    versions: Vec<Option<(SystemTime, u64)>>,
+    load: F,
+    sender: watch::Sender<Arc<OracleConfig>>,
+}
+
+fn versions(paths: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(path).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+impl<F> ConfigWatcher<F>
+where
+    F: FnMut() -> Result<OracleConfig, Error>,
+{
+    /// Watch the files of the `oracle_config`, which is reloaded and
+    /// validated by `load`.
+    pub fn new(paths: Vec<PathBuf>, oracle_config: OracleConfig, load: F) -> Self {
+        let (sender, _) = watch::channel(Arc::new(oracle_config));
+        Self {
+            versions: versions(&paths),
+            paths,
+            load,
+            sender,
+        }
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Arc<OracleConfig>> {
+        self.sender.subscribe()
+    }
+
+    /// Reload the config if any of its files changed since the last check,
+    /// returns whether a new config was sent.
+    pub fn check(&mut self) -> bool {
+        let versions = versions(&self.paths);
+        if versions == self.versions {
+            return false;
+        }
+        self.versions = versions;
+        match (self.load)() {
+            Ok(oracle_config) => {
+                self.sender.send_replace(Arc::new(oracle_config));
+                true
+            }
+            Err(err) => {
+                log::error!("Failed to reload config, keeping the previous one: {}", err);
+                false
+            }
+        }
+    }
+
+    /// Check for changes every `interval` until the process exits.
+    pub fn spawn(mut self, interval: Duration) -> watch::Receiver<Arc<OracleConfig>>
+    where
+        F: Send + 'static,
+    {
+        let receiver = self.subscribe();
+        tokio::task::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                self.check();
+            }
+        });
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::FeedName;
+    use serde_json::json;
+
+    #[test]
+    fn should_keep_previous_config_on_invalid_edit() {
+        let path = std::env::temp_dir().join(format!("oracle-watcher-{}.json", std::process::id()));
+        let write = |value: String| std::fs::write(&path, value).unwrap();
+        let config = |path: serde_json::Value| {
+            json!({
+                "currencies": {
+                    "BTC": { "name": "Bitcoin", "decimals": 8 },
+                    "USD": { "name": "Dollar", "decimals": 6 }
+                },
+                "prices": [{ "pair": ["BTC", "USD"], "feeds": { "kraken": path } }]
+            })
+            .to_string()
+        };
+
+        write(config(json!([["BTC", "USD"]])));
+        let load = {
+            let path = path.clone();
+            move || OracleConfig::from_path(&path)
+        };
+        let mut watcher = ConfigWatcher::new(vec![path.clone()], load().unwrap(), load);
+        let mut receiver = watcher.subscribe();
+        assert!(!watcher.check());
+
+        write(config(json!([["kraken", ["BTC", "USD"]]])));
+        assert!(watcher.check());
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(
+            receiver.borrow_and_update().prices[0].feeds[&FeedName::Kraken][0].feed,
+            Some(FeedName::Kraken)
+        );
+
+        // neither a malformed file nor an invalid path takes effect
+        for invalid in ["{".to_string(), config(json!([["DOT", "USD"]]))] {
+            write(invalid);
+            assert!(!watcher.check());
+            assert!(!receiver.has_changed().unwrap());
+            assert_eq!(
+                receiver.borrow().prices[0].feeds[&FeedName::Kraken][0].feed,
+                Some(FeedName::Kraken)
+            );
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
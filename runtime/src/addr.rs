@@ -1,7 +1,75 @@
-use crate::{BtcAddress, H160};
+use crate::{BtcAddress, H160, H256};
 use bitcoin::{
-    Address, ConversionError, Hash, Network, Payload, PubkeyHash, Script, ScriptHash, WPubkeyHash, WScriptHash,
+    bech32::{self, FromBase32, ToBase32},
+    Address, ConversionError, Hash, Network, Payload, PubkeyHash, Script, ScriptHash, WitnessVersion,
 };
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Characters which may appear in a descriptor, in the order of BIP-380.
+const DESCRIPTOR_INPUT_CHARSET: &str =
+    "0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const DESCRIPTOR_CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Networks an address can be encoded for, see `PartialAddress::encode_all`.
+const NETWORKS: [Network; 4] = [Network::Bitcoin, Network::Testnet, Network::Signet, Network::Regtest];
+
+fn descriptor_polymod(checksum: u64, value: u64) -> u64 {
+    const GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+    let top = checksum >> 35;
+    let mut checksum = ((checksum & 0x7ffffffff) << 5) ^ value;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+        if (top >> i) & 1 == 1 {
+            checksum ^= generator;
+        }
+    }
+    checksum
+}
+
+/// Append the BIP-380 checksum to the `descriptor`, which Bitcoin Core
+/// requires for `importdescriptors`.
+fn with_descriptor_checksum(descriptor: &str) -> Result<String, ConversionError> {
+    let mut checksum = 1;
+    let mut classes = Vec::with_capacity(3);
+    for c in descriptor.chars() {
+        let position = DESCRIPTOR_INPUT_CHARSET.find(c).ok_or(ConversionError::InvalidFormat)? as u64;
+        checksum = descriptor_polymod(checksum, position & 31);
+        classes.push(position >> 5);
+        if classes.len() == 3 {
+            checksum = descriptor_polymod(checksum, classes[0] * 9 + classes[1] * 3 + classes[2]);
+            classes.clear();
+        }
+    }
+    match classes[..] {
+        [class] => checksum = descriptor_polymod(checksum, class),
+        [first, second] => checksum = descriptor_polymod(checksum, first * 3 + second),
+        _ => {}
+    }
+    for _ in 0..8 {
+        checksum = descriptor_polymod(checksum, 0);
+    }
+    checksum ^= 1;
+
+    let checksum = (0..8)
+        .map(|i| DESCRIPTOR_CHECKSUM_CHARSET[((checksum >> (5 * (7 - i))) & 31) as usize] as char)
+        .collect::<String>();
+    Ok(format!("{}#{}", descriptor, checksum))
+}
+
+/// BIP-350: check that a bech32(m) encoded witness address uses the checksum
+/// variant of its witness version. Strings which do not decode as bech32(m),
+/// e.g. legacy addresses, are left to the address parser to report.
+fn check_checksum_variant(btc_address: &str) -> Result<(), ConversionError> {
+    let (data, variant) = match bech32::decode(btc_address) {
+        Ok((_, data, variant)) => (data, variant),
+        Err(_) => return Ok(()),
+    };
+    match data.first().map(|version| WitnessVersion::from_u5(*version)) {
+        Some(Ok(version)) if version.bech32_variant() != variant => {
+            Err(ConversionError::WrongChecksumVariant(version.to_num()))
+        }
+        _ => Ok(()),
+    }
+}
 
 pub trait PartialAddress: Sized + Eq + PartialOrd {
     /// Decode the `PartialAddress` from the `Payload` type.
@@ -24,6 +92,151 @@ pub trait PartialAddress: Sized + Eq + PartialOrd {
     /// # Arguments
     /// * `network` - network to prefix
     fn to_address(&self, network: Network) -> Result<Address, ConversionError>;
+
+    /// Decode the `PartialAddress` of a P2SH `script` given its redeem script.
+    /// A nested witness program such as P2SH-P2WPKH decodes to the program it
+    /// wraps, which a bare `3...` address cannot, any other redeem script
+    /// decodes to the P2SH itself. The nested form is recovered by wrapping
+    /// the script of the payload in a P2SH again.
+    ///
+    /// # Arguments
+    /// * `script` - P2SH script pubkey
+    /// * `redeem` - script whose hash is committed to by the `script`
+    fn from_script_with_redeem(script: &Script, redeem: &Script) -> Result<Self, ConversionError> {
+        if *script != Script::new_p2sh(&redeem.script_hash()) {
+            return Err(ConversionError::InvalidPayload);
+        }
+        if redeem.is_witness_program() {
+            Self::from_payload(Payload::from_script(redeem)?)
+        } else {
+            Self::from_payload(Payload::from_script(script)?)
+        }
+    }
+
+    /// Decode the `PartialAddress` from a string.
+    ///
+    /// # Arguments
+    /// * `btc_address` - encoded Bitcoin address
+    fn decode_str(btc_address: &str) -> Result<Self, ConversionError> {
+        check_checksum_variant(btc_address)?;
+        let address = Address::from_str(btc_address).map_err(|err| ConversionError::AddressParse(err.to_string()))?;
+        Self::from_address(address)
+    }
+
+    /// Encode the `PartialAddress` as a string.
+    ///
+    /// # Arguments
+    /// * `network` - network to prefix
+    fn encode_str(&self, network: Network) -> Result<String, ConversionError> {
+        Ok(self.to_address(network)?.to_string())
+    }
+
+    /// Encode the `PartialAddress` for every network, e.g. to show it for both
+    /// mainnet and testnet. The payload is computed once, so the encodings
+    /// only differ in their network prefix (and bech32 checksum).
+    fn encode_all(&self) -> Result<BTreeMap<Network, String>, ConversionError> {
+        let payload = self.to_payload()?;
+        Ok(NETWORKS
+            .iter()
+            .map(|network| {
+                let address = Address {
+                    payload: payload.clone(),
+                    network: *network,
+                };
+                (*network, address.to_string())
+            })
+            .collect())
+    }
+
+    /// Encode the `PartialAddress` as an output descriptor with its checksum,
+    /// e.g. to import it into a watch-only descriptor wallet.
+    ///
+    /// # Arguments
+    /// * `network` - network to prefix
+    fn to_descriptor(&self, network: Network) -> Result<String, ConversionError> {
+        // key and script descriptors such as `wpkh(...)` require the public key
+        // or script, of which the payload only contains the hash
+        with_descriptor_checksum(&format!("addr({})", self.encode_str(network)?))
+    }
+
+    /// Decode a witness `PartialAddress` that was encoded with a custom
+    /// human-readable part, e.g. for bitcoin-compatible forks.
+    ///
+    /// # Arguments
+    /// * `btc_address` - bech32(m) encoded witness address
+    /// * `hrp` - expected human-readable part
+    fn decode_str_with_hrp(btc_address: &str, hrp: &str) -> Result<Self, ConversionError> {
+        let (found, data, variant) = bech32::decode(btc_address)?;
+        if !found.eq_ignore_ascii_case(hrp) {
+            return Err(ConversionError::InvalidHrp {
+                expected: hrp.to_lowercase(),
+                found,
+            });
+        }
+        let (version, program) = data.split_first().ok_or(ConversionError::InvalidPayload)?;
+        let version = WitnessVersion::from_u5(*version)?;
+        if version.bech32_variant() != variant {
+            return Err(ConversionError::WrongChecksumVariant(version.to_num()));
+        }
+        let program = Vec::<u8>::from_base32(program)?;
+        // validates the witness program length
+        let payload = Payload::from_script(&Script::new_witness_program(version, &program))?;
+        Self::from_payload(payload)
+    }
+
+    /// Encode a witness `PartialAddress` with a custom human-readable part
+    /// instead of the one implied by the network.
+    ///
+    /// # Arguments
+    /// * `hrp` - human-readable part to prefix
+    fn encode_str_with_hrp(&self, hrp: &str) -> Result<String, ConversionError> {
+        match self.to_payload()? {
+            Payload::WitnessProgram { version, program } => {
+                let mut data = vec![bech32::u5::try_from_u8(version.to_num())?];
+                data.extend(program.to_base32());
+                Ok(bech32::encode(hrp, data, version.bech32_variant())?)
+            }
+            _ => Err(ConversionError::InvalidPayload),
+        }
+    }
+}
+
+/// A `Payload` with the network it is encoded for, which is (de)serialized
+/// as its address string, e.g. to persist it in a config.
+///
+/// Legacy addresses do not distinguish between testnet, signet and regtest,
+/// so these deserialize with the testnet network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkedPayload(pub Payload, pub Network);
+
+impl serde::Serialize for NetworkedPayload {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let NetworkedPayload(payload, network) = self;
+        let address = payload.encode_str(*network).map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&address)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for NetworkedPayload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let address = String::deserialize(deserializer)?;
+        let payload = Payload::decode_str(&address).map_err(serde::de::Error::custom)?;
+        let network = Address::from_str(&address).map_err(serde::de::Error::custom)?.network;
+        Ok(NetworkedPayload(payload, network))
+    }
+}
+
+/// Decode each of the `addrs`, e.g. the outputs of a block. The results
+/// are in the order of the inputs and an invalid address does not prevent
+/// decoding the others.
+pub fn decode_many<T: PartialAddress>(addrs: &[&str]) -> Vec<Result<T, ConversionError>> {
+    addrs.iter().map(|addr| T::decode_str(addr)).collect()
 }
 
 impl PartialAddress for BtcAddress {
@@ -31,25 +244,33 @@ impl PartialAddress for BtcAddress {
         match payload {
             Payload::PubkeyHash(hash) => Ok(Self::P2PKH(H160::from(hash.as_hash().into_inner()))),
             Payload::ScriptHash(hash) => Ok(Self::P2SH(H160::from(hash.as_hash().into_inner()))),
-            Payload::WitnessProgram { version: _, program } => {
-                if program.len() == 20 {
-                    Ok(Self::P2WPKHv0(H160::from_slice(program.as_slice())))
-                } else {
-                    Err(ConversionError::InvalidPayload)
-                }
-            }
+            // the parachain has no representation for taproot (v1) or later outputs
+            Payload::WitnessProgram {
+                version: WitnessVersion::V0,
+                program,
+            } => match program.len() {
+                20 => Ok(Self::P2WPKHv0(H160::from_slice(program.as_slice()))),
+                32 => Ok(Self::P2WSHv0(H256::from_slice(program.as_slice()))),
+                _ => Err(ConversionError::InvalidPayload),
+            },
+            Payload::WitnessProgram { .. } => Err(ConversionError::InvalidPayload),
         }
     }
 
     fn to_payload(&self) -> Result<Payload, ConversionError> {
-        let script = match self {
-            Self::P2PKH(hash) => Script::new_p2pkh(&PubkeyHash::from_slice(hash.as_bytes())?),
-            Self::P2SH(hash) => Script::new_p2sh(&ScriptHash::from_slice(hash.as_bytes())?),
-            Self::P2WPKHv0(hash) => Script::new_v0_p2wpkh(&WPubkeyHash::from_slice(hash.as_bytes())?),
-            Self::P2WSHv0(hash) => Script::new_v0_p2wsh(&WScriptHash::from_slice(hash.as_bytes())?),
-        };
-
-        Ok(Payload::from_script(&script)?)
+        // the hashes already have the right length, so the payload is built directly
+        Ok(match self {
+            Self::P2PKH(hash) => Payload::PubkeyHash(PubkeyHash::from_inner(hash.0)),
+            Self::P2SH(hash) => Payload::ScriptHash(ScriptHash::from_inner(hash.0)),
+            Self::P2WPKHv0(hash) => Payload::WitnessProgram {
+                version: WitnessVersion::V0,
+                program: hash.0.to_vec(),
+            },
+            Self::P2WSHv0(hash) => Payload::WitnessProgram {
+                version: WitnessVersion::V0,
+                program: hash.0.to_vec(),
+            },
+        })
     }
 
     fn from_address(address: Address) -> Result<Self, ConversionError> {
@@ -86,7 +307,7 @@ impl PartialAddress for Payload {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::str::FromStr;
+    use bitcoin::{WPubkeyHash, WScriptHash};
 
     #[test]
     fn test_encode_and_decode_payload() {
@@ -100,4 +321,295 @@ mod tests {
                 .to_string()
         );
     }
+
+    #[test]
+    fn test_encode_and_decode_p2wsh_address() {
+        let addr = "bcrt1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qzf4jry";
+        let btc_address = BtcAddress::decode_str(addr).unwrap();
+        assert!(matches!(btc_address, BtcAddress::P2WSHv0(_)));
+        assert_eq!(btc_address.encode_str(Network::Regtest).unwrap(), addr);
+    }
+
+    #[test]
+    fn test_to_payload_matches_script() {
+        let (h160, h256) = (H160::repeat_byte(0x42), H256::repeat_byte(0x42));
+        for (btc_address, script) in [
+            (
+                BtcAddress::P2PKH(h160),
+                Script::new_p2pkh(&PubkeyHash::from_slice(h160.as_bytes()).unwrap()),
+            ),
+            (
+                BtcAddress::P2SH(h160),
+                Script::new_p2sh(&ScriptHash::from_slice(h160.as_bytes()).unwrap()),
+            ),
+            (
+                BtcAddress::P2WPKHv0(h160),
+                Script::new_v0_p2wpkh(&WPubkeyHash::from_slice(h160.as_bytes()).unwrap()),
+            ),
+            (
+                BtcAddress::P2WSHv0(h256),
+                Script::new_v0_p2wsh(&WScriptHash::from_slice(h256.as_bytes()).unwrap()),
+            ),
+        ] {
+            let payload = btc_address.to_payload().unwrap();
+            assert_eq!(payload, Payload::from_script(&script).unwrap());
+            assert_eq!(BtcAddress::from_payload(payload).unwrap(), btc_address);
+            assert_eq!(
+                btc_address.to_address(Network::Regtest).unwrap().script_pubkey(),
+                script
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_p2sh_p2wpkh_with_redeem_script() {
+        // BIP-49 test vector, encoded for mainnet
+        let addr = "36NvZTcMsMowbt78wPzJaHHWaNiyR73Y4g";
+        let redeem = Script::from_str("001438971f73930f6c141d977ac4fd4a727c854935b3").unwrap();
+        let script = Address::from_str(addr).unwrap().script_pubkey();
+
+        // without the redeem script the nested witness program is a plain P2SH
+        assert_eq!(
+            BtcAddress::decode_str(addr).unwrap(),
+            BtcAddress::P2SH(H160::from_slice(&script.as_bytes()[2..22]))
+        );
+
+        let btc_address = BtcAddress::from_script_with_redeem(&script, &redeem).unwrap();
+        assert_eq!(
+            btc_address,
+            BtcAddress::P2WPKHv0(H160::from_slice(&redeem.as_bytes()[2..]))
+        );
+        // wrapping the witness program again yields the original script
+        let nested = Script::new_p2sh(&btc_address.to_payload().unwrap().script_pubkey().script_hash());
+        assert_eq!(nested, script);
+        assert_eq!(
+            Address::p2sh(&btc_address.to_payload().unwrap().script_pubkey(), Network::Bitcoin)
+                .unwrap()
+                .to_string(),
+            addr
+        );
+
+        // the redeem script must match the script hash
+        assert!(matches!(
+            BtcAddress::from_script_with_redeem(&script, &Script::new()),
+            Err(ConversionError::InvalidPayload)
+        ));
+
+        // other redeem scripts are not unwrapped
+        let redeem = Script::from(vec![0x51]);
+        let script = Script::new_p2sh(&redeem.script_hash());
+        assert_eq!(
+            BtcAddress::from_script_with_redeem(&script, &redeem).unwrap(),
+            BtcAddress::P2SH(H160::from_slice(&script.as_bytes()[2..22]))
+        );
+    }
+
+    #[test]
+    fn test_encode_and_decode_p2tr_payload() {
+        let addr = "bcrt1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqc8gma6";
+        let payload = Payload::decode_str(addr).unwrap();
+        assert!(matches!(
+            payload,
+            Payload::WitnessProgram {
+                version: WitnessVersion::V1,
+                ..
+            }
+        ));
+        // encoded as bech32m
+        assert_eq!(payload.encode_str(Network::Regtest).unwrap(), addr);
+        assert_eq!(payload.encode_str_with_hrp("bcrt").unwrap(), addr);
+
+        // must not be mistaken for a P2WSH address with the same program
+        assert!(matches!(
+            BtcAddress::decode_str(addr),
+            Err(ConversionError::InvalidPayload)
+        ));
+    }
+
+    #[test]
+    fn test_encode_and_decode_payload_with_custom_hrp() {
+        let payload = Payload::decode_str("bcrt1q6v2c7q7uv8vu6xle2k9ryfj3y3fuuy4rqnl50f").unwrap();
+
+        // the network hrp matches the default encoding
+        assert_eq!(
+            payload.encode_str_with_hrp("bcrt").unwrap(),
+            payload.encode_str(Network::Regtest).unwrap()
+        );
+
+        let encoded = payload.encode_str_with_hrp("side").unwrap();
+        assert!(encoded.starts_with("side1q"));
+        assert_eq!(Payload::decode_str_with_hrp(&encoded, "side").unwrap(), payload);
+        assert!(matches!(
+            Payload::decode_str_with_hrp(&encoded, "bcrt"),
+            Err(ConversionError::InvalidHrp { .. })
+        ));
+    }
+
+    #[test]
+    fn test_descriptor_checksum() {
+        // test vector of BIP-380
+        assert_eq!(
+            with_descriptor_checksum("raw(deadbeef)").unwrap(),
+            "raw(deadbeef)#89f8spxm"
+        );
+        assert!(matches!(
+            with_descriptor_checksum("raw(\u{e9})"),
+            Err(ConversionError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn test_encode_payload_as_descriptor() {
+        for (addr, descriptor) in [
+            (
+                "bcrt1q6v2c7q7uv8vu6xle2k9ryfj3y3fuuy4rqnl50f",
+                "addr(bcrt1q6v2c7q7uv8vu6xle2k9ryfj3y3fuuy4rqnl50f)#q9ysvrhk",
+            ),
+            (
+                "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn",
+                "addr(mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn)#eccnnjdy",
+            ),
+        ] {
+            let payload = Payload::decode_str(addr).unwrap();
+            assert_eq!(payload.to_descriptor(Network::Regtest).unwrap(), descriptor);
+            let btc_address = BtcAddress::decode_str(addr).unwrap();
+            assert_eq!(btc_address.to_descriptor(Network::Regtest).unwrap(), descriptor);
+        }
+    }
+
+    #[test]
+    fn test_encode_payload_for_all_networks() {
+        let btc_address = BtcAddress::decode_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        let encoded = btc_address.encode_all().unwrap();
+        assert_eq!(encoded.len(), NETWORKS.len());
+        for (network, addr) in &encoded {
+            assert_eq!(*addr, btc_address.encode_str(*network).unwrap());
+            assert_eq!(BtcAddress::decode_str(addr).unwrap(), btc_address);
+        }
+
+        // the program is the same, only the hrp and thus the checksum differ
+        let mainnet = &encoded[&Network::Bitcoin];
+        let regtest = &encoded[&Network::Regtest];
+        assert_eq!(mainnet, "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert_eq!(regtest, "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080");
+        let data = |addr: &str| {
+            let (_, data) = addr.split_once('1').unwrap();
+            data[..data.len() - 6].to_string()
+        };
+        assert_eq!(data(mainnet), data(regtest));
+
+        // legacy addresses share the testnet prefix across the test networks
+        let p2pkh = Payload::decode_str("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn").unwrap();
+        let encoded = p2pkh.encode_all().unwrap();
+        assert_eq!(encoded[&Network::Testnet], "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn");
+        assert_eq!(encoded[&Network::Regtest], encoded[&Network::Testnet]);
+        assert_ne!(encoded[&Network::Bitcoin], encoded[&Network::Testnet]);
+    }
+
+    #[test]
+    fn test_serialize_and_deserialize_networked_payload() {
+        for (addr, network) in [
+            ("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", Network::Testnet),
+            ("2N8hwP1WmJrFF5QWABn38y63uYLhnJYJYTF", Network::Testnet),
+            ("bcrt1q6v2c7q7uv8vu6xle2k9ryfj3y3fuuy4rqnl50f", Network::Regtest),
+            (
+                "bcrt1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qzf4jry",
+                Network::Regtest,
+            ),
+            (
+                "bcrt1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqc8gma6",
+                Network::Regtest,
+            ),
+            ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", Network::Bitcoin),
+        ] {
+            let payload = NetworkedPayload(Payload::decode_str(addr).unwrap(), network);
+            let json = serde_json::to_string(&payload).unwrap();
+            assert_eq!(json, format!("\"{}\"", addr));
+            assert_eq!(serde_json::from_str::<NetworkedPayload>(&json).unwrap(), payload);
+        }
+
+        assert!(serde_json::from_str::<NetworkedPayload>("\"mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfm\"").is_err());
+    }
+
+    #[test]
+    fn test_decode_many_preserves_order() {
+        let addrs = [
+            "bcrt1q6v2c7q7uv8vu6xle2k9ryfj3y3fuuy4rqnl50f",
+            "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfm",
+            "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn",
+            "",
+        ];
+        let decoded = decode_many::<BtcAddress>(&addrs);
+        assert!(matches!(
+            decoded[..],
+            [
+                Ok(BtcAddress::P2WPKHv0(_)),
+                Err(ConversionError::AddressParse(_)),
+                Ok(BtcAddress::P2PKH(_)),
+                Err(ConversionError::AddressParse(_))
+            ]
+        ));
+        assert_eq!(
+            decoded[2].as_ref().unwrap().encode_str(Network::Regtest).unwrap(),
+            addrs[2]
+        );
+    }
+
+    #[test]
+    fn test_encode_with_custom_hrp_rejects_legacy_payload() {
+        let payload = Payload::decode_str("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn").unwrap();
+        assert!(matches!(
+            payload.encode_str_with_hrp("side"),
+            Err(ConversionError::InvalidPayload)
+        ));
+    }
+
+    #[test]
+    fn test_decode_rejects_wrong_checksum_variant() {
+        // test vectors of BIP-173 and BIP-350
+        let v0 = Payload::decode_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap();
+        // the same v0 program with a bech32m checksum
+        assert!(matches!(
+            Payload::decode_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh"),
+            Err(ConversionError::WrongChecksumVariant(0))
+        ));
+        assert!(matches!(
+            BtcAddress::decode_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh"),
+            Err(ConversionError::WrongChecksumVariant(0))
+        ));
+
+        let v1 = Payload::decode_str("bcrt1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqc8gma6").unwrap();
+        // the same v1 program with a bech32 checksum
+        assert!(matches!(
+            Payload::decode_str("bcrt1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqdmchcc"),
+            Err(ConversionError::WrongChecksumVariant(1))
+        ));
+
+        // custom hrps are checked as well
+        for payload in [v0, v1] {
+            let encoded = payload.encode_str_with_hrp("side").unwrap();
+            assert_eq!(Payload::decode_str_with_hrp(&encoded, "side").unwrap(), payload);
+        }
+        assert!(matches!(
+            Payload::decode_str_with_hrp("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh", "bc"),
+            Err(ConversionError::WrongChecksumVariant(0))
+        ));
+    }
+
+    #[test]
+    fn test_decode_str_preserves_parse_error() {
+        let parse_error = |btc_address: &str| match Payload::decode_str(btc_address) {
+            Err(ConversionError::AddressParse(err)) => err,
+            result => panic!("Unexpected result: {:?}", result),
+        };
+
+        // last character changed
+        let bad_checksum = parse_error("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfm");
+        assert!(bad_checksum.contains("checksum"), "{}", bad_checksum);
+
+        let unknown_prefix = parse_error(&bitcoin::util::base58::check_encode_slice(&[0x42; 21]));
+        assert!(unknown_prefix.contains("version"), "{}", unknown_prefix);
+
+        assert_ne!(bad_checksum, unknown_prefix);
+    }
 }
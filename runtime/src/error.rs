@@ -41,6 +41,8 @@ pub enum Error {
     VaultLiquidated,
     #[error("Channel closed unexpectedly")]
     ChannelClosed,
+    #[error("Connection was not opened from a url, cannot reconnect")]
+    CannotReconnect,
     #[error("Cannot replace existing transaction")]
     PoolTooLowPriority,
     #[error("Transaction did not get included - block hash not found")]
@@ -27,7 +27,7 @@ use subxt::{
     Config,
 };
 
-pub use addr::PartialAddress;
+pub use addr::{decode_many, NetworkedPayload, PartialAddress};
 pub use assets::{AssetRegistry, RuntimeCurrencyInfo, TryFromSymbol};
 pub use error::{Error, SubxtError};
 pub use primitives::CurrencyInfo;
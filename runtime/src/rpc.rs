@@ -1,4 +1,5 @@
 use crate::{
+    cli::ConnectionOpts,
     conn::{new_websocket_client, new_websocket_client_with_retry},
     metadata, notify_retry,
     types::*,
@@ -73,6 +74,8 @@ pub struct InterBtcParachain {
     account_id: AccountId,
     shutdown_tx: ShutdownSender,
     fee_rate_update_tx: FeeRateUpdateSender,
+    /// Settings to reopen the connection, unknown if it was not opened from a url
+    connection: Option<ConnectionOpts>,
     pub native_currency_id: CurrencyId,
     pub relay_chain_currency_id: CurrencyId,
     pub wrapped_currency_id: CurrencyId,
@@ -127,6 +130,7 @@ impl InterBtcParachain {
             account_id,
             shutdown_tx,
             fee_rate_update_tx,
+            connection: None,
             native_currency_id,
             relay_chain_currency_id,
             wrapped_currency_id,
@@ -201,7 +205,27 @@ impl InterBtcParachain {
             connection_timeout,
         )
         .await?;
-        Self::new(ws_client, signer, shutdown_tx).await
+        let mut parachain_rpc = Self::new(ws_client, signer, shutdown_tx).await?;
+        parachain_rpc.connection = Some(ConnectionOpts {
+            btc_parachain_url: url.to_string(),
+            btc_parachain_connection_timeout_ms: connection_timeout,
+            max_concurrent_requests,
+            max_notifs_per_subscription,
+        });
+        Ok(parachain_rpc)
+    }
+
+    /// Open a new connection with the same settings and signer, e.g. after
+    /// this one was dropped.
+    pub async fn reconnect(&self) -> Result<Self, Error> {
+        match &self.connection {
+            Some(connection) => {
+                connection
+                    .try_connect(self.signer.clone(), self.shutdown_tx.clone())
+                    .await
+            }
+            None => Err(Error::CannotReconnect),
+        }
     }
 
     async fn get_fresh_nonce(&self) -> u32 {
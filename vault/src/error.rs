@@ -1,4 +1,4 @@
-use bitcoin::Error as BitcoinError;
+use bitcoin::{Error as BitcoinError, Network};
 use jsonrpc_core_client::RpcError;
 use parity_scale_codec::Error as CodecError;
 use runtime::Error as RuntimeError;
@@ -23,6 +23,8 @@ pub enum Error {
     FaucetUrlNotSet,
     #[error("Faucet allowance for `{0}` not set")]
     FaucetAllowanceNotSet(String),
+    #[error("Bitcoin node is not on {0}")]
+    NetworkMismatch(Network),
 
     #[error("RPC error: {0}")]
     RpcError(#[from] RpcError),
@@ -1,7 +1,10 @@
 use crate::{
     delay::RandomDelay, metrics::publish_expected_bitcoin_balance, Error, Event, IssueRequests, VaultIdManager,
 };
-use bitcoin::{BlockHash, Error as BitcoinError, PublicKey, Transaction, TransactionExt};
+use bitcoin::{
+    required_confirmations, BlockHash, ConfirmationPolicy, Error as BitcoinError, PublicKey, Transaction,
+    TransactionExt,
+};
 use futures::{channel::mpsc::Sender, future, SinkExt, StreamExt, TryFutureExt};
 use runtime::{
     BtcAddress, BtcPublicKey, BtcRelayPallet, CancelIssueEvent, ExecuteIssueEvent, H256Le, InterBtcParachain,
@@ -9,7 +12,7 @@ use runtime::{
 };
 use service::{DynBitcoinCoreApi, Error as ServiceError};
 use sha2::{Digest, Sha256};
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
 // initialize `issue_set` with currently open issues, and return the block height
 // from which to start watching the bitcoin chain
@@ -34,14 +37,45 @@ pub(crate) async fn initialize_issue_set(
     Ok(btc_start_height)
 }
 
+/// What to do with a deposit whose OP_RETURN id names another issue than the
+/// one it pays to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OpReturnMismatch {
+    /// Execute the issue without logging the mismatch.
+    Ignore,
+    /// Execute the issue and log a warning.
+    #[default]
+    Warn,
+    /// Do not execute the issue.
+    Skip,
+}
+
+impl FromStr for OpReturnMismatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ignore" => Ok(Self::Ignore),
+            "warn" => Ok(Self::Warn),
+            "skip" => Ok(Self::Skip),
+            _ => Err(format!("Expected one of ignore, warn or skip, got {}", s)),
+        }
+    }
+}
+
 /// execute issue requests on best-effort (i.e. don't retry on error),
 /// returns an error if stream ends, otherwise runs forever
+///
+/// Deposits are streamed once they have `num_confirmations`, the `confirmation_policy`
+/// may require more before the issue of a large deposit is executed.
 pub async fn process_issue_requests(
     bitcoin_core: DynBitcoinCoreApi,
     btc_parachain: InterBtcParachain,
     issue_set: Arc<IssueRequests>,
     btc_start_height: u32,
     num_confirmations: u32,
+    confirmation_policy: ConfirmationPolicy,
+    op_return_mismatch: OpReturnMismatch,
     random_delay: Arc<Box<dyn RandomDelay + Send + Sync>>,
 ) -> Result<(), ServiceError<Error>> {
     let mut stream =
@@ -54,7 +88,8 @@ pub async fn process_issue_requests(
                     bitcoin_core.clone(),
                     btc_parachain.clone(),
                     issue_set.clone(),
-                    num_confirmations,
+                    confirmation_policy.clone(),
+                    op_return_mismatch,
                     block_hash,
                     transaction,
                     random_delay.clone(),
@@ -136,12 +171,21 @@ pub async fn add_keys_from_past_issue_request(
     Ok(())
 }
 
+/// An output paying to the deposit address of an open issue request.
+struct MatchedOutput {
+    issue_id: H256,
+    address: BtcAddress,
+    /// Id embedded in an OP_RETURN output of the same transaction, if any
+    op_return_id: Option<H256>,
+}
+
 /// execute issue requests with a matching Bitcoin payment
 async fn process_transaction_and_execute_issue(
     bitcoin_core: DynBitcoinCoreApi,
     btc_parachain: InterBtcParachain,
     issue_set: Arc<IssueRequests>,
-    num_confirmations: u32,
+    confirmation_policy: ConfirmationPolicy,
+    op_return_mismatch: OpReturnMismatch,
     block_hash: BlockHash,
     transaction: Transaction,
     random_delay: Arc<Box<dyn RandomDelay + Send + Sync>>,
@@ -152,10 +196,40 @@ async fn process_transaction_and_execute_issue(
         .filter_map(|payload| BtcAddress::from_payload(payload).ok())
         .collect();
     let mut issue_requests = issue_set.lock().await;
-    if let Some((issue_id, address)) = addresses.iter().find_map(|address| {
+    if let Some(MatchedOutput {
+        issue_id,
+        address,
+        op_return_id,
+    }) = addresses.iter().find_map(|address| {
         let issue_id = issue_requests.get_key_for_value(address)?;
-        Some((*issue_id, *address))
+        Some(MatchedOutput {
+            issue_id: *issue_id,
+            address: *address,
+            op_return_id: transaction.extract_op_return_id(),
+        })
     }) {
+        // a deposit which names another issue may not be meant for this one, but wallets
+        // can also attach unrelated OP_RETURN data
+        if let Some(op_return_id) = op_return_id.filter(|op_return_id| op_return_id != &issue_id) {
+            match op_return_mismatch {
+                OpReturnMismatch::Ignore => (),
+                OpReturnMismatch::Warn => tracing::warn!(
+                    "Deposit {} for issue {} carries the OP_RETURN id {}",
+                    transaction.txid(),
+                    issue_id,
+                    op_return_id
+                ),
+                OpReturnMismatch::Skip => {
+                    tracing::warn!(
+                        "Deposit {} for issue {} carries the OP_RETURN id {}, not executing",
+                        transaction.txid(),
+                        issue_id,
+                        op_return_id
+                    );
+                    return Ok(());
+                }
+            }
+        }
         let issue = btc_parachain.get_issue_request(issue_id).await?;
         let payload = if let Ok(payload) = address.to_payload() {
             payload
@@ -194,9 +268,11 @@ async fn process_transaction_and_execute_issue(
                 issue_requests.remove_value(&address);
 
                 // at this point we know that the transaction has `num_confirmations` on the bitcoin chain,
-                // but the relay can introduce a delay, so wait until the relay also confirms the transaction.
+                // but the relay can introduce a delay, so wait until the relay also confirms the transaction
+                // with as many confirmations as the amount requires.
+                let confirmations = required_confirmations(transferred, &confirmation_policy);
                 btc_parachain
-                    .wait_for_block_in_relay(H256Le::from_bytes_le(&block_hash), Some(num_confirmations))
+                    .wait_for_block_in_relay(H256Le::from_bytes_le(&block_hash), Some(confirmations))
                     .await?;
 
                 // wait a random amount of blocks, to avoid all vaults flooding the parachain with
@@ -21,6 +21,7 @@ pub mod service {
         execution::execute_open_requests,
         issue::{
             listen_for_issue_cancels, listen_for_issue_executes, listen_for_issue_requests, process_issue_requests,
+            OpReturnMismatch,
         },
         metrics::monitor_bridge_metrics,
         redeem::listen_for_redeem_requests,
@@ -16,7 +16,7 @@ use tokio_stream::StreamExt;
 use vault::{
     metrics::{self, increment_restart_counter},
     process::PidFile,
-    Error, VaultService, VaultServiceConfig, ABOUT, AUTHORS, NAME, VERSION,
+    relay, Error, VaultService, VaultServiceConfig, ABOUT, AUTHORS, NAME, VERSION,
 };
 
 #[derive(Parser)]
@@ -125,6 +125,14 @@ pub struct RunVaultOpts {
     /// Prometheus monitoring settings.
     #[clap(flatten)]
     pub monitoring: MonitoringConfig,
+
+    /// Token to pause and resume relaying at `/relay/{pause,resume}` on the
+    /// Prometheus endpoint, passed as `Authorization: Bearer <token>`.
+    /// `/relay/status` reports whether relaying is paused or degraded, the
+    /// hash and height of the best block relayed since startup and the
+    /// relay rates per minute.
+    #[clap(long)]
+    pub relay_control_token: Option<String>,
 }
 
 async fn catch_signals<F>(mut shutdown_signals: Signals, future: F) -> Result<(), ServiceError<Error>>
@@ -141,6 +149,8 @@ where
                 tracing::info!("Received termination signal: {}", signal);
             }
             tracing::info!("Shutting down...");
+            // let the relayer finish the submission in progress
+            relay::RELAY_CONTROL.stop().await;
         }
     }
     Ok(())
@@ -177,7 +187,12 @@ async fn start() -> Result<(), ServiceError<Error>> {
 
     if !opts.monitoring.no_prometheus {
         metrics::register_custom_metrics()?;
-        let metrics_route = warp::path("metrics").and_then(metrics::metrics_handler);
+        let metrics_route = warp::path("metrics")
+            .and_then(metrics::metrics_handler)
+            .or(relay::control_routes(
+                relay::RELAY_CONTROL.clone(),
+                opts.relay_control_token.clone(),
+            ));
         let prometheus_host = if opts.monitoring.prometheus_external {
             Ipv4Addr::UNSPECIFIED
         } else {
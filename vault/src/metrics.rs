@@ -96,6 +96,24 @@ lazy_static! {
             .expect("Failed to create prometheus metric");
     pub static ref RESTART_COUNT: IntCounter =
         IntCounter::new("restart_count", "Number of service restarts").expect("Failed to create prometheus metric");
+    pub static ref RELAY_SUBMISSION_RATE: Gauge =
+        Gauge::new("relay_submission_rate", "Number of block headers submitted per minute")
+            .expect("Failed to create prometheus metric");
+    pub static ref RELAY_PRODUCTION_RATE: Gauge =
+        Gauge::new("relay_production_rate", "Number of bitcoin blocks produced per minute")
+            .expect("Failed to create prometheus metric");
+    pub static ref RELAY_PAUSED: IntGauge =
+        IntGauge::new("relay_paused", "Set to 1 while relaying is paused").expect("Failed to create prometheus metric");
+    pub static ref RELAY_TIME_LAG: Gauge = Gauge::new(
+        "relay_time_lag_seconds",
+        "Number of seconds since the relayer was last up to date"
+    )
+    .expect("Failed to create prometheus metric");
+    pub static ref RELAY_DEGRADED: IntGauge = IntGauge::new(
+        "relay_degraded",
+        "Set to 1 while the relayer has not been up to date for longer than the maximum time lag"
+    )
+    .expect("Failed to create prometheus metric");
 }
 
 #[derive(Clone, Debug)]
@@ -304,6 +322,11 @@ pub fn register_custom_metrics() -> Result<(), RuntimeError> {
     REGISTRY.register(Box::new(MEAN_SCHEDULED_DURATION.clone()))?;
     REGISTRY.register(Box::new(REMAINING_TIME_TO_REDEEM_HOURS.clone()))?;
     REGISTRY.register(Box::new(RESTART_COUNT.clone()))?;
+    REGISTRY.register(Box::new(RELAY_SUBMISSION_RATE.clone()))?;
+    REGISTRY.register(Box::new(RELAY_PRODUCTION_RATE.clone()))?;
+    REGISTRY.register(Box::new(RELAY_PAUSED.clone()))?;
+    REGISTRY.register(Box::new(RELAY_TIME_LAG.clone()))?;
+    REGISTRY.register(Box::new(RELAY_DEGRADED.clone()))?;
 
     Ok(())
 }
@@ -452,6 +475,25 @@ pub fn increment_restart_counter() {
     RESTART_COUNT.inc();
 }
 
+pub fn publish_relay_paused(paused: bool) {
+    RELAY_PAUSED.set(paused as i64);
+}
+
+pub fn publish_relay_degraded(degraded: bool) {
+    RELAY_DEGRADED.set(degraded as i64);
+}
+
+pub fn publish_relay_time_lag(time_lag: Duration) {
+    RELAY_TIME_LAG.set(time_lag.as_secs_f64());
+}
+
+pub fn publish_relay_rates(submission_rate: f64, production_rate: Option<f64>) {
+    RELAY_SUBMISSION_RATE.set(submission_rate);
+    if let Some(production_rate) = production_rate {
+        RELAY_PRODUCTION_RATE.set(production_rate);
+    }
+}
+
 async fn publish_issue_count<V: VaultDataReader, P: IssuePallet + UtilFuncs>(parachain_rpc: &P, vault_id_manager: &V) {
     if let Ok(issues) = parachain_rpc
         .get_vault_issue_requests(parachain_rpc.get_account_id().clone())
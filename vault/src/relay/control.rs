@@ -0,0 +1,344 @@
+use super::RelayState;
+use crate::metrics::{publish_relay_degraded, publish_relay_paused};
+use bitcoin::BlockHash;
+use lazy_static::lazy_static;
+use service::warp::{self, http::StatusCode, Filter, Rejection, Reply};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{Mutex, MutexGuard},
+    time::timeout,
+};
+
+/// How long a submission in progress may take to finish on shutdown.
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+lazy_static! {
+    /// Shared by all restarts of the relayer, so that it stays paused
+    /// if the relayer is restarted e.g. after a disconnect.
+    pub static ref RELAY_CONTROL: Arc<RelayControl> = Arc::new(RelayControl::default());
+}
+
+/// Pauses the relayer, e.g. during a planned parachain upgrade,
+/// and reports whether it is degraded and the blocks it relayed.
+#[derive(Debug, Default)]
+pub struct RelayControl {
+    paused: AtomicBool,
+    degraded: AtomicBool,
+    best_block: RwLock<Option<(BlockHash, u32)>>,
+    relayed: RwLock<Option<RelayState>>,
+    /// Headers submitted and blocks produced per minute, see `RelayRate`
+    rates: RwLock<Option<(f64, Option<f64>)>>,
+    stopped: AtomicBool,
+    /// Held while the relayer submits to the parachain
+    submitting: Mutex<()>,
+}
+
+impl RelayControl {
+    /// Stop making new submissions, those which are already in flight finish.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+        publish_relay_paused(true);
+        tracing::info!("Relaying paused");
+    }
+
+    /// Resume from the best block stored on the parachain.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        publish_relay_paused(false);
+        tracing::info!("Relaying resumed");
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Set while the relayer has not been up to date for longer than its maximum time lag.
+    pub fn set_degraded(&self, degraded: bool) {
+        if self.degraded.swap(degraded, Ordering::SeqCst) != degraded {
+            publish_relay_degraded(degraded);
+            if degraded {
+                tracing::warn!("Relaying is degraded: no block has been relayed for too long");
+            } else {
+                tracing::info!("Relaying recovered");
+            }
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.degraded.load(Ordering::SeqCst)
+    }
+
+    /// Record the hash and height of the last block which was submitted.
+    pub fn set_best_block(&self, hash: BlockHash, height: u32) {
+        *self.best_block.write().unwrap_or_else(|err| err.into_inner()) = Some((hash, height));
+    }
+
+    /// The hash and height of the best block relayed since startup, if any.
+    pub fn best_block(&self) -> Option<(BlockHash, u32)> {
+        *self.best_block.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Record that the blocks from `height` up to `best_height` were relayed.
+    pub fn record_relayed(&self, height: u32, best_height: u32) {
+        let mut relayed = self.relayed.write().unwrap_or_else(|err| err.into_inner());
+        let start_height = relayed.map_or(height, |relayed| relayed.start_height.min(height));
+        *relayed = Some(RelayState {
+            start_height,
+            best_height,
+        });
+    }
+
+    /// The range of blocks relayed since startup, if any.
+    pub fn relayed(&self) -> Option<RelayState> {
+        *self.relayed.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Record the submission and production rates of the relayer.
+    pub fn set_rates(&self, submission_rate: f64, production_rate: Option<f64>) {
+        *self.rates.write().unwrap_or_else(|err| err.into_inner()) = Some((submission_rate, production_rate));
+    }
+
+    /// The submission and production rates, if anything was submitted since startup.
+    pub fn rates(&self) -> Option<(f64, Option<f64>)> {
+        *self.rates.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Never start another submission and wait up to `STOP_TIMEOUT` for the
+    /// one in progress to finish, so that it is not dropped mid-call on shutdown.
+    pub async fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if timeout(STOP_TIMEOUT, self.submitting.lock()).await.is_err() {
+            tracing::warn!("Submission still in progress after {:?}, stopping anyway", STOP_TIMEOUT);
+        }
+        tracing::info!("Relaying stopped");
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Held by the relayer for the duration of a submission, `None` once stopped.
+    pub async fn start_submission(&self) -> Option<MutexGuard<'_, ()>> {
+        let submitting = self.submitting.lock().await;
+        if self.is_stopped() {
+            None
+        } else {
+            Some(submitting)
+        }
+    }
+}
+
+/// Reply with the status, including the confirmations of the block at `height` if given.
+fn reply_status(
+    control: &RelayControl,
+    authorized: bool,
+    height: Option<u32>,
+    action: impl FnOnce(&RelayControl),
+) -> impl Reply {
+    if !authorized {
+        return warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({ "error": "unauthorized" })),
+            StatusCode::UNAUTHORIZED,
+        );
+    }
+    action(control);
+    let relayed = control.relayed();
+    let mut status = serde_json::json!({
+        "paused": control.is_paused(),
+        "degraded": control.is_degraded(),
+        "best_block": control.best_block().map(|(hash, height)| serde_json::json!({
+            "hash": hash.to_string(),
+            "height": height,
+        })),
+        "rates": control.rates().map(|(submission_rate, production_rate)| serde_json::json!({
+            "submission_rate": submission_rate,
+            "production_rate": production_rate,
+        })),
+        "relayed": relayed.map(|relayed| serde_json::json!({
+            "start_height": relayed.start_height,
+            "best_height": relayed.best_height,
+        })),
+    });
+    if let Some(height) = height {
+        status["confirmations"] = relayed.and_then(|relayed| relayed.confirmations(height)).into();
+    }
+    warp::reply::with_status(warp::reply::json(&status), StatusCode::OK)
+}
+
+/// Routes to pause, resume and get the status of the relayer, `/relay/status/<height>`
+/// also reports the confirmations of the block at that height. Requests must
+/// have the header `Authorization: Bearer <token>`, if no token is set
+/// all requests are rejected.
+pub fn control_routes(
+    control: Arc<RelayControl>,
+    token: Option<String>,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let expected = token.map(|token| format!("Bearer {}", token));
+    let authorized = warp::header::optional::<String>("authorization").map(move |header: Option<String>| {
+        matches!((&expected, header), (Some(expected), Some(header)) if *expected == header)
+    });
+    let with_control = warp::any().map(move || control.clone());
+
+    let pause = warp::post()
+        .and(warp::path!("relay" / "pause"))
+        .and(with_control.clone())
+        .and(authorized.clone())
+        .map(|control: Arc<RelayControl>, authorized| reply_status(&control, authorized, None, RelayControl::pause));
+    let resume = warp::post()
+        .and(warp::path!("relay" / "resume"))
+        .and(with_control.clone())
+        .and(authorized.clone())
+        .map(|control: Arc<RelayControl>, authorized| reply_status(&control, authorized, None, RelayControl::resume));
+    let status = warp::get()
+        .and(warp::path!("relay" / "status"))
+        .and(with_control.clone())
+        .and(authorized.clone())
+        .map(|control: Arc<RelayControl>, authorized| reply_status(&control, authorized, None, |_| ()));
+    let confirmations = warp::get()
+        .and(warp::path!("relay" / "status" / u32))
+        .and(with_control)
+        .and(authorized)
+        .map(|height, control: Arc<RelayControl>, authorized| reply_status(&control, authorized, Some(height), |_| ()));
+    pause.or(resume).or(status).or(confirmations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_status(control: &Arc<RelayControl>) -> serde_json::Value {
+        get_path(control, "/relay/status").await
+    }
+
+    async fn get_path(control: &Arc<RelayControl>, path: &str) -> serde_json::Value {
+        let response = warp::test::request()
+            .path(path)
+            .header("authorization", "Bearer secret")
+            .reply(&control_routes(control.clone(), Some("secret".to_string())))
+            .await;
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_reject_unauthorized_requests() {
+        let control = Arc::new(RelayControl::default());
+        let routes = control_routes(control.clone(), Some("secret".to_string()));
+
+        for authorization in [None, Some("Bearer wrong")] {
+            let mut request = warp::test::request().method("POST").path("/relay/pause");
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            assert_eq!(request.reply(&routes).await.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert!(!control.is_paused());
+
+        // without a token nothing is authorized
+        let response = warp::test::request()
+            .path("/relay/status")
+            .header("authorization", "Bearer ")
+            .reply(&control_routes(control.clone(), None))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/relay/pause")
+            .header("authorization", "Bearer secret")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(control.is_paused());
+    }
+
+    #[tokio::test]
+    async fn should_report_degraded_status() {
+        let control = Arc::new(RelayControl::default());
+        control.set_degraded(true);
+        assert_eq!(get_status(&control).await["degraded"], true);
+        control.set_degraded(false);
+        assert_eq!(get_status(&control).await["degraded"], false);
+    }
+
+    #[tokio::test]
+    async fn should_report_confirmations_of_relayed_blocks() {
+        let control = Arc::new(RelayControl::default());
+        assert_eq!(get_status(&control).await["relayed"], serde_json::Value::Null);
+        assert_eq!(
+            get_path(&control, "/relay/status/100").await["confirmations"],
+            serde_json::Value::Null
+        );
+
+        control.record_relayed(100, 105);
+        control.record_relayed(106, 110);
+        assert_eq!(
+            get_status(&control).await["relayed"],
+            serde_json::json!({ "start_height": 100, "best_height": 110 })
+        );
+        for (height, confirmations) in [
+            (110, serde_json::json!(1)),
+            (100, serde_json::json!(11)),
+            (99, serde_json::Value::Null),
+            (111, serde_json::Value::Null),
+        ] {
+            let path = format!("/relay/status/{}", height);
+            assert_eq!(get_path(&control, &path).await["confirmations"], confirmations);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_wait_for_submission_on_stop() {
+        let control = RelayControl::default();
+        let submission = control.start_submission().await.unwrap();
+        let start = tokio::time::Instant::now();
+        tokio::join!(control.stop(), async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            drop(submission);
+        });
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert!(control.is_stopped());
+        assert!(control.start_submission().await.is_none());
+
+        // a submission which never finishes does not block shutdown
+        let control = RelayControl::default();
+        let _submission = control.start_submission().await.unwrap();
+        let start = tokio::time::Instant::now();
+        control.stop().await;
+        assert_eq!(start.elapsed(), STOP_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn should_report_relay_rates() {
+        let control = Arc::new(RelayControl::default());
+        control.set_rates(0.5, None);
+        assert_eq!(
+            get_status(&control).await["rates"],
+            serde_json::json!({ "submission_rate": 0.5, "production_rate": null })
+        );
+        control.set_rates(0.25, Some(0.1));
+        assert_eq!(
+            get_status(&control).await["rates"],
+            serde_json::json!({ "submission_rate": 0.25, "production_rate": 0.1 })
+        );
+    }
+
+    #[tokio::test]
+    async fn should_report_best_relayed_block() {
+        let control = Arc::new(RelayControl::default());
+        let genesis_hash = bitcoin::genesis_block(bitcoin::Network::Bitcoin).block_hash();
+        control.set_best_block(genesis_hash, 0);
+        assert_eq!(
+            get_status(&control).await["best_block"],
+            serde_json::json!({
+                "hash": "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+                "height": 0,
+            })
+        );
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(clippy::enum_variant_names)]
 
-use bitcoin::Error as BitcoinError;
+use bitcoin::{Error as BitcoinError, Network};
 use runtime::Error as RuntimeError;
 use thiserror::Error;
 
@@ -23,6 +23,14 @@ pub enum Error {
     DecodeHash,
     #[error("Failed to serialize block header")]
     SerializeHeader,
+    #[error("Parachain disconnected")]
+    ParachainDisconnected,
+    #[error("Relay loop stalled for {0:?}")]
+    Stalled(std::time::Duration),
+    #[error("Backing chain is not {0}")]
+    NetworkMismatch(Network),
+    #[error("Gave up after {0} retries: {1}")]
+    RetriesExhausted(u32, Box<Error>),
 
     #[error("BitcoinError: {0}")]
     BitcoinError(#[from] BitcoinError),
@@ -30,6 +38,16 @@ pub enum Error {
     RuntimeError(#[from] RuntimeError),
 }
 
+impl Error {
+    pub fn is_parachain_disconnected(&self) -> bool {
+        match self {
+            Error::ParachainDisconnected => true,
+            Error::RuntimeError(err) => err.is_rpc_disconnect_error(),
+            _ => false,
+        }
+    }
+}
+
 #[cfg(test)]
 impl PartialEq for Error {
     fn eq(&self, other: &Self) -> bool {
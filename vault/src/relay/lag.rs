@@ -0,0 +1,62 @@
+use std::{sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+/// Tracks how long ago the relayer was last up to date, which catches a
+/// relayer that is stuck while few blocks are produced, when the lag in
+/// blocks stays small.
+#[derive(Debug)]
+pub struct TimeLag {
+    max_time_lag: Duration,
+    last_progress: Mutex<Instant>,
+}
+
+impl TimeLag {
+    pub fn new(max_time_lag: Duration) -> Self {
+        Self {
+            max_time_lag,
+            last_progress: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Report that blocks were relayed, or that there were none to relay.
+    pub fn record_progress(&self) {
+        if let Ok(mut last_progress) = self.last_progress.lock() {
+            *last_progress = Instant::now();
+        }
+    }
+
+    /// Time since the relayer was last up to date.
+    pub fn time_lag(&self) -> Duration {
+        self.last_progress
+            .lock()
+            .map(|last_progress| last_progress.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Whether blocks have been pending for longer than the maximum lag.
+    pub fn is_degraded(&self) -> bool {
+        self.time_lag() > self.max_time_lag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::advance;
+
+    #[tokio::test(start_paused = true)]
+    async fn should_degrade_without_relays() {
+        let time_lag = TimeLag::new(Duration::from_secs(30 * 60));
+
+        advance(Duration::from_secs(20 * 60)).await;
+        assert_eq!(time_lag.time_lag(), Duration::from_secs(20 * 60));
+        assert!(!time_lag.is_degraded());
+
+        advance(Duration::from_secs(11 * 60)).await;
+        assert!(time_lag.is_degraded());
+
+        time_lag.record_progress();
+        assert_eq!(time_lag.time_lag(), Duration::ZERO);
+        assert!(!time_lag.is_degraded());
+    }
+}
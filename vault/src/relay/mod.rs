@@ -1,21 +1,44 @@
-use runtime::InterBtcParachain;
-use service::{DynBitcoinCoreApi, Error as ServiceError};
-use std::{sync::Arc, time::Duration};
+use bitcoin::{deserialize, genesis_block, serialize, BlockHeader, Network};
+use service::Error as ServiceError;
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::time::sleep;
 
-use crate::delay::RandomDelay;
+use crate::{
+    delay::RandomDelay,
+    metrics::{publish_relay_rates, publish_relay_time_lag},
+};
 
 mod backing;
+mod control;
 mod error;
 mod issuing;
+mod lag;
+mod rate;
+mod retry;
+mod state;
+mod watchdog;
 
 pub use backing::Backing;
+pub use control::{control_routes, RelayControl, RELAY_CONTROL};
 pub use error::Error;
 pub use issuing::Issuing;
+pub use lag::TimeLag;
+pub use rate::{RelayRate, RATE_WINDOW};
+pub use retry::{is_transient, retry_transient, RetryBacking};
+pub use state::RelayState;
+pub use watchdog::Watchdog;
 
 // 10 minutes = 600 seconds
 const SLEEP_TIME: Duration = Duration::from_secs(600);
 
+/// Delay before the first reconnection attempt, doubles on every failure
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
 /// Retrieves `batch` blocks starting at block `height` from the backing blockchain
 async fn collect_headers(height: u32, batch: u32, cli: &impl Backing) -> Result<Vec<Vec<u8>>, Error> {
     let mut headers = Vec::new();
@@ -66,6 +89,19 @@ pub struct Config {
     pub interval: Option<Duration>,
     /// Number of confirmations a block needs to have before it is submitted.
     pub btc_confirmations: u32,
+    /// Restart the relayer if an iteration takes longer than this,
+    /// should be larger than the interval
+    pub stall_timeout: Option<Duration>,
+    /// Network of the backing chain, the relayer aborts before
+    /// submitting anything if the genesis block does not match
+    pub network: Option<Network>,
+    /// Pauses and resumes relaying, defaults to a control which is never paused
+    pub control: Option<Arc<RelayControl>>,
+    /// Report the relayer as degraded if it has not been up to date for this long
+    pub max_time_lag: Option<Duration>,
+    /// Log the headers instead of submitting them, nothing is ever submitted
+    /// to the issuing blockchain
+    pub dry_run: bool,
 }
 
 /// Runner implements the main loop for the relayer
@@ -77,6 +113,12 @@ pub struct Runner<B: Backing, I: Issuing> {
     max_batch_size: u32,
     interval: Duration,
     btc_confirmations: u32,
+    rate: Mutex<RelayRate>,
+    watchdog: Option<Arc<Watchdog>>,
+    time_lag: Option<TimeLag>,
+    network: Option<Network>,
+    control: Arc<RelayControl>,
+    dry_run: bool,
 }
 
 impl<B: Backing, I: Issuing> Runner<B, I> {
@@ -94,7 +136,90 @@ impl<B: Backing, I: Issuing> Runner<B, I> {
             max_batch_size: conf.max_batch_size,
             interval: conf.interval.unwrap_or(SLEEP_TIME),
             btc_confirmations: conf.btc_confirmations,
+            rate: Mutex::new(RelayRate::new(RATE_WINDOW)),
+            watchdog: conf
+                .stall_timeout
+                .map(|stall_timeout| Arc::new(Watchdog::new(stall_timeout))),
+            time_lag: conf.max_time_lag.map(TimeLag::new),
+            network: conf.network,
+            control: conf.control.unwrap_or_default(),
+            dry_run: conf.dry_run,
+        }
+    }
+
+    /// Record the submitted headers, starting at `height`, and publish
+    /// the updated relay rates
+    fn record_submission(&self, height: u32, headers: &[Vec<u8>]) {
+        let now = Instant::now();
+        if let Ok(mut rate) = self.rate.lock() {
+            rate.record(now, headers);
+            let (submission_rate, production_rate) = (rate.submission_rate(now), rate.production_rate(now));
+            publish_relay_rates(submission_rate, production_rate);
+            self.control.set_rates(submission_rate, production_rate);
+        }
+        if let Some(header) = headers
+            .last()
+            .and_then(|header| deserialize::<BlockHeader>(header).ok())
+        {
+            let best_height = height.saturating_add(headers.len() as u32).saturating_sub(1);
+            self.control.set_best_block(header.block_hash(), best_height);
+            self.control.record_relayed(height, best_height);
+        }
+        self.record_progress();
+    }
+
+    /// Record that the relayer is up to date
+    fn record_progress(&self) {
+        if let Some(time_lag) = &self.time_lag {
+            time_lag.record_progress();
+        }
+        self.publish_time_lag();
+    }
+
+    /// Publish the time lag and flag the relayer as degraded if it exceeds the maximum
+    fn publish_time_lag(&self) {
+        if let Some(time_lag) = &self.time_lag {
+            publish_relay_time_lag(time_lag.time_lag());
+            self.control.set_degraded(time_lag.is_degraded());
+        }
+    }
+
+    /// Log the headers which would have been submitted starting at `height`.
+    /// Since nothing is stored, wait before the same headers are logged again.
+    async fn log_dry_run(&self, height: u32, headers: &[Vec<u8>]) {
+        for (height, header) in (height..).zip(headers) {
+            tracing::info!(
+                "Dry run, not submitting block header at height {}: {}",
+                height,
+                hex::encode(header)
+            );
         }
+        sleep(self.interval).await;
+    }
+
+    /// Submit the `headers`, one at a time or as a batch
+    async fn submit_headers(&self, mut headers: Vec<Vec<u8>>) -> Result<(), Error> {
+        if headers.len() == 1 {
+            self.issuing
+                .submit_block_header(headers.remove(0), self.random_delay.clone())
+                .await
+        } else {
+            self.issuing.submit_block_header_batch(headers).await
+        }
+    }
+
+    /// Check that the genesis block of the backing chain matches the
+    /// expected network, so that we never relay headers of another chain
+    pub async fn check_network(&self) -> Result<(), Error> {
+        let network = match self.network {
+            Some(network) => network,
+            None => return Ok(()),
+        };
+        let genesis_hash = self.backing.get_block_hash(0).await?;
+        if genesis_hash != serialize(&genesis_block(network).block_hash()) {
+            return Err(Error::NetworkMismatch(network));
+        }
+        Ok(())
     }
 
     /// Returns the block header at `height`
@@ -118,18 +243,76 @@ impl<B: Backing, I: Issuing> Runner<B, I> {
             .saturating_sub(self.btc_confirmations))
     }
 
+    /// Reconnect to the issuing blockchain with exponential backoff,
+    /// retrying until a connection is established
+    async fn reconnect<C, F>(&mut self, connect: C)
+    where
+        C: Fn() -> F,
+        F: Future<Output = Result<I, Error>>,
+    {
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            tracing::warn!("{}, reconnecting in {:?}", Error::ParachainDisconnected, delay);
+            sleep(delay).await;
+            match connect().await {
+                Ok(issuing) => {
+                    self.issuing = issuing;
+                    tracing::info!("Reconnected to the parachain");
+                    return;
+                }
+                Err(err) => {
+                    tracing::error!("Failed to reconnect: {}", err);
+                    delay = delay.saturating_mul(2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        }
+    }
+
+    /// Submit the next block(s), reconnecting if the issuing blockchain
+    /// disconnected. Since the start height is computed from the blocks
+    /// stored on the issuing blockchain, submission resumes where it left off.
+    pub async fn submit_next_or_reconnect<C, F>(&mut self, connect: C) -> Result<(), Error>
+    where
+        C: Fn() -> F,
+        F: Future<Output = Result<I, Error>>,
+    {
+        match self.submit_next().await {
+            Err(err) if err.is_parachain_disconnected() => {
+                self.reconnect(connect).await;
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
     /// Submit the next block(s) or initialize the relay,
     /// may submit up to `max_batch_size` blocks at a time
     pub async fn submit_next(&self) -> Result<(), Error> {
+        if self.control.is_paused() {
+            // submissions from the previous call have already finished
+            tracing::trace!("Relaying is paused, waiting...");
+            // falling behind on purpose does not degrade the relayer
+            self.record_progress();
+            sleep(self.interval).await;
+            return Ok(());
+        }
+        self.publish_time_lag();
+
         if !self.issuing.is_initialized().await? {
             let start_height = self.start_height.unwrap_or(self.get_num_confirmed_blocks().await?);
+            let header = self.backing.get_block_header(start_height).await?.unwrap();
+            if self.dry_run {
+                tracing::info!("Dry run, not initializing at height {}", start_height);
+                self.log_dry_run(start_height, &[header]).await;
+                return Ok(());
+            }
+            let _submission = match self.control.start_submission().await {
+                Some(submission) => submission,
+                None => return Ok(()),
+            };
             tracing::info!("Initializing at height {}", start_height);
-            self.issuing
-                .initialize(
-                    self.backing.get_block_header(start_height).await?.unwrap(),
-                    start_height,
-                )
-                .await?;
+            self.issuing.initialize(header, start_height).await?;
+            self.control.record_relayed(start_height, start_height);
         }
 
         let max_height = self.get_num_confirmed_blocks().await?;
@@ -147,16 +330,24 @@ impl<B: Backing, I: Issuing> Runner<B, I> {
             0 => {
                 // nothing to submit right now. Wait a little while
                 tracing::trace!("Waiting for the next Bitcoin block...");
+                self.record_progress();
                 sleep(self.interval).await;
             }
             1 => {
                 // submit a single block header
                 tracing::info!("Processing block at height {}", current_height);
                 let header = self.get_block_header(current_height).await?;
+                if self.dry_run {
+                    self.log_dry_run(current_height, &[header]).await;
+                    return Ok(());
+                }
+                let _submission = match self.control.start_submission().await {
+                    Some(submission) => submission,
+                    None => return Ok(()),
+                };
                 // TODO: check if block already stored
-                self.issuing
-                    .submit_block_header(header, self.random_delay.clone())
-                    .await?;
+                self.submit_headers(vec![header.clone()]).await?;
+                self.record_submission(current_height, &[header]);
                 tracing::info!("Submitted block at height {}", current_height);
             }
             _ => {
@@ -167,7 +358,16 @@ impl<B: Backing, I: Issuing> Runner<B, I> {
                     batch_size
                 );
                 let headers = collect_headers(current_height, batch_size, &self.backing).await?;
-                self.issuing.submit_block_header_batch(headers).await?;
+                if self.dry_run {
+                    self.log_dry_run(current_height, &headers).await;
+                    return Ok(());
+                }
+                let _submission = match self.control.start_submission().await {
+                    Some(submission) => submission,
+                    None => return Ok(()),
+                };
+                self.submit_headers(headers.clone()).await?;
+                self.record_submission(current_height, &headers);
                 tracing::info!(
                     "Submitted blocks {} -> {} [{}]",
                     current_height,
@@ -181,25 +381,75 @@ impl<B: Backing, I: Issuing> Runner<B, I> {
     }
 }
 
-pub async fn run_relayer(
-    runner: Runner<DynBitcoinCoreApi, InterBtcParachain>,
-) -> Result<(), ServiceError<crate::Error>> {
+/// Relay until the relay control is stopped, which happens on shutdown.
+/// The issuing blockchain is reconnected with `connect` if it disconnects.
+async fn relay_until_stopped<B, I, C, F>(
+    runner: &mut Runner<B, I>,
+    connect: C,
+) -> Result<(), ServiceError<crate::Error>>
+where
+    B: Backing,
+    I: Issuing,
+    C: Fn() -> F,
+    F: Future<Output = Result<I, Error>>,
+{
     loop {
-        match runner.submit_next().await {
+        if runner.control.is_stopped() {
+            tracing::info!("Relayer shut down");
+            return Ok(());
+        }
+        match runner.submit_next_or_reconnect(&connect).await {
             Ok(_) => (),
             Err(Error::RuntimeError(ref err)) if err.is_duplicate_block() => {
                 tracing::info!("Attempted to submit block that already exists")
             }
-            Err(Error::RuntimeError(ref err)) if err.is_rpc_disconnect_error() => {
+            Err(Error::BitcoinError(err)) if err.is_transport_error() => {
                 return Err(ServiceError::ClientShutdown);
             }
-            Err(Error::BitcoinError(err)) if err.is_transport_error() => {
+            Err(err @ Error::RetriesExhausted(..)) => {
+                tracing::error!("Bitcoin node is unavailable: {}", err);
                 return Err(ServiceError::ClientShutdown);
             }
             Err(err) => {
                 tracing::error!("Failed to submit_next: {}", err);
             }
         }
+        if let Some(watchdog) = &runner.watchdog {
+            watchdog.feed();
+        }
+    }
+}
+
+pub async fn run_relayer<B, I, C, F>(mut runner: Runner<B, I>, connect: C) -> Result<(), ServiceError<crate::Error>>
+where
+    B: Backing,
+    I: Issuing,
+    C: Fn() -> F,
+    F: Future<Output = Result<I, Error>>,
+{
+    match runner.check_network().await {
+        Err(Error::NetworkMismatch(network)) => {
+            return Err(ServiceError::Abort(crate::Error::NetworkMismatch(network)));
+        }
+        Err(err) => {
+            tracing::error!("Failed to check the bitcoin network: {}", err);
+            return Err(ServiceError::ClientShutdown);
+        }
+        Ok(()) => (),
+    }
+
+    let watchdog = runner.watchdog.clone();
+    let relay = relay_until_stopped(&mut runner, connect);
+    match watchdog {
+        Some(watchdog) => {
+            watchdog.feed();
+            watchdog.guard(relay).await.unwrap_or_else(|err| {
+                // exit so that the relayer is restarted
+                tracing::error!("{}", err);
+                Err(ServiceError::ClientShutdown)
+            })
+        }
+        None => relay.await,
     }
 }
 
@@ -213,10 +463,12 @@ mod tests {
         cell::{Ref, RefCell, RefMut},
         collections::HashMap,
         rc::Rc,
+        sync::atomic::{AtomicUsize, Ordering},
     };
 
     struct DummyIssuing {
         headers: Rc<RefCell<HashMap<u32, Vec<u8>>>>,
+        connected: bool,
     }
 
     unsafe impl Sync for DummyIssuing {}
@@ -225,6 +477,22 @@ mod tests {
         fn new(headers: HashMap<u32, Vec<u8>>) -> DummyIssuing {
             DummyIssuing {
                 headers: Rc::new(RefCell::new(headers)),
+                connected: true,
+            }
+        }
+
+        /// Simulate a dropped connection, the headers persist across reconnects
+        fn disconnected(&self) -> DummyIssuing {
+            DummyIssuing {
+                headers: self.headers.clone(),
+                connected: false,
+            }
+        }
+
+        fn reconnected(&self) -> DummyIssuing {
+            DummyIssuing {
+                headers: self.headers.clone(),
+                connected: true,
             }
         }
 
@@ -240,6 +508,9 @@ mod tests {
     #[async_trait]
     impl Issuing for DummyIssuing {
         async fn is_initialized(&self) -> Result<bool, Error> {
+            if !self.connected {
+                return Err(Error::ParachainDisconnected);
+            }
             Ok(!self.get_headers().is_empty())
         }
 
@@ -391,10 +662,8 @@ mod tests {
             backing,
             issuing,
             Config {
-                start_height: None,
                 max_batch_size: 1,
-                interval: None,
-                btc_confirmations: 0,
+                ..Default::default()
             },
             Arc::new(Box::new(ZeroDelay)),
         );
@@ -415,8 +684,7 @@ mod tests {
             Config {
                 start_height: Some(0),
                 max_batch_size: 16,
-                interval: None,
-                btc_confirmations: 0,
+                ..Default::default()
             },
             Arc::new(Box::new(ZeroDelay)),
         );
@@ -447,10 +715,8 @@ mod tests {
             backing,
             issuing,
             Config {
-                start_height: None,
                 max_batch_size: 1,
-                interval: None,
-                btc_confirmations: 0,
+                ..Default::default()
             },
             Arc::new(Box::new(ZeroDelay)),
         );
@@ -477,10 +743,10 @@ mod tests {
             backing,
             issuing,
             Config {
-                start_height: None,
                 interval: Some(Duration::from_secs(0)),
                 max_batch_size: 16,
                 btc_confirmations: 1,
+                ..Default::default()
             },
             Arc::new(Box::new(ZeroDelay)),
         );
@@ -510,10 +776,10 @@ mod tests {
             backing,
             issuing,
             Config {
-                start_height: None,
                 max_batch_size: 1,
                 interval: Some(Duration::from_secs(0)),
                 btc_confirmations: 1,
+                ..Default::default()
             },
             Arc::new(Box::new(ZeroDelay)),
         );
@@ -544,10 +810,10 @@ mod tests {
             backing,
             issuing,
             Config {
-                start_height: None,
                 max_batch_size: 1,
                 interval: Some(Duration::from_secs(0)),
                 btc_confirmations: 2,
+                ..Default::default()
             },
             Arc::new(Box::new(ZeroDelay)),
         );
@@ -569,4 +835,323 @@ mod tests {
         assert!(!runner.issuing.is_block_stored(make_hash("d")).await?);
         Ok(())
     }
+
+    #[tokio::test(start_paused = true)]
+    async fn submit_next_resumes_after_reconnect() -> Result<(), Error> {
+        let backing_hashes = make_hashes(vec![(2, "a"), (3, "b"), (4, "c"), (5, "d")]);
+        let issuing_hashes = make_hashes(vec![(2, "a"), (3, "b")]);
+        let backing = DummyBacking::new(backing_hashes);
+        let issuing = DummyIssuing::new(issuing_hashes);
+        let disconnected = issuing.disconnected();
+        let mut runner = Runner::new(
+            backing,
+            disconnected,
+            Config {
+                max_batch_size: 1,
+                ..Default::default()
+            },
+            Arc::new(Box::new(ZeroDelay)),
+        );
+
+        // the first attempt to reconnect fails
+        let attempts = RefCell::new(0);
+        let connect = || {
+            *attempts.borrow_mut() += 1;
+            let result = if *attempts.borrow() > 1 {
+                Ok(issuing.reconnected())
+            } else {
+                Err(Error::ParachainDisconnected)
+            };
+            async move { result }
+        };
+
+        runner.submit_next_or_reconnect(connect).await?;
+        assert_eq!(*attempts.borrow(), 2);
+        assert_eq!(runner.issuing.get_best_height().await?, 3);
+
+        runner.submit_next_or_reconnect(connect).await?;
+        runner.submit_next_or_reconnect(connect).await?;
+        assert_eq!(runner.issuing.get_best_height().await?, 5);
+        assert!(runner.issuing.is_block_stored(make_hash("c")).await?);
+        assert!(runner.issuing.is_block_stored(make_hash("d")).await?);
+        assert_eq!(*attempts.borrow(), 2);
+        Ok(())
+    }
+
+    fn network_runner(genesis_hash: Vec<u8>) -> Runner<DummyBacking, DummyIssuing> {
+        let hashes = vec![(0, genesis_hash)].into_iter().collect();
+        Runner::new(
+            DummyBacking::new(hashes),
+            DummyIssuing::new(HashMap::new()),
+            Config {
+                max_batch_size: 1,
+                network: Some(Network::Regtest),
+                ..Default::default()
+            },
+            Arc::new(Box::new(ZeroDelay)),
+        )
+    }
+
+    #[tokio::test]
+    async fn should_accept_matching_genesis() {
+        let runner = network_runner(serialize(&genesis_block(Network::Regtest).block_hash()));
+        assert_eq!(runner.check_network().await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn should_abort_on_mismatched_genesis() {
+        // backing reports the mainnet chain
+        let runner = network_runner(serialize(&genesis_block(Network::Bitcoin).block_hash()));
+        assert_eq!(
+            runner.check_network().await,
+            Err(Error::NetworkMismatch(Network::Regtest))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_record_best_relayed_block() -> Result<(), Error> {
+        let header = |nonce| {
+            serialize(&BlockHeader {
+                nonce,
+                ..genesis_block(Network::Regtest).header
+            })
+        };
+        let backing_hashes = (2..=5).map(|height| (height, header(height))).collect();
+        let issuing_hashes = (2..=3).map(|height| (height, header(height))).collect();
+        let control = Arc::new(RelayControl::default());
+        let runner = Runner::new(
+            DummyBacking::new(backing_hashes),
+            DummyIssuing::new(issuing_hashes),
+            Config {
+                max_batch_size: 16,
+                control: Some(control.clone()),
+                ..Default::default()
+            },
+            Arc::new(Box::new(ZeroDelay)),
+        );
+        assert_eq!(control.best_block(), None);
+
+        // the best block of a batch is its last
+        runner.submit_next().await?;
+        assert_eq!(runner.issuing.get_best_height().await?, 5);
+        let best_hash = deserialize::<BlockHeader>(&header(5)).unwrap().block_hash();
+        assert_eq!(control.best_block(), Some((best_hash, 5)));
+        assert_eq!(
+            control.relayed(),
+            Some(RelayState {
+                start_height: 4,
+                best_height: 5
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_submit_while_paused() -> Result<(), Error> {
+        let backing_hashes = make_hashes(vec![(2, "a"), (3, "b"), (4, "c"), (5, "d")]);
+        let issuing_hashes = make_hashes(vec![(2, "a"), (3, "b")]);
+        let control = Arc::new(RelayControl::default());
+        let runner = Runner::new(
+            DummyBacking::new(backing_hashes),
+            DummyIssuing::new(issuing_hashes),
+            Config {
+                max_batch_size: 1,
+                control: Some(control.clone()),
+                ..Default::default()
+            },
+            Arc::new(Box::new(ZeroDelay)),
+        );
+
+        control.pause();
+        for _ in 0..3 {
+            runner.submit_next().await?;
+        }
+        assert_eq!(runner.issuing.get_best_height().await?, 3);
+
+        // continues from the best block stored on the issuing chain
+        control.resume();
+        runner.submit_next().await?;
+        assert_eq!(runner.issuing.get_best_height().await?, 4);
+        assert!(runner.issuing.is_block_stored(make_hash("c")).await?);
+        Ok(())
+    }
+
+    async fn never_reconnect() -> Result<DummyIssuing, Error> {
+        Err(Error::ParachainDisconnected)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_reconnect_while_relaying() -> Result<(), Error> {
+        let backing_hashes = make_hashes(vec![(2, "a"), (3, "b"), (4, "c"), (5, "d")]);
+        let issuing = DummyIssuing::new(make_hashes(vec![(2, "a"), (3, "b")]));
+        let control = Arc::new(RelayControl::default());
+        let runner = Runner::new(
+            DummyBacking::new(backing_hashes),
+            issuing.disconnected(),
+            Config {
+                max_batch_size: 1,
+                control: Some(control.clone()),
+                ..Default::default()
+            },
+            Arc::new(Box::new(ZeroDelay)),
+        );
+
+        let attempts = RefCell::new(0);
+        let connect = || {
+            *attempts.borrow_mut() += 1;
+            let reconnected = issuing.reconnected();
+            async move { Ok(reconnected) }
+        };
+        let stop = async {
+            while issuing.get_best_height().await? < 5 {
+                sleep(Duration::from_secs(1)).await;
+            }
+            control.stop().await;
+            Ok::<_, Error>(())
+        };
+        // the relayer keeps running after the disconnect and resumes where it left off
+        let (relayed, stopped) = tokio::join!(run_relayer(runner, connect), stop);
+        assert!(matches!(relayed, Ok(())));
+        stopped?;
+        assert_eq!(*attempts.borrow(), 1);
+        assert!(issuing.is_block_stored(make_hash("c")).await?);
+        assert!(issuing.is_block_stored(make_hash("d")).await?);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_shut_down_when_stopped() -> Result<(), Error> {
+        let backing_hashes = make_hashes(vec![(2, "a"), (3, "b"), (4, "c"), (5, "d")]);
+        let issuing = DummyIssuing::new(make_hashes(vec![(2, "a"), (3, "b")]));
+        let control = Arc::new(RelayControl::default());
+        let mut runner = Runner::new(
+            DummyBacking::new(backing_hashes),
+            issuing.reconnected(),
+            Config {
+                max_batch_size: 1,
+                control: Some(control.clone()),
+                ..Default::default()
+            },
+            Arc::new(Box::new(ZeroDelay)),
+        );
+
+        let stop = async {
+            while issuing.get_best_height().await? < 5 {
+                sleep(Duration::from_secs(1)).await;
+            }
+            // the relayer is waiting for the next block, not submitting
+            let start = tokio::time::Instant::now();
+            control.stop().await;
+            assert_eq!(start.elapsed(), Duration::ZERO);
+            Ok::<_, Error>(())
+        };
+        let (relayed, stopped) = tokio::join!(relay_until_stopped(&mut runner, never_reconnect), stop);
+        assert!(matches!(relayed, Ok(())));
+        stopped?;
+        assert_eq!(issuing.get_best_height().await?, 5);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_submit_once_stopped() -> Result<(), Error> {
+        let backing_hashes = make_hashes(vec![(2, "a"), (3, "b"), (4, "c")]);
+        let control = Arc::new(RelayControl::default());
+        control.stop().await;
+        for issuing_hashes in [HashMap::new(), make_hashes(vec![(2, "a"), (3, "b")])] {
+            for max_batch_size in [1, 16] {
+                let mut runner = Runner::new(
+                    DummyBacking::new(backing_hashes.clone()),
+                    DummyIssuing::new(issuing_hashes.clone()),
+                    Config {
+                        max_batch_size,
+                        control: Some(control.clone()),
+                        ..Default::default()
+                    },
+                    Arc::new(Box::new(ZeroDelay)),
+                );
+                runner.submit_next().await?;
+                assert_eq!(*runner.issuing.get_headers(), issuing_hashes);
+                assert!(matches!(
+                    relay_until_stopped(&mut runner, never_reconnect).await,
+                    Ok(())
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the calls which would mutate the issuing blockchain
+    struct RecordingIssuing {
+        issuing: DummyIssuing,
+        submissions: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Issuing for RecordingIssuing {
+        async fn is_initialized(&self) -> Result<bool, Error> {
+            self.issuing.is_initialized().await
+        }
+
+        async fn initialize(&self, header: Vec<u8>, height: u32) -> Result<(), Error> {
+            self.submissions.fetch_add(1, Ordering::SeqCst);
+            self.issuing.initialize(header, height).await
+        }
+
+        async fn submit_block_header(
+            &self,
+            header: Vec<u8>,
+            random_delay: Arc<Box<dyn RandomDelay + Send + Sync>>,
+        ) -> Result<(), Error> {
+            self.submissions.fetch_add(1, Ordering::SeqCst);
+            self.issuing.submit_block_header(header, random_delay).await
+        }
+
+        async fn submit_block_header_batch(&self, headers: Vec<Vec<u8>>) -> Result<(), Error> {
+            self.submissions.fetch_add(1, Ordering::SeqCst);
+            self.issuing.submit_block_header_batch(headers).await
+        }
+
+        async fn get_best_height(&self) -> Result<u32, Error> {
+            self.issuing.get_best_height().await
+        }
+
+        async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>, Error> {
+            self.issuing.get_block_hash(height).await
+        }
+
+        async fn is_block_stored(&self, hash: Vec<u8>) -> Result<bool, Error> {
+            self.issuing.is_block_stored(hash).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_submit_in_dry_run() -> Result<(), Error> {
+        let backing_hashes = make_hashes(vec![(2, "a"), (3, "b"), (4, "c"), (5, "d")]);
+        // uninitialized, a single header and a batch of headers
+        for (issuing_hashes, max_batch_size) in [
+            (HashMap::new(), 1),
+            (make_hashes(vec![(2, "a"), (3, "b")]), 1),
+            (make_hashes(vec![(2, "a"), (3, "b")]), 16),
+        ] {
+            let runner = Runner::new(
+                DummyBacking::new(backing_hashes.clone()),
+                RecordingIssuing {
+                    issuing: DummyIssuing::new(issuing_hashes.clone()),
+                    submissions: AtomicUsize::new(0),
+                },
+                Config {
+                    max_batch_size,
+                    dry_run: true,
+                    ..Default::default()
+                },
+                Arc::new(Box::new(ZeroDelay)),
+            );
+
+            runner.submit_next().await?;
+            runner.submit_next().await?;
+            assert_eq!(runner.issuing.submissions.load(Ordering::SeqCst), 0);
+            assert_eq!(*runner.issuing.issuing.get_headers(), issuing_hashes);
+        }
+        Ok(())
+    }
 }
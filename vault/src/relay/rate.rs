@@ -0,0 +1,123 @@
+use bitcoin::{deserialize, BlockHeader};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+const SECONDS_PER_MINUTE: f64 = 60.0;
+
+/// Rolling window over which the relay rates are computed.
+pub const RATE_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Tracks the number of submitted block headers and the timestamps of the
+/// relayed blocks over a rolling window, so we can compare how fast we relay
+/// against how fast bitcoin produces blocks.
+#[derive(Debug)]
+pub struct RelayRate {
+    window: Duration,
+    /// Number of headers submitted at each point in time
+    submissions: VecDeque<(Instant, usize)>,
+    /// Timestamps of the relayed blocks, as reported in their headers
+    block_times: VecDeque<(Instant, u32)>,
+}
+
+impl RelayRate {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            submissions: VecDeque::new(),
+            block_times: VecDeque::new(),
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        let window = self.window;
+        let expired = |at: &Instant| now.saturating_duration_since(*at) > window;
+        while matches!(self.submissions.front(), Some((at, _)) if expired(at)) {
+            self.submissions.pop_front();
+        }
+        while matches!(self.block_times.front(), Some((at, _)) if expired(at)) {
+            self.block_times.pop_front();
+        }
+    }
+
+    /// Record the submission of the raw `headers` at time `now`.
+    pub fn record(&mut self, now: Instant, headers: &[Vec<u8>]) {
+        self.submissions.push_back((now, headers.len()));
+        self.block_times.extend(
+            headers
+                .iter()
+                .filter_map(|header| deserialize::<BlockHeader>(header).ok())
+                .map(|header| (now, header.time)),
+        );
+        self.prune(now);
+    }
+
+    /// Number of headers submitted per minute, averaged over the window.
+    pub fn submission_rate(&mut self, now: Instant) -> f64 {
+        self.prune(now);
+        let submitted: usize = self.submissions.iter().map(|(_, count)| count).sum();
+        submitted as f64 * SECONDS_PER_MINUTE / self.window.as_secs_f64()
+    }
+
+    /// Number of blocks produced per minute, inferred from the timestamps of
+    /// the relayed blocks. Returns `None` if there are not enough blocks.
+    pub fn production_rate(&mut self, now: Instant) -> Option<f64> {
+        self.prune(now);
+        let first = self.block_times.iter().map(|(_, time)| *time).min()?;
+        let last = self.block_times.iter().map(|(_, time)| *time).max()?;
+        match last.saturating_sub(first) {
+            0 => None,
+            elapsed => Some((self.block_times.len() - 1) as f64 * SECONDS_PER_MINUTE / elapsed as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{serialize, BlockHash, Hash, TxMerkleNode};
+
+    fn make_header(time: u32) -> Vec<u8> {
+        serialize(&BlockHeader {
+            version: 4,
+            prev_blockhash: BlockHash::all_zeros(),
+            merkle_root: TxMerkleNode::all_zeros(),
+            time,
+            bits: 0,
+            nonce: 0,
+        })
+    }
+
+    #[test]
+    fn should_compute_submission_rate() {
+        let start = Instant::now();
+        let mut rate = RelayRate::new(Duration::from_secs(10 * 60));
+
+        rate.record(start, &[make_header(0)]);
+        rate.record(start + Duration::from_secs(60), &[make_header(600), make_header(1200)]);
+        rate.record(start + Duration::from_secs(120), &[make_header(1800)]);
+
+        // 4 headers in a 10 minute window
+        assert_eq!(rate.submission_rate(start + Duration::from_secs(120)), 0.4);
+        // 4 blocks spanning 30 minutes
+        assert_eq!(rate.production_rate(start + Duration::from_secs(120)), Some(0.1));
+
+        // the first submission falls out of the window
+        assert_eq!(rate.submission_rate(start + Duration::from_secs(11 * 60)), 0.3);
+        assert_eq!(rate.production_rate(start + Duration::from_secs(11 * 60)), Some(0.1));
+
+        // everything falls out of the window
+        assert_eq!(rate.submission_rate(start + Duration::from_secs(13 * 60)), 0.0);
+        assert_eq!(rate.production_rate(start + Duration::from_secs(13 * 60)), None);
+    }
+
+    #[test]
+    fn should_ignore_unparsable_headers_for_production_rate() {
+        let now = Instant::now();
+        let mut rate = RelayRate::new(Duration::from_secs(60));
+        rate.record(now, &[b"a".to_vec(), b"b".to_vec()]);
+        assert_eq!(rate.submission_rate(now), 2.0);
+        assert_eq!(rate.production_rate(now), None);
+    }
+}
@@ -0,0 +1,148 @@
+use super::{Backing, Error};
+use async_trait::async_trait;
+use bitcoin::{BitcoinError as BitcoinCoreError, BitcoinRpcError, Error as BitcoinError, JsonRpcError};
+use std::{future::Future, time::Duration};
+use tokio::time::sleep;
+
+/// Delay before the first retry, doubles on every transient failure
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Whether the bitcoin core rpc call may succeed if it is retried, e.g. after
+/// the connection was reset or while the node is warming up. Errors returned
+/// by the node for the request itself, such as invalid parameters, are permanent.
+pub fn is_transient(err: &BitcoinError) -> bool {
+    match err {
+        BitcoinError::ConnectionRefused | BitcoinError::TimeElapsed(_) => true,
+        BitcoinError::BitcoinError(BitcoinCoreError::JsonRpc(JsonRpcError::Transport(_))) => true,
+        BitcoinError::BitcoinError(BitcoinCoreError::JsonRpc(JsonRpcError::Rpc(err))) => matches!(
+            BitcoinRpcError::from(err.clone()),
+            BitcoinRpcError::RpcInWarmup | BitcoinRpcError::RpcClientNotConnected
+        ),
+        _ => false,
+    }
+}
+
+/// Call `f` until it succeeds or fails with a permanent error, retrying
+/// transient bitcoin core errors at most `max_retries` times with
+/// exponential backoff.
+pub async fn retry_transient<T, F, R>(max_retries: u32, f: F) -> Result<T, Error>
+where
+    F: Fn() -> R,
+    R: Future<Output = Result<T, Error>>,
+{
+    let mut delay = RETRY_DELAY;
+    let mut retries = 0;
+    loop {
+        match f().await {
+            Err(Error::BitcoinError(err)) if is_transient(&err) => {
+                if retries >= max_retries {
+                    return Err(Error::RetriesExhausted(retries, Box::new(Error::BitcoinError(err))));
+                }
+                retries += 1;
+                tracing::warn!("Bitcoin rpc failed: {}, retrying in {:?}", err, delay);
+                sleep(delay).await;
+                delay = delay.saturating_mul(2).min(MAX_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Backing chain whose calls are retried on transient bitcoin core errors.
+pub struct RetryBacking<B> {
+    backing: B,
+    max_retries: u32,
+}
+
+impl<B> RetryBacking<B> {
+    pub fn new(backing: B, max_retries: u32) -> Self {
+        Self { backing, max_retries }
+    }
+}
+
+#[async_trait]
+impl<B: Backing + Send + Sync> Backing for RetryBacking<B> {
+    async fn get_block_count(&self) -> Result<u32, Error> {
+        retry_transient(self.max_retries, || self.backing.get_block_count()).await
+    }
+
+    async fn get_block_header(&self, height: u32) -> Result<Option<Vec<u8>>, Error> {
+        retry_transient(self.max_retries, || self.backing.get_block_header(height)).await
+    }
+
+    async fn get_block_hash(&self, height: u32) -> Result<Vec<u8>, Error> {
+        retry_transient(self.max_retries, || self.backing.get_block_hash(height)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::RpcError;
+    use std::{
+        io,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    fn transport_error() -> BitcoinError {
+        BitcoinError::BitcoinError(BitcoinCoreError::JsonRpc(JsonRpcError::Transport(Box::new(
+            io::Error::new(io::ErrorKind::ConnectionReset, "connection reset"),
+        ))))
+    }
+
+    fn rpc_error(code: i32) -> BitcoinError {
+        BitcoinError::BitcoinError(BitcoinCoreError::JsonRpc(JsonRpcError::Rpc(RpcError {
+            code,
+            message: String::new(),
+            data: None,
+        })))
+    }
+
+    #[test]
+    fn test_classify_transient_errors() {
+        assert!(is_transient(&transport_error()));
+        assert!(is_transient(&BitcoinError::ConnectionRefused));
+        assert!(is_transient(&rpc_error(BitcoinRpcError::RpcInWarmup as i32)));
+
+        assert!(!is_transient(&rpc_error(BitcoinRpcError::RpcInvalidParameter as i32)));
+        assert!(!is_transient(&rpc_error(
+            BitcoinRpcError::RpcInvalidAddressOrKey as i32
+        )));
+        assert!(!is_transient(&BitcoinError::InvalidBitcoinHeight));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_transient_errors() {
+        let calls = AtomicU32::new(0);
+        let fail_twice = || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => Err(Error::BitcoinError(transport_error())),
+                _ => Ok(42),
+            }
+        };
+        assert_eq!(retry_transient(2, fail_twice).await.unwrap(), 42);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        calls.store(0, Ordering::SeqCst);
+        assert!(matches!(
+            retry_transient(1, fail_twice).await,
+            Err(Error::RetriesExhausted(1, _))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_propagate_permanent_errors() {
+        let calls = AtomicU32::new(0);
+        let result = retry_transient(5, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err::<(), _>(Error::BitcoinError(rpc_error(
+                BitcoinRpcError::RpcInvalidParameter as i32,
+            )))
+        })
+        .await;
+        assert!(matches!(result, Err(Error::BitcoinError(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
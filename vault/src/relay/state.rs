@@ -0,0 +1,49 @@
+/// Range of backing chain blocks relayed to the issuing chain since startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayState {
+    /// Height the relay was initialized at, or of the first block relayed since startup
+    pub start_height: u32,
+    /// Height of the best block stored
+    pub best_height: u32,
+}
+
+impl RelayState {
+    /// Number of confirmations of the block at `height`, where the best block
+    /// has one. Returns `None` if the height is outside of the relayed range.
+    pub fn confirmations(&self, height: u32) -> Option<u32> {
+        if height < self.start_height {
+            return None;
+        }
+        self.best_height
+            .checked_sub(height)
+            .map(|depth| depth.saturating_add(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATE: RelayState = RelayState {
+        start_height: 100,
+        best_height: 110,
+    };
+
+    #[test]
+    fn should_count_tip_as_one_confirmation() {
+        assert_eq!(STATE.confirmations(110), Some(1));
+    }
+
+    #[test]
+    fn should_count_confirmations_of_deeper_blocks() {
+        assert_eq!(STATE.confirmations(109), Some(2));
+        assert_eq!(STATE.confirmations(100), Some(11));
+    }
+
+    #[test]
+    fn should_not_count_blocks_outside_relayed_range() {
+        assert_eq!(STATE.confirmations(111), None);
+        assert_eq!(STATE.confirmations(99), None);
+        assert_eq!(STATE.confirmations(0), None);
+    }
+}
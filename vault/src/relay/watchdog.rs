@@ -0,0 +1,89 @@
+use super::Error;
+use std::{future::Future, sync::Mutex, time::Duration};
+use tokio::time::{sleep, Instant};
+
+/// Detects a relay loop which has stopped making progress, e.g. because
+/// a future never resolves.
+#[derive(Debug)]
+pub struct Watchdog {
+    timeout: Duration,
+    last_progress: Mutex<Instant>,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            last_progress: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Report that the relay loop made progress.
+    pub fn feed(&self) {
+        if let Ok(mut last_progress) = self.last_progress.lock() {
+            *last_progress = Instant::now();
+        }
+    }
+
+    /// Time since the relay loop last made progress.
+    pub fn elapsed(&self) -> Duration {
+        self.last_progress
+            .lock()
+            .map(|last_progress| last_progress.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Resolves once no progress has been made for longer than the timeout.
+    async fn stalled(&self) {
+        loop {
+            let elapsed = self.elapsed();
+            if elapsed >= self.timeout {
+                return;
+            }
+            sleep(self.timeout - elapsed).await;
+        }
+    }
+
+    /// Run the `future` until it completes or stalls.
+    pub async fn guard<F: Future>(&self, future: F) -> Result<F::Output, Error> {
+        tokio::select! {
+            output = future => Ok(output),
+            _ = self.stalled() => Err(Error::Stalled(self.elapsed())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn should_fire_after_threshold() {
+        let start = Instant::now();
+        let watchdog = Watchdog::new(Duration::from_secs(60));
+
+        let result = watchdog.guard(futures::future::pending::<()>()).await;
+        assert_eq!(result, Err(Error::Stalled(Duration::from_secs(60))));
+        assert_eq!(start.elapsed(), Duration::from_secs(60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_not_fire_while_making_progress() {
+        let start = Instant::now();
+        let watchdog = Watchdog::new(Duration::from_secs(60));
+
+        let progress = async {
+            for _ in 0..5 {
+                sleep(Duration::from_secs(30)).await;
+                watchdog.feed();
+            }
+        };
+        assert_eq!(watchdog.guard(progress).await, Ok(()));
+        assert_eq!(start.elapsed(), Duration::from_secs(150));
+
+        // stops making progress after the last feed
+        let result = watchdog.guard(futures::future::pending::<()>()).await;
+        assert!(result.is_err());
+        assert_eq!(start.elapsed(), Duration::from_secs(210));
+    }
+}
@@ -3,12 +3,12 @@ use crate::{
     error::Error,
     faucet, issue,
     metrics::{poll_metrics, publish_tokio_metrics, PerCurrencyMetrics},
-    relay::run_relayer,
+    relay::{self, run_relayer, RetryBacking, RELAY_CONTROL},
     service::*,
     Event, IssueRequests, CHAIN_HEIGHT_POLLING_INTERVAL,
 };
 use async_trait::async_trait;
-use bitcoin::{Error as BitcoinError, Network, PublicKey};
+use bitcoin::{ConfirmationPolicy, Error as BitcoinError, Network, PublicKey};
 use clap::Parser;
 use futures::{
     channel::{mpsc, mpsc::Sender},
@@ -51,6 +51,13 @@ fn parse_collateral_and_amount(
     ))
 }
 
+fn parse_amount_and_confirmations(s: &str) -> Result<(u128, u32), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let pos = s
+        .find('=')
+        .ok_or_else(|| format!("invalid amount=confirmations: no `=` found in `{}`", s))?;
+    Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct VaultServiceConfig {
     /// Automatically register the vault with the given amount of collateral and a newly generated address.
@@ -77,6 +84,11 @@ pub struct VaultServiceConfig {
     #[clap(long)]
     pub no_random_delay: bool,
 
+    /// What to do with a deposit whose OP_RETURN id names another issue: `ignore`, `warn`,
+    /// or `skip` to not execute the issue.
+    #[clap(long, default_value = "warn")]
+    pub op_return_mismatch: OpReturnMismatch,
+
     /// Timeout in milliseconds to repeat collateralization checks.
     #[clap(long, value_parser = parse_duration_ms, default_value = "5000")]
     pub collateral_timeout_ms: Duration,
@@ -86,6 +98,11 @@ pub struct VaultServiceConfig {
     #[clap(long)]
     pub btc_confirmations: Option<u32>,
 
+    /// Wait for more bitcoin confirmations before executing issues with deposits of at least
+    /// the given amount (in satoshis), e.g. `100000000=6`. May be given multiple times.
+    #[clap(long, value_parser = parse_amount_and_confirmations)]
+    pub issue_confirmations: Vec<(u128, u32)>,
+
     /// Minimum time to the the redeem/replace execution deadline to make the bitcoin payment.
     #[clap(long, value_parser = parse_duration_minutes, default_value = "120")]
     pub payment_margin_minutes: Duration,
@@ -107,10 +124,27 @@ pub struct VaultServiceConfig {
     #[clap(long, default_value = "0")]
     pub bitcoin_relay_confirmations: u32,
 
+    /// Retry transient bitcoin rpc errors of the relayer this many times before giving up.
+    #[clap(long, default_value = "5")]
+    pub bitcoin_relay_rpc_retries: u32,
+
+    /// Restart the relayer if it makes no progress for this many milliseconds.
+    #[clap(long, value_parser = parse_duration_ms)]
+    pub bitcoin_relay_stall_timeout_ms: Option<Duration>,
+
+    /// Report the relayer as degraded if it has not been up to date for this many minutes,
+    /// even though bitcoin blocks are pending.
+    #[clap(long, value_parser = parse_duration_minutes)]
+    pub bitcoin_relay_max_time_lag_minutes: Option<Duration>,
+
     /// Don't relay bitcoin block headers.
     #[clap(long)]
     pub no_bitcoin_block_relay: bool,
 
+    /// Log the bitcoin block headers which would be relayed instead of submitting them.
+    #[clap(long)]
+    pub bitcoin_relay_dry_run: bool,
+
     /// Deprecated - kept only to not break clients.
     #[clap(long)]
     pub no_auto_refund: bool,
@@ -607,6 +641,14 @@ impl VaultService {
         let listen_for_fee_rate_estimate_changes =
             |rpc: InterBtcParachain| async move { rpc.listen_for_fee_rate_changes().await };
 
+        let reconnect_parachain = {
+            let btc_parachain = self.btc_parachain.clone();
+            move || {
+                let btc_parachain = btc_parachain.clone();
+                async move { btc_parachain.reconnect().await.map_err(relay::Error::from) }
+            }
+        };
+
         tracing::info!("Starting all services...");
         let tasks = vec![
             (
@@ -720,17 +762,29 @@ impl VaultService {
                 "Bitcoin Relay",
                 maybe_run(
                     !self.config.no_bitcoin_block_relay,
-                    run_relayer(Runner::new(
-                        self.btc_rpc_master_wallet.clone(),
-                        self.btc_parachain.clone(),
-                        Config {
-                            start_height: self.config.bitcoin_relay_start_height,
-                            max_batch_size: self.config.max_batch_size,
-                            interval: Some(self.config.bitcoin_poll_interval_ms),
-                            btc_confirmations: self.config.bitcoin_relay_confirmations,
-                        },
-                        random_delay.clone(),
-                    )),
+                    run_relayer(
+                        Runner::new(
+                            RetryBacking::new(
+                                self.btc_rpc_master_wallet.clone(),
+                                self.config.bitcoin_relay_rpc_retries,
+                            ),
+                            self.btc_parachain.clone(),
+                            Config {
+                                start_height: self.config.bitcoin_relay_start_height,
+                                max_batch_size: self.config.max_batch_size,
+                                interval: Some(self.config.bitcoin_poll_interval_ms),
+                                btc_confirmations: self.config.bitcoin_relay_confirmations,
+                                stall_timeout: self.config.bitcoin_relay_stall_timeout_ms,
+                                // already validated against the parachain on startup
+                                network: Some(self.btc_rpc_master_wallet.network()),
+                                control: Some(RELAY_CONTROL.clone()),
+                                max_time_lag: self.config.bitcoin_relay_max_time_lag_minutes,
+                                dry_run: self.config.bitcoin_relay_dry_run,
+                            },
+                            random_delay.clone(),
+                        ),
+                        reconnect_parachain,
+                    ),
                 ),
             ),
             (
@@ -743,6 +797,8 @@ impl VaultService {
                         issue_set.clone(),
                         oldest_issue_btc_height,
                         num_confirmations,
+                        ConfirmationPolicy::new(num_confirmations, self.config.issue_confirmations.clone()),
+                        self.config.op_return_mismatch,
                         random_delay,
                     ),
                 ),
@@ -629,6 +629,8 @@ async fn test_automatic_issue_execution_succeeds() {
                 issue_set.clone(),
                 1,
                 0,
+                bitcoin::ConfirmationPolicy::new(0, vec![]),
+                vault::service::OpReturnMismatch::Warn,
                 Arc::new(Box::new(ZeroDelay)),
             ),
             periodically_produce_blocks(vault2_provider.clone()),
@@ -719,6 +721,8 @@ async fn test_automatic_issue_execution_succeeds_with_big_transaction() {
                 issue_set.clone(),
                 1,
                 0,
+                bitcoin::ConfirmationPolicy::new(0, vec![]),
+                vault::service::OpReturnMismatch::Warn,
                 Arc::new(Box::new(ZeroDelay)),
             ),
             periodically_produce_blocks(vault2_provider.clone()),
@@ -1104,7 +1108,7 @@ mod test_with_bitcoind {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn test_automatic_rbf_succeeds() {
-        use vault::relay::run_relayer;
+        use vault::relay::{self, run_relayer, RetryBacking};
 
         test_with_vault(|client, vault_id, vault_provider| async move {
             let relayer_provider = setup_provider(client.clone(), AccountKeyring::Bob).await;
@@ -1115,7 +1119,7 @@ mod test_with_bitcoind {
 
             let height = bitcoin_core.get_block_count().await.unwrap() as u32;
             let relayer = Runner::new(
-                btc_rpc.clone(),
+                RetryBacking::new(btc_rpc.clone(), 0),
                 user_provider.clone(),
                 Config {
                     start_height: Some(max(1, height.saturating_sub(200))), /* important to skip the genesis block
@@ -1123,7 +1127,7 @@ mod test_with_bitcoind {
                                                                              * get rejected */
                     max_batch_size: 256,
                     interval: Some(std::time::Duration::from_secs(1)),
-                    btc_confirmations: 0,
+                    ..Default::default()
                 },
                 Arc::new(Box::new(ZeroDelay)),
             );
@@ -1131,7 +1135,17 @@ mod test_with_bitcoind {
             tracing::trace!("Initializing relay");
             relayer.submit_next().await.unwrap(); // make sure the relay is initialized
 
-            let parachain_miner = join(run_relayer(relayer), periodically_produce_blocks(user_provider.clone()));
+            let reconnect_parachain = {
+                let user_provider = user_provider.clone();
+                move || {
+                    let user_provider = user_provider.clone();
+                    async move { user_provider.reconnect().await.map_err(relay::Error::from) }
+                }
+            };
+            let parachain_miner = join(
+                run_relayer(relayer, reconnect_parachain),
+                periodically_produce_blocks(user_provider.clone()),
+            );
             tokio::spawn(parachain_miner);
 
             // setup vault id manager